@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+"""Greedy bot for the bot protocol of rust-2048, see `src/bot.rs`.
+
+It plays the action which scores the most points, then keeps the most empty cells, on square
+boards with the classic ruleset. Run it with:
+
+    cargo run --release -- bot "python3 bots/greedy.py" --games 10
+"""
+import sys
+
+# cells of each line from the front to the back of the movement
+LINES = {
+    "left": [[row * 4 + col for col in range(4)] for row in range(4)],
+    "right": [[row * 4 + col for col in reversed(range(4))] for row in range(4)],
+    "up": [[row * 4 + col for row in range(4)] for col in range(4)],
+    "down": [[row * 4 + col for row in reversed(range(4))] for col in range(4)],
+}
+
+
+def parse(grid):
+    """Cells of the board: numbers, 0 when empty and None for stones and special tiles"""
+    cells = []
+    for row in grid.split("/"):
+        for cell in row.split(","):
+            cells.append(0 if cell == "-" else int(cell) if cell.isdigit() else None)
+    return cells
+
+
+def slide(cells, action):
+    """Board after `action` with its points, tiles slide up to stones and merge once"""
+    after, points = list(cells), 0
+    for line in LINES[action]:
+        front, last = 0, None
+        for i, cell in enumerate(line):
+            value = cells[cell]
+            if value is None:
+                front, last = i + 1, None
+            elif value:
+                after[cell] = 0
+                if value == last:
+                    after[line[front - 1]] = 2 * value
+                    points += 2 * value
+                    last = None
+                else:
+                    after[line[front]] = value
+                    last = value
+                    front += 1
+    return after, points
+
+
+def choose(cells, actions):
+    best = None
+    for action in actions:
+        if action not in LINES:
+            continue
+        after, points = slide(cells, action)
+        if after == cells:
+            continue
+        rating = (points, after.count(0))
+        if best is None or rating > best[0]:
+            best = (rating, action)
+    return best[1] if best else actions[0]
+
+
+def main():
+    actions = []
+    for line in sys.stdin:
+        words = line.split()
+        if not words:
+            continue
+        if words[0] == "2048":
+            print("ready greedy", flush=True)
+        elif words[0] == "game":
+            actions = words[4].split(",")
+        elif words[0] == "board":
+            print(choose(parse(words[1]), actions), flush=True)
+        elif words[0] == "end":
+            print("# game ended with", " ".join(words[1:]), file=sys.stderr)
+        elif words[0] == "quit":
+            break
+
+
+if __name__ == "__main__":
+    main()
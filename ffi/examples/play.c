@@ -0,0 +1,40 @@
+/*
+ * Plays a game with a fixed preference of directions and prints the final board.
+ *
+ *     cargo build --release
+ *     cc examples/play.c -Iinclude target/release/libgame2048.a -lpthread -ldl -lm -o play
+ *     ./play 42
+ */
+#include <inttypes.h>
+#include <stdio.h>
+
+#include "game2048.h"
+
+int main(int argc, char **argv) {
+    if (game2048_abi_version() != GAME2048_ABI_VERSION) {
+        fprintf(stderr, "Library and header versions differ\n");
+        return 2;
+    }
+    uint64_t seed = argc > 1 ? strtoull(argv[1], NULL, 10) : 0;
+    const int8_t preference[] = {GAME2048_DOWN, GAME2048_LEFT, GAME2048_RIGHT, GAME2048_UP};
+
+    Game2048 *game = game2048_new(seed);
+    while (!game2048_is_gameover(game)) {
+        for (size_t i = 0; i < sizeof(preference); i++) {
+            if (game2048_move(game, preference[i])) {
+                break;
+            }
+        }
+    }
+
+    uint32_t grid[GAME2048_GRID_SIZE];
+    game2048_grid(game, grid);
+    for (size_t cell = 0; cell < GAME2048_GRID_SIZE; cell++) {
+        printf("%5" PRIu32 "%s", grid[cell], cell % 4 == 3 ? "\n" : " ");
+    }
+    printf("seed %" PRIu64 " score %" PRIu32 " max tile %" PRIu32 " moves %" PRIu32 "\n",
+           game2048_seed(game), game2048_score(game), game2048_max_tile(game),
+           game2048_moves(game));
+    game2048_free(game);
+    return 0;
+}
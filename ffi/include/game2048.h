@@ -0,0 +1,138 @@
+#ifndef GAME2048_H
+#define GAME2048_H
+
+/* Generated with cbindgen from src/lib.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Version of the API, changed whenever a function changes
+ */
+#define GAME2048_ABI_VERSION 1
+
+/**
+ * Number of cells of the grid
+ */
+#define GAME2048_GRID_SIZE 16
+
+#define GAME2048_UP -4
+
+#define GAME2048_DOWN 4
+
+#define GAME2048_LEFT -1
+
+#define GAME2048_RIGHT 1
+
+/**
+ * Classic game played through the C API
+ */
+typedef struct Game2048 Game2048;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Version of the API of the library
+ */
+uint32_t game2048_abi_version(void);
+
+/**
+ * Creates a classic game whose spawns are fully determined by `seed`, to be destroyed with
+ * `game2048_free`
+ */
+Game2048 *game2048_new(uint64_t seed);
+
+/**
+ * Destroys a game created by `game2048_new`
+ *
+ * # Safety
+ *
+ * `game` is null or a game created by `game2048_new` which is not used afterwards.
+ */
+void game2048_free(Game2048 *game);
+
+/**
+ * Checks if `action` moves tiles
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+bool game2048_can_move(const Game2048 *game, int8_t action);
+
+/**
+ * Plays `action` then spawns tiles, returns `false` without changing the game if the action
+ * does not move tiles
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+bool game2048_move(Game2048 *game, int8_t action);
+
+/**
+ * Writes the values of the `GAME2048_GRID_SIZE` cells row by row in `grid`, `0` for an empty
+ * cell
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`, `grid` is null or points to
+ * `GAME2048_GRID_SIZE` writable values.
+ */
+void game2048_grid(const Game2048 *game, uint32_t *grid);
+
+/**
+ * Score of the game, `0` for a null game
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+uint32_t game2048_score(const Game2048 *game);
+
+/**
+ * Number of moves played, `0` for a null game
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+uint32_t game2048_moves(const Game2048 *game);
+
+/**
+ * Highest tile of the grid, `0` for a null game
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+uint32_t game2048_max_tile(const Game2048 *game);
+
+/**
+ * Seed of the spawns of the game, `0` for a null game
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+uint64_t game2048_seed(const Game2048 *game);
+
+/**
+ * Checks if no action moves tiles anymore, `true` for a null game
+ *
+ * # Safety
+ *
+ * `game` is null or a live game created by `game2048_new`.
+ */
+bool game2048_is_gameover(const Game2048 *game);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GAME2048_H */
@@ -0,0 +1,196 @@
+//! C API of the engine of the game, declared in `include/game2048.h`
+//!
+//! A game is an opaque `Game2048` created by `game2048_new` and destroyed by `game2048_free`.
+//! Every function accepts a null game and does nothing with it. Actions are the offsets of
+//! `rust_2048::topology::Topology`: `-4` (up), `4` (down), `-1` (left) and `1` (right).
+//!
+//! The API is stable: functions are only added, `game2048_abi_version` changes if one ever
+//! changes.
+use rust_2048::game::Game;
+
+/// Version of the API, changed whenever a function changes
+pub const GAME2048_ABI_VERSION: u32 = 1;
+/// Number of cells of the grid
+pub const GAME2048_GRID_SIZE: usize = 16;
+pub const GAME2048_UP: i8 = -4;
+pub const GAME2048_DOWN: i8 = 4;
+pub const GAME2048_LEFT: i8 = -1;
+pub const GAME2048_RIGHT: i8 = 1;
+
+/// Classic game played through the C API
+pub struct Game2048 {
+    game: Game,
+}
+
+/// Version of the API of the library
+#[no_mangle]
+pub extern "C" fn game2048_abi_version() -> u32 {
+    GAME2048_ABI_VERSION
+}
+
+/// Creates a classic game whose spawns are fully determined by `seed`, to be destroyed with
+/// `game2048_free`
+#[no_mangle]
+pub extern "C" fn game2048_new(seed: u64) -> *mut Game2048 {
+    Box::into_raw(Box::new(Game2048 {
+        game: Game::from_seed(seed),
+    }))
+}
+
+/// Destroys a game created by `game2048_new`
+///
+/// # Safety
+///
+/// `game` is null or a game created by `game2048_new` which is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_free(game: *mut Game2048) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Checks if `action` moves tiles
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_can_move(game: *const Game2048, action: i8) -> bool {
+    game.as_ref().is_some_and(|game| {
+        game.game.topology().actions().contains(&action) && game.game.partial_move(action)
+    })
+}
+
+/// Plays `action` then spawns tiles, returns `false` without changing the game if the action
+/// does not move tiles
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_move(game: *mut Game2048, action: i8) -> bool {
+    if !game2048_can_move(game, action) {
+        return false;
+    }
+    let game = &mut (*game).game;
+    game.action(action);
+    game.random();
+    true
+}
+
+/// Writes the values of the `GAME2048_GRID_SIZE` cells row by row in `grid`, `0` for an empty
+/// cell
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`, `grid` is null or points to
+/// `GAME2048_GRID_SIZE` writable values.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_grid(game: *const Game2048, grid: *mut u32) {
+    if let (Some(game), false) = (game.as_ref(), grid.is_null()) {
+        let cells = game.game.copy_grid();
+        grid.copy_from_nonoverlapping(cells.as_ptr(), GAME2048_GRID_SIZE);
+    }
+}
+
+/// Score of the game, `0` for a null game
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_score(game: *const Game2048) -> u32 {
+    game.as_ref().map_or(0, |game| game.game.score())
+}
+
+/// Number of moves played, `0` for a null game
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_moves(game: *const Game2048) -> u32 {
+    game.as_ref().map_or(0, |game| game.game.moves())
+}
+
+/// Highest tile of the grid, `0` for a null game
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_max_tile(game: *const Game2048) -> u32 {
+    game.as_ref().map_or(0, |game| game.game.max_tile())
+}
+
+/// Seed of the spawns of the game, `0` for a null game
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_seed(game: *const Game2048) -> u64 {
+    game.as_ref().map_or(0, |game| game.game.seed())
+}
+
+/// Checks if no action moves tiles anymore, `true` for a null game
+///
+/// # Safety
+///
+/// `game` is null or a live game created by `game2048_new`.
+#[no_mangle]
+pub unsafe extern "C" fn game2048_is_gameover(game: *const Game2048) -> bool {
+    game.as_ref().is_none_or(|game| game.game.is_gameover())
+}
+
+#[cfg(test)]
+mod test_ffi {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn seeded_games() {
+        unsafe {
+            let first = game2048_new(42);
+            let second = game2048_new(42);
+            assert_eq!(game2048_seed(first), 42);
+            let mut moves = 0;
+            while !game2048_is_gameover(first) {
+                let action = [GAME2048_DOWN, GAME2048_LEFT, GAME2048_RIGHT, GAME2048_UP]
+                    .into_iter()
+                    .find(|&action| game2048_can_move(first, action))
+                    .unwrap();
+                assert!(game2048_move(first, action));
+                assert!(game2048_move(second, action));
+                moves += 1;
+            }
+            assert!(!game2048_move(first, GAME2048_UP));
+            let mut grids = [[0; GAME2048_GRID_SIZE]; 2];
+            game2048_grid(first, grids[0].as_mut_ptr());
+            game2048_grid(second, grids[1].as_mut_ptr());
+            assert_eq!(grids[0], grids[1]);
+            assert_eq!(game2048_moves(first), moves);
+            assert_eq!(game2048_score(first), game2048_score(second));
+            assert_eq!(game2048_max_tile(first), *grids[0].iter().max().unwrap());
+            game2048_free(first);
+            game2048_free(second);
+        }
+    }
+
+    #[test]
+    fn null_games() {
+        unsafe {
+            let mut grid = [7; GAME2048_GRID_SIZE];
+            game2048_grid(ptr::null(), grid.as_mut_ptr());
+            assert_eq!(grid, [7; GAME2048_GRID_SIZE]);
+            assert!(!game2048_move(ptr::null_mut(), GAME2048_LEFT));
+            assert_eq!(game2048_score(ptr::null()), 0);
+            assert!(game2048_is_gameover(ptr::null()));
+            game2048_free(ptr::null_mut());
+            let game = game2048_new(1);
+            game2048_grid(game, ptr::null_mut());
+            assert!(!game2048_move(game, 3));
+            game2048_free(game);
+        }
+    }
+}
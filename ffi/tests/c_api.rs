@@ -0,0 +1,55 @@
+//! Builds `examples/play.c` against the static library and checks it plays like the engine
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use rust_2048::game::Game;
+
+#[test]
+fn c_example() {
+    // the test binary is in `target/<profile>/deps`, where `cargo test` builds the library, and
+    // `cargo build` copies it to `target/<profile>`
+    let exe = env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let library = [deps, deps.parent().unwrap()]
+        .map(|dir| dir.join("libgame2048.a"))
+        .into_iter()
+        .find(|library| library.exists())
+        .expect("Static library not found");
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("play");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .arg(root.join("examples/play.c"))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg(library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .expect("C compiler not found");
+    assert!(status.success());
+
+    let output = Command::new(&program).arg("42").output().unwrap();
+    assert!(output.status.success());
+    let output = String::from_utf8(output.stdout).unwrap();
+
+    let mut game = Game::from_seed(42);
+    while !game.is_gameover() {
+        let action = [4, -1, 1, -4]
+            .into_iter()
+            .find(|&action| game.partial_move(action))
+            .unwrap();
+        game.action(action);
+        game.random();
+    }
+    let summary = format!(
+        "seed 42 score {} max tile {} moves {}",
+        game.score(),
+        game.max_tile(),
+        game.moves()
+    );
+    assert!(output.ends_with(&format!("{summary}\n")), "{output}");
+    assert_eq!(output.lines().count(), 5);
+}
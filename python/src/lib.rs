@@ -0,0 +1,214 @@
+//! Python module `game2048`, bindings of the engine of the game
+//!
+//! Actions are the offsets of `rust_2048::topology::Topology`: `-4` (up), `4` (down), `-1`
+//! (left), `1` (right) and the diagonals on other boards.
+use numpy::{PyArray1, PyArray2, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use rust_2048::game::{self, Game, SpawnRules, Variant};
+use rust_2048::ruleset::Ruleset;
+use rust_2048::solver::{self, Solver};
+use rust_2048::topology::Topology;
+
+/// Variant named `ruleset` and `topology`
+fn variant(ruleset: &str, topology: &str) -> PyResult<Variant> {
+    let ruleset = Ruleset::from_name(ruleset)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown ruleset `{ruleset}`")))?;
+    let topology = Topology::from_name(topology)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown topology `{topology}`")))?;
+    Ok(Variant { ruleset, topology })
+}
+
+/// Game following the rules of the Rust game
+#[pyclass(name = "Game", module = "game2048")]
+#[derive(Clone)]
+struct PyGame {
+    game: Game,
+}
+
+impl PyGame {
+    /// Checks that `action` moves tiles
+    fn check(&self, action: i8) -> PyResult<()> {
+        if !self.game.topology().actions().contains(&action) || !self.game.partial_move(action) {
+            return Err(PyValueError::new_err(format!(
+                "Action {action} is not possible"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyGame {
+    /// Creates a game, its spawns are fully determined by `seed` (random if it is not given)
+    ///
+    /// `rules` is a preset of spawn rules: `original`, `hard` or `double`.
+    #[new]
+    #[pyo3(signature = (seed=None, ruleset="classic", topology="square", rules="original"))]
+    fn new(seed: Option<u64>, ruleset: &str, topology: &str, rules: &str) -> PyResult<PyGame> {
+        let variant = variant(ruleset, topology)?;
+        let rules = SpawnRules::preset(rules)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown spawn rules `{rules}`")))?;
+        let game = match seed {
+            Some(seed) => Game::with_variant(seed, variant, rules),
+            None => Game::init_first_elements(variant, rules),
+        };
+        Ok(PyGame { game })
+    }
+
+    /// Reads a game written in text notation, see `str(game)`
+    #[staticmethod]
+    fn from_notation(notation: &str) -> PyResult<PyGame> {
+        let game = notation.parse().map_err(PyValueError::new_err)?;
+        Ok(PyGame { game })
+    }
+
+    fn __str__(&self) -> String {
+        self.game.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game.from_notation('{}')", self.game)
+    }
+
+    /// Independent copy of the game
+    fn copy(&self) -> PyGame {
+        self.clone()
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.game.seed()
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.game.score()
+    }
+
+    #[getter]
+    fn moves(&self) -> u32 {
+        self.game.moves()
+    }
+
+    #[getter]
+    fn max_tile(&self) -> u32 {
+        self.game.max_tile()
+    }
+
+    #[getter]
+    fn ruleset(&self) -> &'static str {
+        self.game.ruleset().name()
+    }
+
+    #[getter]
+    fn topology(&self) -> &'static str {
+        self.game.topology().name()
+    }
+
+    /// Values of the 16 cells row by row, special tiles are `STONE`, `WILDCARD` and `BOMB`
+    #[getter]
+    fn grid(&self) -> Vec<u32> {
+        self.game.copy_grid().to_vec()
+    }
+
+    /// Grid as a 4 x 4 numpy array of `uint32`
+    fn board<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u32>>> {
+        PyArray1::from_slice_bound(py, &self.game.copy_grid()).reshape([4, 4])
+    }
+
+    /// Every action of the board
+    fn actions(&self) -> Vec<i8> {
+        self.game.topology().actions().to_vec()
+    }
+
+    /// Actions which move tiles
+    fn legal_actions(&self) -> Vec<i8> {
+        (self.game.topology().actions().iter())
+            .copied()
+            .filter(|&action| self.game.partial_move(action))
+            .collect()
+    }
+
+    /// Checks if `action` moves tiles
+    fn can_move(&self, action: i8) -> bool {
+        self.check(action).is_ok()
+    }
+
+    /// Moves the tiles without spawning and returns the points scored, raises `ValueError` if
+    /// the action does not move tiles
+    #[pyo3(name = "move")]
+    fn move_tiles(&mut self, action: i8) -> PyResult<u32> {
+        self.check(action)?;
+        let score = self.game.score();
+        self.game.action(action);
+        Ok(self.game.score() - score)
+    }
+
+    /// Spawns tiles following the spawn rules of the game
+    fn spawn(&mut self) {
+        self.game.random();
+    }
+
+    /// Moves the tiles then spawns, returns the points scored
+    fn play(&mut self, action: i8) -> PyResult<u32> {
+        let points = self.move_tiles(action)?;
+        self.game.random();
+        Ok(points)
+    }
+
+    fn is_gameover(&self) -> bool {
+        self.game.is_gameover()
+    }
+
+    /// Rating of the board used by the solver, the higher the better
+    fn heuristic(&self) -> f32 {
+        self.game.heuristic()
+    }
+}
+
+/// Expectimax solver of the Rust game
+#[pyclass(name = "Solver", module = "game2048")]
+struct PySolver {
+    solver: Solver,
+}
+
+#[pymethods]
+impl PySolver {
+    /// Creates a solver looking `depth` actions ahead
+    #[new]
+    #[pyo3(signature = (depth=solver::DEFAULT_DEPTH))]
+    fn new(depth: u32) -> PySolver {
+        PySolver {
+            solver: Solver::new(depth),
+        }
+    }
+
+    #[getter]
+    fn depth(&self) -> u32 {
+        self.solver.depth
+    }
+
+    /// Expected rating of every legal action of `game`, as `(action, rating)` pairs
+    fn evaluate(&self, game: &PyGame) -> Vec<(i8, f32)> {
+        self.solver.evaluate(&game.game)
+    }
+
+    /// Action with the best rating, `None` if the game is over
+    fn best_action(&self, game: &PyGame) -> Option<i8> {
+        self.solver.best_action(&game.game)
+    }
+}
+
+#[pymodule]
+fn game2048(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PySolver>()?;
+    module.add("ACTIONS", game::ACTIONS.to_vec())?;
+    module.add("WIN_TILE", game::WIN_TILE)?;
+    module.add("STONE", game::STONE)?;
+    module.add("WILDCARD", game::WILDCARD)?;
+    module.add("BOMB", game::BOMB)?;
+    Ok(())
+}
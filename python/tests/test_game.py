@@ -0,0 +1,54 @@
+import numpy as np
+import pytest
+
+import game2048
+from game2048 import Game, Solver
+
+
+def test_seeded_games():
+    first, second = Game(seed=42), Game(seed=42)
+    assert first.grid == second.grid
+    assert first.seed == 42
+    assert sum(value != 0 for value in first.grid) == 2
+    action = first.legal_actions()[0]
+    first.play(action)
+    second.play(action)
+    assert first.grid == second.grid
+    assert first.moves == 1
+
+
+def test_moves_and_board():
+    game = Game.from_notation("2,2,-,-/-,-,-,-/-,-,-,-/4,-,-,# 0 0 1")
+    assert game.legal_actions() == [-4, 4, -1, 1]
+    assert game.move(-1) == 4
+    board = game.board()
+    assert board.shape == (4, 4)
+    assert board.dtype == np.uint32
+    assert board[0, 0] == 4
+    assert board[3, 3] == game2048.STONE
+    game.spawn()
+    assert np.count_nonzero(game.board()) == 4
+    copy = Game.from_notation(str(game))
+    assert copy.grid == game.grid
+
+
+def test_invalid_input():
+    game = Game.from_notation("2,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1")
+    assert not game.can_move(-1)
+    with pytest.raises(ValueError):
+        game.move(-1)
+    with pytest.raises(ValueError):
+        Game(ruleset="unknown")
+    with pytest.raises(ValueError):
+        Game.from_notation("2,2")
+
+
+def test_solver():
+    game = Game(seed=3, topology="hexagonal")
+    solver = Solver(depth=1)
+    ratings = dict(solver.evaluate(game))
+    assert set(ratings) == set(game.legal_actions())
+    while (action := solver.best_action(game)) is not None:
+        game.play(action)
+    assert game.is_gameover()
+    assert game.max_tile >= 64
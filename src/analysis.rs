@@ -0,0 +1,224 @@
+//! Analysis of a recorded game for coaching
+//!
+//! Each position of a replay is rated by the solver for every action which moves tiles, the
+//! played action is compared with the best one and flagged as a blunder when it loses too much of
+//! its rating.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{Game, Variant};
+use crate::replay::{Replay, ReplayError};
+use crate::solver::{self, Solver};
+
+/// Share of the rating of the best action lost by the played one above which it is a blunder
+pub const BLUNDER_LOSS: f32 = 0.1;
+
+/// Ratings of the actions of one position of the game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveAnalysis {
+    /// Position before the move in text notation, it can be pasted in the game to try it again
+    pub position: String,
+    pub played: i8,
+    /// Expected rating of every action which moves tiles, see `Solver::evaluate`
+    pub ratings: Vec<(i8, f32)>,
+    pub best: i8,
+    /// Share of the rating of the best action lost by the played one, from `0` to `1`
+    pub loss: f32,
+    pub blunder: bool,
+}
+
+impl MoveAnalysis {
+    /// Rating of the played action relative to the best one, from `0` to `1`
+    pub fn accuracy(&self) -> f32 {
+        1. - self.loss
+    }
+}
+
+/// Report of the analysis of a game, written as text by `Display` or as JSON by `save`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    pub seed: u64,
+    pub variant: Variant,
+    /// Depth of the solver which rated the actions
+    pub depth: u32,
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: Vec<MoveAnalysis>,
+}
+
+impl Analysis {
+    /// Replays `replay` and rates the actions of every position with `solver`
+    ///
+    /// The claimed result is not checked, see `Replay::verify`.
+    pub fn new(replay: &Replay, solver: &Solver) -> Result<Analysis, ReplayError> {
+        let mut game = Game::with_variant(replay.seed, replay.variant, replay.rules.clone());
+        let mut moves = Vec::with_capacity(replay.actions.len());
+        for (index, &action) in replay.actions.iter().enumerate() {
+            // the solver only rates actions which move tiles
+            let ratings = solver.evaluate(&game);
+            let played = ratings.iter().find(|&&(rated, _)| rated == action);
+            let (Some(&(_, played)), Some((best, best_rating))) =
+                (played, solver::best_rated(&ratings))
+            else {
+                return Err(ReplayError::IllegalAction { index, action });
+            };
+            let loss = if best_rating > 0. {
+                (best_rating - played) / best_rating
+            } else {
+                0.
+            };
+            moves.push(MoveAnalysis {
+                position: game.to_string(),
+                played: action,
+                ratings,
+                best,
+                loss,
+                blunder: loss > BLUNDER_LOSS,
+            });
+            game.action(action);
+            game.random();
+        }
+        Ok(Analysis {
+            seed: replay.seed,
+            variant: replay.variant,
+            depth: solver.depth,
+            score: game.score(),
+            max_tile: game.max_tile(),
+            moves,
+        })
+    }
+
+    /// Moves flagged as blunders with their index
+    pub fn blunders(&self) -> impl Iterator<Item = (usize, &MoveAnalysis)> {
+        (self.moves.iter().enumerate()).filter(|(_, analysis)| analysis.blunder)
+    }
+
+    /// Saves the report as JSON at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+    }
+}
+
+impl fmt::Display for Analysis {
+    /// One line per move: the played and best actions, the loss and the ratings of the actions,
+    /// blunders are followed by their position
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let topology = self.variant.topology;
+        writeln!(
+            f,
+            "Seed {}, {} {}, depth {}: score {}, max tile {}, {} moves, {} blunders",
+            self.seed,
+            self.variant.ruleset.name(),
+            topology.name(),
+            self.depth,
+            self.score,
+            self.max_tile,
+            self.moves.len(),
+            self.blunders().count()
+        )?;
+        for (index, analysis) in self.moves.iter().enumerate() {
+            let ratings: Vec<String> = (analysis.ratings.iter())
+                .map(|&(action, rating)| format!("{} {rating:.1}", topology.action_name(action)))
+                .collect();
+            write!(
+                f,
+                "{:>5}. {:<10} best {:<10} loss {:>3.0}%  [{}]",
+                index + 1,
+                topology.action_name(analysis.played),
+                topology.action_name(analysis.best),
+                analysis.loss * 100.,
+                ratings.join(", ")
+            )?;
+            if analysis.blunder {
+                write!(f, "  BLUNDER\n       {}", analysis.position)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_analysis {
+    use super::*;
+    use crate::game::{SpawnRules, ACTIONS};
+
+    /// Replay of a game of `count` moves from the seed `seed`, choosing the worst action every
+    /// `worst` moves and the best one otherwise
+    fn played(seed: u64, count: usize, worst: usize) -> Replay {
+        let solver = Solver::new(1);
+        let mut game = Game::from_seed(seed);
+        while (game.moves() as usize) < count && !game.is_gameover() {
+            let ratings = solver.evaluate(&game);
+            let action = if (game.moves() as usize + 1).is_multiple_of(worst) {
+                (ratings.iter())
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap()
+                    .0
+            } else {
+                solver::best_rated(&ratings).unwrap().0
+            };
+            game.action(action);
+            game.random();
+        }
+        Replay::from_game(&game)
+    }
+
+    #[test]
+    fn blunders() {
+        let replay = played(5, 60, 20);
+        let analysis = Analysis::new(&replay, &Solver::new(1)).unwrap();
+        assert_eq!(analysis.moves.len(), 60);
+        assert_eq!(
+            (analysis.score, analysis.max_tile),
+            (replay.score, replay.max_tile)
+        );
+        for (index, analysis) in analysis.moves.iter().enumerate() {
+            assert_eq!(analysis.played, replay.actions[index]);
+            assert!((0. ..=1.).contains(&analysis.loss));
+            assert!(analysis.ratings.len() <= ACTIONS.len());
+            if (index + 1) % 20 != 0 {
+                assert_eq!(analysis.loss, 0.);
+            }
+        }
+        let blunders: Vec<usize> = analysis.blunders().map(|(index, _)| index).collect();
+        assert!(!blunders.is_empty());
+        assert!(blunders.iter().all(|index| (index + 1) % 20 == 0));
+        // the position of a move can be played again from the notation
+        let position: Game = analysis.moves[10].position.parse().unwrap();
+        let game = replay.replay().unwrap();
+        assert_eq!(position.moves(), 10);
+        assert_eq!(position.seed(), game.seed());
+
+        let report = analysis.to_string();
+        assert_eq!(report.lines().count(), 1 + 60 + analysis.blunders().count());
+        assert!(report.starts_with("Seed 5, classic square, depth 1: score "));
+        let json = serde_json::to_string(&analysis).unwrap();
+        assert_eq!(serde_json::from_str::<Analysis>(&json).unwrap(), analysis);
+    }
+
+    #[test]
+    fn illegal_replay() {
+        let mut replay = played(5, 10, 100);
+        replay.actions.insert(3, 7);
+        assert_eq!(
+            Analysis::new(&replay, &Solver::new(1)),
+            Err(ReplayError::IllegalAction {
+                index: 3,
+                action: 7
+            })
+        );
+        let replay = Replay::from_game(&Game::with_rules(5, SpawnRules::default()));
+        assert!(Analysis::new(&replay, &Solver::default())
+            .unwrap()
+            .moves
+            .is_empty());
+    }
+}
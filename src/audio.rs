@@ -0,0 +1,131 @@
+#[cfg(feature = "audio")]
+use ggez::audio::{SoundSource, Source};
+use ggez::Context;
+
+use crate::settings::Settings;
+
+// playback speed ratio added each time a merged tile doubles
+const PITCH_STEP: f32 = 0.06;
+
+/// Sound of a build without the `audio` feature, which never exists
+#[cfg(not(feature = "audio"))]
+enum Source {}
+
+/// Sound effects and background music of the game
+///
+/// Every sound is optional: when a file cannot be loaded or played (missing or invalid resource,
+/// device lost, ...), the sound is disabled and the game keeps running silently. Without an audio
+/// device ggez cannot create its context, the game is then built without the `audio` feature and
+/// no sound is loaded.
+pub struct Audio {
+    slide: Option<Source>,
+    merge: Option<Source>,
+    spawn: Option<Source>,
+    win: Option<Source>,
+    gameover: Option<Source>,
+    music: Option<Source>,
+}
+
+/// Loads a sound from resources
+#[cfg(feature = "audio")]
+fn load(ctx: &mut Context, path: &str) -> Option<Source> {
+    match Source::new(ctx, path) {
+        Ok(source) => Some(source),
+        Err(error) => {
+            eprintln!("Sound {path} is disabled: {error}");
+            None
+        }
+    }
+}
+
+/// Sounds are never loaded without the `audio` feature
+#[cfg(not(feature = "audio"))]
+fn load(_ctx: &mut Context, _path: &str) -> Option<Source> {
+    None
+}
+
+/// Plays a sound effect with the given volume and pitch, disables it on failure
+#[cfg(feature = "audio")]
+fn play(ctx: &mut Context, sound: &mut Option<Source>, volume: f32, pitch: f32) {
+    if volume <= 0. {
+        return;
+    }
+    if let Some(source) = sound {
+        source.set_volume(volume);
+        source.set_pitch(pitch);
+        if let Err(error) = source.play_detached(ctx) {
+            eprintln!("Sound playback is disabled: {error}");
+            *sound = None;
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn play(_ctx: &mut Context, _sound: &mut Option<Source>, _volume: f32, _pitch: f32) {}
+
+/// Sets the volume of the background music, starting or stopping it if needed, disables it on
+/// failure
+#[cfg(feature = "audio")]
+fn play_music(ctx: &mut Context, music: &mut Option<Source>, gain: f32) {
+    if let Some(source) = music.as_mut() {
+        source.set_volume(gain);
+        source.set_repeat(true);
+        let result = if gain > 0. && !source.playing() {
+            source.play(ctx)
+        } else if gain <= 0. && source.playing() {
+            source.stop(ctx)
+        } else {
+            Ok(())
+        };
+        if let Err(error) = result {
+            eprintln!("Music is disabled: {error}");
+            *music = None;
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn play_music(_ctx: &mut Context, _music: &mut Option<Source>, _gain: f32) {}
+
+impl Audio {
+    pub fn new(ctx: &mut Context, settings: &Settings) -> Self {
+        let music = load(ctx, "/music.wav");
+        let mut audio = Self {
+            slide: load(ctx, "/slide.wav"),
+            merge: load(ctx, "/merge.wav"),
+            spawn: load(ctx, "/spawn.wav"),
+            win: load(ctx, "/win.wav"),
+            gameover: load(ctx, "/gameover.wav"),
+            music,
+        };
+        audio.apply(ctx, settings);
+        audio
+    }
+
+    /// Applies volume settings to the background music, starting or stopping it if needed
+    pub fn apply(&mut self, ctx: &mut Context, settings: &Settings) {
+        play_music(ctx, &mut self.music, settings.music_gain());
+    }
+
+    pub fn slide(&mut self, ctx: &mut Context, settings: &Settings) {
+        play(ctx, &mut self.slide, settings.effects_gain(), 1.);
+    }
+
+    /// Plays the merge sound, the pitch rises with the value of the merged tile
+    pub fn merge(&mut self, ctx: &mut Context, settings: &Settings, value: u32) {
+        let pitch = 1. + PITCH_STEP * (value.max(4) as f32).log2() - 2. * PITCH_STEP;
+        play(ctx, &mut self.merge, settings.effects_gain(), pitch);
+    }
+
+    pub fn spawn(&mut self, ctx: &mut Context, settings: &Settings) {
+        play(ctx, &mut self.spawn, settings.effects_gain(), 1.);
+    }
+
+    pub fn win(&mut self, ctx: &mut Context, settings: &Settings) {
+        play(ctx, &mut self.win, settings.effects_gain(), 1.);
+    }
+
+    pub fn gameover(&mut self, ctx: &mut Context, settings: &Settings) {
+        play(ctx, &mut self.gameover, settings.effects_gain(), 1.);
+    }
+}
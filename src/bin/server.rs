@@ -0,0 +1,358 @@
+//! Self-hostable server of network races, see `rust_2048::net` for the protocol
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use rust_2048::game::{SpawnRules, Variant, SPAWN_PRESETS, WIN_TILE};
+use rust_2048::net::{self, ClientMessage, Race, ServerMessage, DEFAULT_PORT};
+
+/// Options given on the command line
+struct Options {
+    address: String,
+    players: usize,
+    target: u32,
+    rules: SpawnRules,
+}
+
+/// Returns the help message of the command line
+fn usage() -> String {
+    format!(
+        "Usage: server [--address <host:port>] [--players 1-4] [--target <tile>] [--rules {}]",
+        SPAWN_PRESETS.join("|")
+    )
+}
+
+impl Options {
+    /// Parses command line arguments (without the program name)
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            address: format!("0.0.0.0:{DEFAULT_PORT}"),
+            players: 2,
+            target: WIN_TILE,
+            rules: SpawnRules::default(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value after {arg}"))?;
+            match arg.as_str() {
+                "--address" => options.address = value,
+                "--players" => {
+                    options.players = value
+                        .parse()
+                        .ok()
+                        .filter(|players| (1..=4).contains(players))
+                        .ok_or_else(|| format!("Invalid number of players `{value}`"))?;
+                }
+                "--target" => {
+                    options.target = value
+                        .parse()
+                        .map_err(|_| format!("Invalid target `{value}`"))?;
+                }
+                "--rules" => {
+                    options.rules = SpawnRules::preset(&value)
+                        .ok_or_else(|| format!("Unknown spawn rules `{value}`"))?;
+                }
+                unknown => return Err(format!("Unknown argument `{unknown}`")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Something which happened on a connection, identified by its index
+enum Event {
+    Connected(usize, TcpStream),
+    Message(usize, ClientMessage),
+    /// A line which is not a message, with the reason
+    Invalid(usize, String),
+    Disconnected(usize),
+}
+
+/// Race being played with the connection of each player
+struct Room {
+    race: Race,
+    connections: Vec<usize>,
+}
+
+struct Server {
+    options: Options,
+    // stream of every open connection, to send messages
+    streams: HashMap<usize, TcpStream>,
+    // connections waiting for the next race with the name of their player
+    lobby: Vec<(usize, String)>,
+    rooms: HashMap<usize, Room>,
+    // room and index of the player of every connection in a race
+    seats: HashMap<usize, (usize, usize)>,
+    next_room: usize,
+}
+
+impl Server {
+    fn new(options: Options) -> Server {
+        Server {
+            options,
+            streams: HashMap::new(),
+            lobby: Vec::new(),
+            rooms: HashMap::new(),
+            seats: HashMap::new(),
+            next_room: 0,
+        }
+    }
+
+    /// Sends `message` to `connection`, a failure is noticed by the reader of the connection
+    fn send(&mut self, connection: usize, message: &ServerMessage) {
+        if let Some(stream) = self.streams.get_mut(&connection) {
+            if let Err(error) = net::send(stream, message) {
+                eprintln!("Message could not be sent to connection {connection}: {error}");
+            }
+        }
+    }
+
+    /// Sends `message` to every player of `room`
+    fn broadcast(&mut self, room: usize, message: &ServerMessage) {
+        for connection in self.rooms[&room].connections.clone() {
+            self.send(connection, message);
+        }
+    }
+
+    /// Closes `connection`, its reader reports the disconnection
+    fn close(&mut self, connection: usize) {
+        if let Some(stream) = self.streams.remove(&connection) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(connection, stream) => {
+                self.streams.insert(connection, stream);
+            }
+            Event::Message(connection, ClientMessage::Join { name }) => self.join(connection, name),
+            Event::Message(connection, ClientMessage::Move { action, grid }) => {
+                self.play(connection, action, grid)
+            }
+            Event::Invalid(connection, reason) => {
+                self.send(connection, &ServerMessage::Rejected { reason })
+            }
+            Event::Disconnected(connection) => {
+                self.streams.remove(&connection);
+                self.leave(connection);
+            }
+        }
+    }
+
+    /// Puts `connection` in the lobby and starts a race once it is full
+    fn join(&mut self, connection: usize, name: String) {
+        let joined = self.seats.contains_key(&connection)
+            || self.lobby.iter().any(|&(waiting, _)| waiting == connection);
+        if joined {
+            let reason = String::from("Already joined");
+            self.send(connection, &ServerMessage::Rejected { reason });
+            return;
+        }
+        self.lobby.push((connection, name));
+        self.update_lobby();
+        if self.lobby.len() == self.options.players {
+            self.start_race();
+        }
+    }
+
+    /// Sends the players of the lobby to everyone waiting
+    fn update_lobby(&mut self) {
+        let message = ServerMessage::Lobby {
+            players: self.lobby.iter().map(|(_, name)| name.clone()).collect(),
+            needed: self.options.players,
+        };
+        for (connection, _) in self.lobby.clone() {
+            self.send(connection, &message);
+        }
+    }
+
+    /// Starts a race between the players of the lobby
+    fn start_race(&mut self) {
+        let (connections, players): (Vec<usize>, Vec<String>) = self.lobby.drain(..).unzip();
+        let (seed, variant, rules) = (
+            rand::random(),
+            Variant::default(),
+            self.options.rules.clone(),
+        );
+        let race = Race::new(
+            seed,
+            variant,
+            rules.clone(),
+            players.len(),
+            self.options.target,
+        );
+        let room = self.next_room;
+        self.next_room += 1;
+        println!("Race {room} started: {}", players.join(", "));
+        for (you, &connection) in connections.iter().enumerate() {
+            self.seats.insert(connection, (room, you));
+            let start = ServerMessage::Start {
+                seed,
+                variant,
+                rules: rules.clone(),
+                target: self.options.target,
+                players: players.clone(),
+                you,
+            };
+            self.send(connection, &start);
+        }
+        self.rooms.insert(room, Room { race, connections });
+    }
+
+    /// Checks the move of `connection` and shares it with the other players
+    fn play(&mut self, connection: usize, action: i8, grid: [u32; 16]) {
+        let Some(&(room, player)) = self.seats.get(&connection) else {
+            let reason = String::from("Not in a race");
+            self.send(connection, &ServerMessage::Rejected { reason });
+            return;
+        };
+        match self
+            .rooms
+            .get_mut(&room)
+            .unwrap()
+            .race
+            .play(player, action, grid)
+        {
+            Ok(score) => {
+                let moved = ServerMessage::Moved {
+                    player,
+                    action,
+                    score,
+                };
+                self.broadcast(room, &moved);
+            }
+            Err(reason) => {
+                println!("Race {room}: player {player} rejected, {reason}");
+                self.send(connection, &ServerMessage::Rejected { reason });
+                self.seats.remove(&connection);
+                self.close(connection);
+                self.broadcast(room, &ServerMessage::Left { player });
+            }
+        }
+        self.finish_race(room);
+    }
+
+    /// Removes `connection` from the lobby or from its race
+    fn leave(&mut self, connection: usize) {
+        if self.lobby.iter().any(|&(waiting, _)| waiting == connection) {
+            self.lobby.retain(|&(waiting, _)| waiting != connection);
+            self.update_lobby();
+        }
+        if let Some((room, player)) = self.seats.remove(&connection) {
+            self.rooms.get_mut(&room).unwrap().race.leave(player);
+            self.broadcast(room, &ServerMessage::Left { player });
+            self.finish_race(room);
+        }
+    }
+
+    /// Ends `room` if its race is finished and closes the connections of its players
+    fn finish_race(&mut self, room: usize) {
+        let Some(winner) = self.rooms[&room].race.result() else {
+            return;
+        };
+        println!("Race {room} finished, winner: {winner:?}");
+        self.broadcast(room, &ServerMessage::Finished { winner });
+        for connection in self.rooms.remove(&room).unwrap().connections {
+            self.seats.remove(&connection);
+            self.close(connection);
+        }
+    }
+}
+
+/// Accepts connections and forwards what happens on them to `events`
+fn accept(listener: TcpListener, events: Sender<Event>) {
+    for (connection, stream) in listener.incoming().enumerate() {
+        let (reader, writer) = match stream.and_then(|writer| Ok((writer.try_clone()?, writer))) {
+            Ok(streams) => streams,
+            Err(error) => {
+                eprintln!("Connection failed: {error}");
+                continue;
+            }
+        };
+        let _ = writer.set_nodelay(true);
+        if events.send(Event::Connected(connection, writer)).is_err() {
+            return;
+        }
+        let events = events.clone();
+        thread::spawn(move || {
+            net::read_messages(&mut BufReader::new(reader), |message| {
+                let event = match message {
+                    Ok(message) => Event::Message(connection, message),
+                    Err(reason) => Event::Invalid(connection, reason),
+                };
+                events.send(event).is_ok()
+            });
+            let _ = events.send(Event::Disconnected(connection));
+        });
+    }
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{}", usage());
+            std::process::exit(2);
+        }
+    };
+    let listener = match TcpListener::bind(&options.address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Could not listen on {}: {error}", options.address);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Listening on {}, races of {} players to {}",
+        options.address, options.players, options.target
+    );
+    serve(listener, options);
+}
+
+/// Runs the server on the connections of `listener`
+fn serve(listener: TcpListener, options: Options) {
+    let (events, receiver) = mpsc::channel();
+    thread::spawn(move || accept(listener, events));
+    let mut server = Server::new(options);
+    for event in receiver {
+        server.handle(event);
+    }
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::*;
+    use std::io::{BufRead, Write};
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let options = Options::parse([String::from("--players"), String::from("2")]).unwrap();
+        thread::spawn(move || serve(listener, options));
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        stream.write_all(b"not json\n").unwrap();
+        let rejected: Option<ServerMessage> = net::receive(&mut reader).unwrap();
+        assert!(matches!(rejected, Some(ServerMessage::Rejected { .. })));
+        // the connection stays open after a malformed line
+        let name = String::from("alice");
+        net::send(&mut stream, &ClientMessage::Join { name }).unwrap();
+        let lobby: Option<ServerMessage> = net::receive(&mut reader).unwrap();
+        assert!(matches!(lobby, Some(ServerMessage::Lobby { .. })));
+
+        stream
+            .write_all(&vec![b'x'; net::MAX_LINE as usize])
+            .unwrap();
+        let rejected: Option<ServerMessage> = net::receive(&mut reader).unwrap();
+        assert!(matches!(rejected, Some(ServerMessage::Rejected { .. })));
+        let mut rest = String::new();
+        assert_eq!(reader.read_line(&mut rest).unwrap_or(0), 0);
+    }
+}
@@ -0,0 +1,403 @@
+use ggez::glam::*;
+use ggez::graphics::{
+    Canvas, Color, DrawMode, DrawParam, Drawable, Mesh, PxScale, Rect, Text, TextFragment,
+};
+use ggez::{Context, GameResult};
+
+use crate::colors::{GameColor, GameColors};
+use crate::game::{ActionReport, Cell, Game};
+use crate::topology::Topology;
+
+// number of how many images will be drawn for an animation
+pub const NB_I: f32 = 8.;
+
+#[derive(Debug)]
+pub struct Movement {
+    number: usize,
+    start: Vec2,
+    q: Vec2,
+    r: Vec2,
+}
+
+/// Corners of a hexagon centered in the 105 x 105 box of a cell
+fn hexagon_points(radius: f32) -> Vec<Vec2> {
+    (0..6)
+        .map(|i| {
+            let angle = (60. * i as f32 + 30.).to_radians();
+            Vec2::new(52.5, 52.5) + radius * Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+/// Top left corner of the box of every cell of a board with `topology`, drawn in the 500 x 500
+/// square at `origin` scaled by `scale`
+fn cell_locations(topology: Topology, origin: Vec2, scale: f32) -> Vec<Vec2> {
+    let locations: Vec<Vec2> = match topology {
+        Topology::Square | Topology::Diagonal => (0..4)
+            .flat_map(|i| {
+                (0..4).map(move |j| Vec2::new((15 + 121 * j) as f32, (15 + 121 * i) as f32))
+            })
+            .collect(),
+        // rows of the rhombus are shifted by half a cell, centers are spaced by a hexagon width
+        Topology::Hexagonal => (0..16)
+            .map(|cell| {
+                let (q, r) = ((cell % 4) as f32, (cell / 4) as f32);
+                let center = Vec2::new(54. + 87. * (q + r / 2.), 138. + 75. * r);
+                center - Vec2::new(52.5, 52.5)
+            })
+            .collect(),
+    };
+    locations
+        .into_iter()
+        .map(|location| origin + scale * location)
+        .collect()
+}
+
+/// Shape and size of the cells of a board
+#[derive(Debug, Clone, Copy)]
+struct CellShape {
+    hexagon: bool,
+    scale: f32,
+}
+
+/// Draws a cell, as a square or as a hexagon
+fn draw_cell(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    number: u32,
+    game_color: &GameColor,
+    location: Vec2,
+    shape: CellShape,
+) -> GameResult<()> {
+    let CellShape { hexagon, scale } = shape;
+    let param = DrawParam::new().dest(location).scale(Vec2::splat(scale));
+    let rect = if hexagon {
+        Mesh::new_polygon(ctx, DrawMode::fill(), &hexagon_points(48.), game_color.rgb)?
+    } else {
+        Mesh::new_rounded_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0., 0., 105., 105.),
+            5.,
+            game_color.rgb,
+        )?
+    };
+    // hexagons are narrower than squares
+    let size = if hexagon {
+        0.7 * game_color.size
+    } else {
+        game_color.size
+    };
+    let content = match Cell::from(number) {
+        Cell::Wildcard => String::from("?"),
+        _ => format!("{}", number),
+    };
+    let text = Text::new(
+        TextFragment::new(content)
+            .font("ClearSans-Bold")
+            .color(game_color.font_color)
+            .scale(PxScale::from(size)),
+    );
+    let [w, h] = text.dimensions(ctx).unwrap().center().into();
+    canvas.draw(&rect, param);
+    match Cell::from(number) {
+        // a stone is drawn as a raised block and a bomb as a dark ball, both without text
+        Cell::Stone => {
+            let block = if hexagon {
+                Mesh::new_polygon(
+                    ctx,
+                    DrawMode::fill(),
+                    &hexagon_points(36.),
+                    game_color.font_color,
+                )?
+            } else {
+                Mesh::new_rounded_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    Rect::new(12., 12., 81., 81.),
+                    5.,
+                    game_color.font_color,
+                )?
+            };
+            canvas.draw(&block, param);
+        }
+        Cell::Bomb => {
+            let ball = Mesh::new_circle(
+                ctx,
+                DrawMode::fill(),
+                Vec2::new(52.5, 56.),
+                30.,
+                0.5,
+                game_color.font_color,
+            )?;
+            canvas.draw(&ball, param);
+        }
+        _ => {
+            let offset = Vec2::new((53 - w as i32 - 2) as f32, (53 - h as i32 - 5) as f32);
+            canvas.draw(&text, param.dest(location + scale * offset));
+        }
+    }
+    Ok(())
+}
+
+/// A board drawn in the window with the state of its animations
+pub struct BoardView {
+    pub game: Game,
+    locations: Vec<Vec2>,
+    shape: CellShape,
+    counter_1: u32,
+    counter_2: u32,
+    // whether a tile must be spawned after the last action
+    has_moved: bool,
+    after_grid: [u32; 16],
+    static_locs: Vec<(usize, u32)>,
+    moves: Vec<(usize, usize, u32)>,
+    additions: Vec<(usize, u32)>,
+    // report of the last action, waiting to be animated
+    report: Option<ActionReport>,
+    movements: Vec<Movement>,
+}
+
+impl BoardView {
+    /// Creates the view of `game`, drawn in the 500 x 500 square at `origin` scaled by `scale`
+    pub fn new(game: Game, origin: Vec2, scale: f32) -> Self {
+        let topology = game.topology();
+        Self {
+            locations: cell_locations(topology, origin, scale),
+            shape: CellShape {
+                hexagon: topology == Topology::Hexagonal,
+                scale,
+            },
+            counter_1: NB_I as u32 + 1, // for movement animations
+            counter_2: NB_I as u32 + 1, // for addition animations
+            has_moved: false,
+            after_grid: game.copy_grid(),
+            static_locs: Vec::new(),
+            moves: Vec::new(),
+            additions: Vec::new(),
+            report: None,
+            movements: Vec::new(),
+            game,
+        }
+    }
+
+    /// Replaces the game of the board, for undo
+    pub fn set_game(&mut self, game: Game) {
+        self.reset_animations();
+        self.counter_1 = NB_I as u32 + 1;
+        self.counter_2 = NB_I as u32 + 1;
+        self.after_grid = game.copy_grid();
+        self.has_moved = false;
+        self.report = None;
+        self.game = game;
+    }
+
+    /// Checks if an animation is running
+    pub fn is_animating(&self) -> bool {
+        self.counter_1 <= NB_I as u32 || self.counter_2 <= NB_I as u32
+    }
+
+    /// Plays `action` if it is possible and returns what happened to the tiles
+    pub fn play(&mut self, action: i8) -> Option<ActionReport> {
+        if !self.game.partial_move(action) {
+            return None;
+        }
+        self.reset_animations();
+        let report = self.game.action(action);
+        self.after_grid = self.game.copy_grid();
+        self.has_moved = true;
+        self.report = Some(report.clone());
+        Some(report)
+    }
+
+    /// Spawns tiles if an action was played since the last spawn, returns `true` if it did
+    pub fn spawn(&mut self) -> bool {
+        if !self.has_moved {
+            return false;
+        }
+        self.game.random();
+        self.has_moved = false;
+        true
+    }
+
+    /// Resets animation
+    fn reset_animations(&mut self) {
+        self.moves.clear();
+        self.additions.clear();
+        self.movements.clear();
+        self.static_locs.clear();
+    }
+
+    /// Color of a tile, picked from its rank in the ruleset of the game
+    fn color<'a>(&self, game_colors: &'a GameColors, number: u32) -> &'a GameColor {
+        match game_colors.special(Cell::from(number)) {
+            Some(game_color) => game_color,
+            None => &game_colors[self.game.ruleset().rank(number)],
+        }
+    }
+
+    /// Returns a vector of prepared information for movement animations
+    fn prepare_movements(&self) -> Vec<Movement> {
+        self.moves
+            .iter()
+            .map(|&(start, end, number)| {
+                let start = self.locations[start];
+                let end = self.locations[end];
+                let diff = end - start;
+                let q = Vec2::new(diff[0] / NB_I, diff[1] / NB_I);
+                let r = Vec2::new(diff[0] % NB_I, diff[1] / NB_I) / NB_I;
+                Movement {
+                    number: number as usize,
+                    start,
+                    q,
+                    r,
+                }
+            })
+            .collect()
+    }
+
+    /// Prepare movement animations and addition animations from the report of an action
+    fn prepare_animations(&mut self, report: ActionReport) {
+        let merged: Vec<usize> = report.merges.iter().map(|&(cell, _)| cell).collect();
+        for (start, end, number) in report.moves {
+            if start == end && !merged.contains(&end) {
+                self.static_locs.push((start, number));
+            } else {
+                self.moves.push((start, end, number));
+            }
+        }
+        self.additions = report.merges;
+        self.movements = self.prepare_movements();
+        self.counter_1 = 0;
+        self.counter_2 = 0;
+    }
+
+    /// Animates one frame of movement animations
+    fn animate_movements(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        game_colors: &GameColors,
+        i: u32,
+    ) -> GameResult<()> {
+        for &location in self.locations.iter() {
+            let game_color = self.color(game_colors, 0);
+            draw_cell(canvas, ctx, 0, game_color, location, self.shape)?;
+        }
+
+        for &(pos, number) in self.static_locs.iter() {
+            let location = self.locations[pos];
+            let game_color = self.color(game_colors, number);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+
+        for movement in self.movements.iter() {
+            let location = movement.start + (i as f32) * (movement.q + movement.r);
+            let number = movement.number as u32;
+            let game_color = self.color(game_colors, number);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+
+        for &(pos, number) in self.additions.iter() {
+            let location = self.locations[pos];
+            let game_color = &self.color(game_colors, number).scale(0., NB_I);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+        Ok(())
+    }
+
+    /// Animates one frame of addition animations
+    fn animate_additions(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        game_colors: &GameColors,
+        i: u32,
+    ) -> GameResult<()> {
+        for (pos, &number) in self.after_grid.iter().enumerate() {
+            let location = self.locations[pos];
+            let game_color = self.color(game_colors, number);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+
+        for &(pos, number) in self.additions.iter() {
+            let location = self.locations[pos];
+            let game_color = &self.color(game_colors, number).scale(i as f32, NB_I);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the current grid
+    fn draw_grid(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        game_colors: &GameColors,
+    ) -> GameResult<()> {
+        let grid = self.game.copy_grid();
+        for (&location, number) in self.locations.iter().zip(grid) {
+            let game_color = self.color(game_colors, number);
+            draw_cell(canvas, ctx, number, game_color, location, self.shape)?;
+        }
+        Ok(())
+    }
+
+    /// Cell under `point`, `None` if it is outside of every cell
+    pub fn cell_at(&self, point: Vec2) -> Option<usize> {
+        let size = 105. * self.shape.scale;
+        self.locations.iter().position(|&location| {
+            let offset = point - location;
+            (0. ..size).contains(&offset.x) && (0. ..size).contains(&offset.y)
+        })
+    }
+
+    /// Draws an outline around `cell`, the cell selected in the editor
+    pub fn draw_cursor(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        cell: usize,
+        color: Color,
+    ) -> GameResult<()> {
+        let param = DrawParam::new()
+            .dest(self.locations[cell])
+            .scale(Vec2::splat(self.shape.scale));
+        let outline = if self.shape.hexagon {
+            Mesh::new_polygon(ctx, DrawMode::stroke(6.), &hexagon_points(48.), color)?
+        } else {
+            Mesh::new_rounded_rectangle(
+                ctx,
+                DrawMode::stroke(6.),
+                Rect::new(0., 0., 105., 105.),
+                5.,
+                color,
+            )?
+        };
+        canvas.draw(&outline, param);
+        Ok(())
+    }
+
+    /// Draws the next frame of the board: the running animation or the current grid
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        game_colors: &GameColors,
+    ) -> GameResult<()> {
+        if let Some(report) = self.report.take() {
+            self.prepare_animations(report);
+        }
+        if self.counter_1 <= NB_I as u32 {
+            self.animate_movements(canvas, ctx, game_colors, self.counter_1)?;
+            self.counter_1 += 1;
+        } else if self.counter_2 <= NB_I as u32 {
+            self.animate_additions(canvas, ctx, game_colors, self.counter_2)?;
+            self.counter_2 += 1;
+        } else {
+            self.reset_animations();
+            self.draw_grid(canvas, ctx, game_colors)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,518 @@
+//! Bots written in any language, playing through their standard input and output
+//!
+//! # Protocol
+//!
+//! The runner launches the bot as a process and both sides send one message per line. Lines of
+//! the bot which are empty or start with `#` are ignored, its standard error is left to the
+//! terminal so that it can log there.
+//!
+//! Runner to bot:
+//!
+//! - `2048 1`: greeting with the version of the protocol, sent once
+//! - `game 3 classic square up,down,left,right 4x4 original`: a new game starts, with its number,
+//!   its ruleset, its board, the actions of the board, the size of the board and the spawn rules,
+//!   written as in the text notation of `Game`
+//! - `board 2,2,-,-/-,-,-,-/-,-,-,-/4,-,-,# 12 3`: the board to play, the grid of the text notation
+//!   of `Game` (rows of the size of the board), followed by the score and the number of moves
+//! - `end 2380 256 214 over`: end of the game with its score, its highest tile, its number of
+//!   moves and why it ended, see `Ending`
+//! - `quit`: the bot should exit, it is killed otherwise
+//!
+//! Bot to runner:
+//!
+//! - `ready random`: answer to the greeting with the name of the bot, within `START_TIMEOUT`
+//! - `left`: answer to a board with one of the actions of the game, written as in `game` or as
+//!   its number (see `Topology`), within the timeout of the runner
+//!
+//! An action which does not exist or does not move any tile ends the game, like an answer which
+//! comes too late.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::{Game, SpawnRules, Variant, SIZE};
+use crate::replay::Replay;
+use crate::topology::Topology;
+
+/// Version of the protocol sent in the greeting
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Time given to the bot to answer the greeting, long enough to start an interpreter
+pub const START_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time given to the bot to answer a board when none is given
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+// time given to the bot to exit after `quit` before it is killed
+const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Reason why the bot could not answer
+#[derive(Debug)]
+pub enum BotError {
+    /// The bot could not be launched or written to
+    Io(io::Error),
+    /// The bot closed its output, it probably exited
+    Closed,
+    /// The bot did not answer in time
+    Timeout,
+    /// The answer to the greeting does not follow the protocol
+    Greeting(String),
+    /// The answer is not an action of the board or does not move any tile
+    Illegal(String),
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotError::Io(error) => write!(f, "{error}"),
+            BotError::Closed => write!(f, "the bot closed its output"),
+            BotError::Timeout => write!(f, "the bot did not answer in time"),
+            BotError::Greeting(line) => write!(f, "unexpected answer to the greeting `{line}`"),
+            BotError::Illegal(line) => write!(f, "illegal action `{line}`"),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<io::Error> for BotError {
+    fn from(error: io::Error) -> Self {
+        BotError::Io(error)
+    }
+}
+
+/// Why a game played by a bot ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    /// The board is locked
+    Over,
+    /// The bot answered an action which does not exist or does not move any tile
+    Illegal(String),
+    /// The bot did not answer in time
+    Timeout,
+    /// The bot exited or could not be written to
+    Crashed,
+    /// The game was left before its end, in the window
+    Stopped,
+}
+
+impl Ending {
+    /// Name of the ending in the `end` message
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ending::Over => "over",
+            Ending::Illegal(_) => "illegal",
+            Ending::Timeout => "timeout",
+            Ending::Crashed => "crashed",
+            Ending::Stopped => "stopped",
+        }
+    }
+}
+
+impl From<&BotError> for Ending {
+    fn from(error: &BotError) -> Self {
+        match error {
+            BotError::Illegal(line) | BotError::Greeting(line) => Ending::Illegal(line.clone()),
+            BotError::Timeout => Ending::Timeout,
+            BotError::Io(_) | BotError::Closed => Ending::Crashed,
+        }
+    }
+}
+
+/// Name of `action` in the protocol, the name of the topology with `-` between words
+pub fn action_token(topology: Topology, action: i8) -> String {
+    topology.action_name(action).replace(' ', "-")
+}
+
+/// Action of `topology` written as `token`, by its name or its number
+pub fn parse_action(topology: Topology, token: &str) -> Option<i8> {
+    (topology.actions().iter().copied())
+        .find(|&action| token == action_token(topology, action) || token.parse() == Ok(action))
+}
+
+/// Process of a bot following the protocol
+pub struct Bot {
+    /// Name given by the bot in its answer to the greeting
+    pub name: String,
+    /// Time given to the bot to answer a board
+    pub timeout: Duration,
+    child: Child,
+    stdin: ChildStdin,
+    receiver: Receiver<String>,
+    // number of games started
+    games: u32,
+    playing: bool,
+    // time before which the board sent must be answered
+    deadline: Option<Instant>,
+}
+
+impl Bot {
+    /// Launches `program` with `args` and greets it
+    pub fn launch(program: &str, args: &[String], timeout: Duration) -> Result<Bot, BotError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("Standard input is piped");
+        let reader = BufReader::new(child.stdout.take().expect("Standard output is piped"));
+        let (sender, receiver) = mpsc::channel();
+        // lines are read in the background so that answers can be waited for with a timeout
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                let line = line.trim().to_string();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut bot = Bot {
+            name: program.to_string(),
+            timeout,
+            child,
+            stdin,
+            receiver,
+            games: 0,
+            playing: false,
+            deadline: None,
+        };
+        bot.send(&format!("2048 {PROTOCOL_VERSION}"))?;
+        let line = bot.receive(Instant::now() + START_TIMEOUT)?;
+        match line.split_once(' ').unwrap_or((&line, "")) {
+            ("ready", "") => (),
+            ("ready", name) => bot.name = name.trim().to_string(),
+            _ => return Err(BotError::Greeting(line)),
+        }
+        Ok(bot)
+    }
+
+    /// Checks if a game was started and not finished yet
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Waits for the action of the bot on `game`, checked with `Game::partial_move`
+    pub fn reply(&mut self, game: &Game) -> Result<i8, BotError> {
+        self.request(game)?;
+        let line = self.receive(self.deadline.expect("A board was sent"))?;
+        self.deadline = None;
+        Bot::check(game, line)
+    }
+
+    /// Action of the bot on `game` if it answered, without waiting: the board is sent by the first
+    /// call and the following ones check the answer, until the timeout
+    pub fn poll(&mut self, game: &Game) -> Result<Option<i8>, BotError> {
+        self.request(game)?;
+        let line = match self.receiver.try_recv() {
+            Ok(line) => line,
+            Err(TryRecvError::Disconnected) => return Err(BotError::Closed),
+            Err(TryRecvError::Empty)
+                if self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() > deadline) =>
+            {
+                return Err(BotError::Timeout)
+            }
+            Err(TryRecvError::Empty) => return Ok(None),
+        };
+        self.deadline = None;
+        Bot::check(game, line).map(Some)
+    }
+
+    /// Tells the bot that the game ended
+    pub fn finish(&mut self, game: &Game, ending: &Ending) -> Result<(), BotError> {
+        self.playing = false;
+        self.deadline = None;
+        let (score, max_tile, moves) = (game.score(), game.max_tile(), game.moves());
+        self.send(&format!("end {score} {max_tile} {moves} {}", ending.name()))
+    }
+
+    /// Sends `game` to the bot unless it is already waited for, starting a new game first if
+    /// needed
+    fn request(&mut self, game: &Game) -> Result<(), BotError> {
+        if self.deadline.is_some() {
+            return Ok(());
+        }
+        if !self.playing {
+            self.games += 1;
+            self.playing = true;
+            let topology = game.topology();
+            let actions: Vec<String> = (topology.actions().iter())
+                .map(|&action| action_token(topology, action))
+                .collect();
+            let (ruleset, board) = (game.ruleset(), topology.name());
+            self.send(&format!(
+                "game {} {} {board} {} {SIZE}x{SIZE} {}",
+                self.games,
+                ruleset.name(),
+                actions.join(","),
+                game.rules().notation(ruleset)
+            ))?;
+        }
+        // late answers to an earlier board are dropped
+        while self.receiver.try_recv().is_ok() {}
+        let notation = game.to_string();
+        let grid = notation.split(' ').next().unwrap_or_default();
+        self.send(&format!("board {grid} {} {}", game.score(), game.moves()))?;
+        self.deadline = Some(Instant::now() + self.timeout);
+        Ok(())
+    }
+
+    /// Action written in `line` if it moves tiles of `game`
+    fn check(game: &Game, line: String) -> Result<i8, BotError> {
+        match parse_action(game.topology(), &line) {
+            Some(action) if game.partial_move(action) => Ok(action),
+            _ => Err(BotError::Illegal(line)),
+        }
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), BotError> {
+        writeln!(self.stdin, "{line}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Next line of the bot, received before `deadline`
+    fn receive(&mut self, deadline: Instant) -> Result<String, BotError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.receiver.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(BotError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(BotError::Closed),
+        }
+    }
+}
+
+impl Drop for Bot {
+    /// Asks the bot to quit and kills it if it is still running a moment later
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let start = Instant::now();
+        while start.elapsed() < QUIT_TIMEOUT {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Result of a game played by a bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotGame {
+    /// Replay of the game, with its score and highest tile
+    pub replay: Replay,
+    pub ending: Ending,
+    /// Time spent by the game in seconds, mostly thinking by the bot
+    pub duration: f64,
+}
+
+/// Results of the games of a bot, written as text by `Display` or as JSON by `save`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BotReport {
+    pub bot: String,
+    pub games: Vec<BotGame>,
+}
+
+impl BotReport {
+    /// Saves the report as JSON at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+    }
+}
+
+impl fmt::Display for BotReport {
+    /// One line per game followed by the average score and the highest tiles reached
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, game) in self.games.iter().enumerate() {
+            let replay = &game.replay;
+            write!(
+                f,
+                "Game {:>3} (seed {}): score {:>6}, max tile {:>5}, {:>5} moves, {}",
+                index + 1,
+                replay.seed,
+                replay.score,
+                replay.max_tile,
+                replay.actions.len(),
+                game.ending.name()
+            )?;
+            match &game.ending {
+                Ending::Illegal(line) => writeln!(f, " `{line}`")?,
+                _ => writeln!(f)?,
+            }
+        }
+        let count = self.games.len().max(1) as f64;
+        let score: f64 = self.games.iter().map(|game| game.replay.score as f64).sum();
+        writeln!(
+            f,
+            "{}: {} games, average score {:.0}",
+            self.bot,
+            self.games.len(),
+            score / count
+        )?;
+        let mut tiles: Vec<u32> = self.games.iter().map(|game| game.replay.max_tile).collect();
+        tiles.sort_unstable_by(|a, b| b.cmp(a));
+        tiles.dedup();
+        for tile in tiles.into_iter().take(4) {
+            let reached = (self.games.iter())
+                .filter(|game| game.replay.max_tile >= tile)
+                .count();
+            writeln!(
+                f,
+                "  {tile:>5} reached in {:.1}%",
+                100. * reached as f64 / count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Games played by a bot one after the other, dealt by consecutive seeds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Runner {
+    pub games: u32,
+    /// Seed of the first game
+    pub seed: u64,
+    pub variant: Variant,
+    pub rules: SpawnRules,
+}
+
+impl Runner {
+    /// Plays every game with `bot`, calling `progress` after each one, until the bot crashes
+    pub fn run(&self, bot: &mut Bot, mut progress: impl FnMut(&BotGame)) -> BotReport {
+        let mut games = Vec::new();
+        for seed in (self.seed..).take(self.games as usize) {
+            let game = self.play(bot, seed);
+            progress(&game);
+            let crashed = game.ending == Ending::Crashed;
+            games.push(game);
+            if crashed {
+                break;
+            }
+        }
+        BotReport {
+            bot: bot.name.clone(),
+            games,
+        }
+    }
+
+    /// Plays the game dealt by `seed` until the board is locked or the bot fails
+    pub fn play(&self, bot: &mut Bot, seed: u64) -> BotGame {
+        let start = Instant::now();
+        let mut game = Game::with_variant(seed, self.variant, self.rules.clone());
+        let ending = loop {
+            if game.is_gameover() {
+                break Ending::Over;
+            }
+            match bot.reply(&game) {
+                Ok(action) => {
+                    game.action(action);
+                    game.random();
+                }
+                Err(error) => break Ending::from(&error),
+            }
+        };
+        // a bot which cannot be told crashes on the next game
+        let _ = bot.finish(&game, &ending);
+        BotGame {
+            replay: Replay::from_game(&game),
+            ending,
+            duration: start.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test_bot {
+    use super::*;
+
+    /// Bot written in shell, answering `answer` to every board
+    fn shell_bot(answer: &str, timeout: Duration) -> Result<Bot, BotError> {
+        let script = format!(
+            "read greeting; echo '# greeted by' $greeting; echo ready shell; \
+            while read kind rest; do \
+                if [ $kind = board ]; then {answer}; fi; \
+                if [ $kind = quit ]; then exit; fi; \
+            done"
+        );
+        Bot::launch("sh", &[String::from("-c"), script], timeout)
+    }
+
+    fn runner(games: u32) -> Runner {
+        Runner {
+            games,
+            seed: 3,
+            variant: Variant::default(),
+            rules: SpawnRules::default(),
+        }
+    }
+
+    #[test]
+    fn actions() {
+        assert_eq!(parse_action(Topology::Square, "left"), Some(-1));
+        assert_eq!(parse_action(Topology::Square, "4"), Some(4));
+        assert_eq!(parse_action(Topology::Hexagonal, "up-left"), Some(-4));
+        assert_eq!(parse_action(Topology::Square, "up-left"), None);
+        assert_eq!(parse_action(Topology::Square, "5"), None);
+        assert_eq!(action_token(Topology::Diagonal, 5), "down-right");
+    }
+
+    #[test]
+    fn illegal_actions_end_games() {
+        // left stops moving tiles long before the board is locked
+        let mut bot = shell_bot("echo left", DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(bot.name, "shell");
+        let report = runner(2).run(&mut bot, |_| ());
+        assert_eq!(report.games.len(), 2);
+        for game in report.games.iter() {
+            assert_eq!(game.ending, Ending::Illegal(String::from("left")));
+            assert!(game.replay.actions.iter().all(|&action| action == -1));
+            assert!(game.replay.verify(&SpawnRules::default()).is_ok());
+        }
+        assert!(report.to_string().contains("shell: 2 games"));
+
+        let mut bot = shell_bot("echo sideways", DEFAULT_TIMEOUT).unwrap();
+        let game = runner(1).play(&mut bot, 0);
+        assert_eq!(game.ending, Ending::Illegal(String::from("sideways")));
+        assert!(game.replay.actions.is_empty());
+    }
+
+    #[test]
+    fn slow_and_crashing_bots() {
+        let timeout = Duration::from_millis(100);
+        let mut bot = shell_bot("sleep 1; echo left", timeout).unwrap();
+        let game = Game::from_seed(0);
+        assert!(matches!(bot.poll(&game), Ok(None)));
+        thread::sleep(timeout * 2);
+        assert!(matches!(bot.poll(&game), Err(BotError::Timeout)));
+
+        let mut bot = shell_bot("exit", DEFAULT_TIMEOUT).unwrap();
+        let report = runner(3).run(&mut bot, |_| ());
+        assert_eq!(report.games.len(), 1);
+        assert_eq!(report.games[0].ending, Ending::Crashed);
+
+        let silent = Bot::launch(
+            "sh",
+            &[String::from("-c"), String::from("echo hello")],
+            timeout,
+        );
+        assert!(matches!(silent, Err(BotError::Greeting(line)) if line == "hello"));
+        assert!(matches!(
+            Bot::launch("/nonexistent/bot", &[], timeout),
+            Err(BotError::Io(_))
+        ));
+    }
+}
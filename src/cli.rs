@@ -0,0 +1,606 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::analysis::Analysis;
+use crate::bot::{self, Bot, Ending, Runner};
+use crate::game::{Game, SpawnRules, SpawnerKind, Variant, SPAWNERS, SPAWN_PRESETS};
+use crate::ntuple::{self, Network};
+use crate::replay::Replay;
+use crate::solver::Solver;
+use crate::tablebase::{Region, Tablebase};
+use crate::topology::Topology;
+
+// target of generated tablebases, 3 x 3 boards take seconds up to 64 and minutes beyond
+const TABLEBASE_TARGET: u32 = 64;
+// games played by a training session, a few minutes with the small network
+const TRAINING_GAMES: u64 = 10000;
+// number of summaries printed during a training session, the network is saved after each one
+const TRAINING_REPORTS: u64 = 10;
+// games played by a bot
+const BOT_GAMES: u64 = 10;
+// report of the games of a bot, in the current directory
+const BOT_REPORT: &str = "bot-results.json";
+
+/// Commands with the flags they accept, the game itself has no command
+const COMMANDS: [(&str, &[&str]); 6] = [
+    (
+        "",
+        &[
+            "--spawner",
+            "--network",
+            "--connect",
+            "--name",
+            "--position",
+        ],
+    ),
+    ("verify", &["--rules"]),
+    (
+        "analyze",
+        &["--depth", "--json", "--tablebase", "--network"],
+    ),
+    ("tablebase", &["--region", "--target", "--topology"]),
+    ("train", &["--games", "--rate", "--tuples"]),
+    (
+        "bot",
+        &[
+            "--games",
+            "--seed",
+            "--timeout",
+            "--json",
+            "--topology",
+            "--watch",
+            "--spawner",
+        ],
+    ),
+];
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Spawner used instead of the one chosen in the settings
+    pub spawner: Option<SpawnerKind>,
+    /// Address of a race server to join
+    pub connect: Option<String>,
+    /// Name of the player in network races
+    pub name: Option<String>,
+    /// Replay to verify instead of starting the game
+    pub verify: Option<PathBuf>,
+    /// Preset of the spawn rules a verified replay must follow
+    pub rules: Option<&'static str>,
+    /// Game to start from, given in text notation
+    pub position: Option<Game>,
+    /// Replay to analyze instead of starting the game
+    pub analyze: Option<PathBuf>,
+    /// Depth of the solver analyzing a replay
+    pub depth: Option<u32>,
+    /// File of the JSON report of an analysis or of the games of a bot
+    pub report: Option<PathBuf>,
+    /// Tablebase consulted by the solver analyzing a replay
+    pub tablebase: Option<PathBuf>,
+    /// File of a tablebase to generate instead of starting the game
+    pub generate: Option<PathBuf>,
+    /// Region of the generated tablebase
+    pub region: Option<Region>,
+    /// Target tile of the generated tablebase
+    pub target: Option<u32>,
+    /// Topology of the generated tablebase or of the games of a bot
+    pub topology: Option<Topology>,
+    /// Network rating boards for the analysis of a replay and the hints of the game
+    pub network: Option<PathBuf>,
+    /// File of a network to train instead of starting the game
+    pub train: Option<PathBuf>,
+    /// Number of training games or of games played by a bot
+    pub games: Option<u64>,
+    /// Learning rate of the training
+    pub rate: Option<f32>,
+    /// Preset of the tuples of a new network
+    pub tuples: Option<&'static str>,
+    /// Command launching a bot, split on spaces
+    pub bot: Option<String>,
+    /// Seed of the first game played by a bot
+    pub seed: Option<u64>,
+    /// Time given to a bot to answer a board
+    pub timeout: Option<Duration>,
+    /// Whether the bot plays in the window instead of the terminal
+    pub watch: bool,
+}
+
+/// Returns the help message of the command line
+pub fn usage() -> String {
+    let spawners: Vec<&str> = SPAWNERS.iter().map(|kind| kind.name()).collect();
+    let presets: Vec<&str> = ntuple::PRESETS.iter().map(|&(name, _)| name).collect();
+    format!(
+        "Usage: rust-2048 [--spawner {}] [--network <weights>] [--connect <host:port>] \
+        [--name <name>]\n       \
+        rust-2048 [--spawner {0}] [--network <weights>] --position <notation>\n       \
+        rust-2048 verify <replay> [--rules {}]\n       \
+        rust-2048 analyze <replay> [--depth <n>] [--json <report>] [--tablebase <file>] \
+        [--network <weights>]\n       \
+        rust-2048 tablebase <file> --region 2x2|3x3|<cells> [--target <tile>] \
+        [--topology <name>]\n       \
+        rust-2048 train <weights> [--games <n>] [--rate <rate>] [--tuples {}]\n       \
+        rust-2048 bot <command> [--games <n>] [--seed <seed>] [--timeout <ms>] [--json <report>] \
+        [--topology <name>] [--spawner {0}] [--watch]",
+        spawners.join("|"),
+        SPAWN_PRESETS.join("|"),
+        presets.join("|")
+    )
+}
+
+impl Options {
+    /// Parses command line arguments (without the program name)
+    ///
+    /// A command comes first, at most one is given and each flag must apply to it, see
+    /// `COMMANDS`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        let (mut command, mut first) = ("", true);
+        while let Some(arg) = args.next() {
+            let known = COMMANDS
+                .iter()
+                .find(|&&(name, _)| !name.is_empty() && name == arg);
+            if let Some(&(name, _)) = known {
+                if !command.is_empty() {
+                    return Err(format!("Unexpected command `{name}` after `{command}`"));
+                }
+                if !first {
+                    return Err(format!("Command `{name}` must come before the options"));
+                }
+                command = name;
+            }
+            first = false;
+            let (_, allowed) = COMMANDS.iter().find(|&&(name, _)| name == command).unwrap();
+            let exists = COMMANDS
+                .iter()
+                .any(|(_, flags)| flags.contains(&arg.as_str()));
+            if exists && !allowed.contains(&arg.as_str()) {
+                return Err(match command {
+                    "" => format!("Option `{arg}` needs a command"),
+                    _ => format!("Option `{arg}` does not apply to `{command}`"),
+                });
+            }
+            match arg.as_str() {
+                "--spawner" => {
+                    let name = args.next().ok_or("Missing value after --spawner")?;
+                    let kind = SpawnerKind::from_name(&name)
+                        .ok_or_else(|| format!("Unknown spawner `{name}`"))?;
+                    options.spawner = Some(kind);
+                }
+                "--connect" => {
+                    let address = args.next().ok_or("Missing value after --connect")?;
+                    options.connect = Some(address);
+                }
+                "--position" => {
+                    let notation = args.next().ok_or("Missing value after --position")?;
+                    let game = (notation.parse())
+                        .map_err(|error| format!("Invalid position `{notation}`: {error}"))?;
+                    options.position = Some(game);
+                }
+                "verify" => {
+                    let path = args.next().ok_or("Missing replay after verify")?;
+                    options.verify = Some(PathBuf::from(path));
+                }
+                "--rules" => {
+                    let name = args.next().ok_or("Missing value after --rules")?;
+                    let preset = (SPAWN_PRESETS.into_iter())
+                        .find(|&preset| preset == name)
+                        .ok_or_else(|| format!("Unknown spawn rules `{name}`"))?;
+                    options.rules = Some(preset);
+                }
+                "analyze" => {
+                    let path = args.next().ok_or("Missing replay after analyze")?;
+                    options.analyze = Some(PathBuf::from(path));
+                }
+                "--depth" => {
+                    let depth = args.next().ok_or("Missing value after --depth")?;
+                    let depth = (depth.parse().ok())
+                        .filter(|&depth| depth > 0)
+                        .ok_or_else(|| format!("Invalid depth `{depth}`"))?;
+                    options.depth = Some(depth);
+                }
+                "--json" => {
+                    let path = args.next().ok_or("Missing value after --json")?;
+                    options.report = Some(PathBuf::from(path));
+                }
+                "--tablebase" => {
+                    let path = args.next().ok_or("Missing value after --tablebase")?;
+                    options.tablebase = Some(PathBuf::from(path));
+                }
+                "tablebase" => {
+                    let path = args.next().ok_or("Missing file after tablebase")?;
+                    options.generate = Some(PathBuf::from(path));
+                }
+                "--region" => {
+                    let name = args.next().ok_or("Missing value after --region")?;
+                    let region = Region::from_name(&name)
+                        .ok_or_else(|| format!("Invalid region `{name}`"))?;
+                    options.region = Some(region);
+                }
+                "--target" => {
+                    let target = args.next().ok_or("Missing value after --target")?;
+                    let target = (target.parse().ok())
+                        .filter(|&target: &u32| target.is_power_of_two() && target >= 4)
+                        .ok_or_else(|| format!("Invalid target `{target}`"))?;
+                    options.target = Some(target);
+                }
+                "--topology" => {
+                    let name = args.next().ok_or("Missing value after --topology")?;
+                    let topology = Topology::from_name(&name)
+                        .ok_or_else(|| format!("Unknown topology `{name}`"))?;
+                    options.topology = Some(topology);
+                }
+                "--network" => {
+                    let path = args.next().ok_or("Missing value after --network")?;
+                    options.network = Some(PathBuf::from(path));
+                }
+                "train" => {
+                    let path = args.next().ok_or("Missing weights after train")?;
+                    options.train = Some(PathBuf::from(path));
+                }
+                "--games" => {
+                    let games = args.next().ok_or("Missing value after --games")?;
+                    let games = (games.parse().ok())
+                        .filter(|&games| games > 0)
+                        .ok_or_else(|| format!("Invalid number of games `{games}`"))?;
+                    options.games = Some(games);
+                }
+                "--rate" => {
+                    let rate = args.next().ok_or("Missing value after --rate")?;
+                    let rate = (rate.parse().ok())
+                        .filter(|&rate: &f32| rate > 0. && rate <= 1.)
+                        .ok_or_else(|| format!("Invalid learning rate `{rate}`"))?;
+                    options.rate = Some(rate);
+                }
+                "--tuples" => {
+                    let name = args.next().ok_or("Missing value after --tuples")?;
+                    let &(preset, _) = (ntuple::PRESETS.iter())
+                        .find(|&&(preset, _)| preset == name)
+                        .ok_or_else(|| format!("Unknown tuples `{name}`"))?;
+                    options.tuples = Some(preset);
+                }
+                "bot" => {
+                    let command = args.next().ok_or("Missing command after bot")?;
+                    options.bot = Some(command);
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("Missing value after --seed")?;
+                    let seed = seed.parse().map_err(|_| format!("Invalid seed `{seed}`"))?;
+                    options.seed = Some(seed);
+                }
+                "--timeout" => {
+                    let timeout = args.next().ok_or("Missing value after --timeout")?;
+                    let timeout = (timeout.parse().ok())
+                        .filter(|&timeout| timeout > 0)
+                        .ok_or_else(|| format!("Invalid timeout `{timeout}`"))?;
+                    options.timeout = Some(Duration::from_millis(timeout));
+                }
+                "--watch" => options.watch = true,
+                "--name" => {
+                    let name = args.next().ok_or("Missing value after --name")?;
+                    options.name = Some(name);
+                }
+                unknown => return Err(format!("Unknown argument `{unknown}`")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Verifies the replay stored at `path` against the spawn rules of the preset `rules`, prints the
+/// result and returns the exit code
+pub fn verify(path: &Path, rules: &str) -> i32 {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Replay could not be read: {error}");
+            return 2;
+        }
+    };
+    let expected = SpawnRules::preset(rules).expect("Presets are valid");
+    match replay.verify(&expected) {
+        Ok(game) => {
+            println!(
+                "Valid replay: score {}, max tile {}, {} moves",
+                game.score(),
+                game.max_tile(),
+                game.moves()
+            );
+            println!(
+                "Ruleset {}, board {}, spawn rules {rules}",
+                replay.variant.ruleset.name(),
+                replay.variant.topology.name()
+            );
+            0
+        }
+        Err(error) => {
+            println!("Invalid replay: {error}");
+            1
+        }
+    }
+}
+
+/// Generates the tablebase described by `options`, up to 64 on the square topology by default,
+/// saves it at `path` and returns the exit code
+pub fn generate(path: &Path, options: &Options) -> i32 {
+    let Some(region) = options.region else {
+        eprintln!("Missing --region of the tablebase\n{}", usage());
+        return 2;
+    };
+    let topology = options.topology.unwrap_or(Topology::Square);
+    let start = Instant::now();
+    let tablebase =
+        Tablebase::generate(topology, region, options.target.unwrap_or(TABLEBASE_TARGET));
+    let value = tablebase.start_value();
+    println!(
+        "Region {region} up to {}: {} states in {:.1?}",
+        tablebase.target,
+        tablebase.len(),
+        start.elapsed()
+    );
+    println!(
+        "Optimal play: {:.2}% to reach {}, expected score {:.1}",
+        100. * value.win,
+        tablebase.target,
+        value.score
+    );
+    match tablebase.save(path) {
+        Ok(()) => {
+            println!("Tablebase saved to {}", path.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("Tablebase could not be saved: {error}");
+            2
+        }
+    }
+}
+
+/// Trains the network stored at `path` by self-play, or a new one of the small preset by
+/// default, saves it along the way and returns the exit code
+pub fn train(path: &Path, options: &Options) -> i32 {
+    let mut network = if path.exists() {
+        match load_network(path) {
+            Some(network) => network,
+            None => return 2,
+        }
+    } else {
+        Network::preset(options.tuples.unwrap_or("small")).expect("Presets are valid")
+    };
+    let games = options.games.unwrap_or(TRAINING_GAMES);
+    let rate = options.rate.unwrap_or(ntuple::DEFAULT_RATE);
+    let report = games.div_ceil(TRAINING_REPORTS);
+    let start = Instant::now();
+    let mut episodes = Vec::new();
+    for game in 1..=games {
+        episodes.push(network.train(rate));
+        if game % report != 0 && game != games {
+            continue;
+        }
+        let count = episodes.len() as f64;
+        let score: f64 = episodes.iter().map(|episode| episode.score as f64).sum();
+        let won = episodes
+            .iter()
+            .filter(|episode| episode.max_tile >= 2048)
+            .count();
+        let best = episodes.iter().map(|episode| episode.max_tile).max();
+        println!(
+            "Games {:>8}: average score {:>7.0}, 2048 reached {:>5.1}%, best tile {:>5} ({:.0?})",
+            network.games,
+            score / count,
+            100. * won as f64 / count,
+            best.unwrap_or(0),
+            start.elapsed()
+        );
+        episodes.clear();
+        if let Err(error) = network.save(path) {
+            eprintln!("Network could not be saved: {error}");
+            return 2;
+        }
+    }
+    println!("Network saved to {}", path.display());
+    0
+}
+
+/// Loads the network stored at `path`, printing the error if it cannot be read
+pub fn load_network(path: &Path) -> Option<Network> {
+    Network::load(path)
+        .inspect_err(|error| eprintln!("Network could not be read: {error}"))
+        .ok()
+}
+
+/// Analyzes the replay stored at `path`, prints the report and saves it as JSON in `report`,
+/// next to the replay by default, returns the exit code
+///
+/// Positions covered by the tablebase stored at `tablebase` are rated exactly, the network stored
+/// at `network` rates the other ones.
+pub fn analyze(
+    path: &Path,
+    depth: Option<u32>,
+    report: Option<&Path>,
+    tablebase: Option<&Path>,
+    network: Option<&Path>,
+) -> i32 {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Replay could not be read: {error}");
+            return 2;
+        }
+    };
+    let mut solver = depth.map_or_else(Solver::default, Solver::new);
+    if let Some(tablebase) = tablebase {
+        match Tablebase::load(tablebase) {
+            Ok(tablebase) => solver.tablebase = Some(Arc::new(tablebase)),
+            Err(error) => {
+                eprintln!("Tablebase could not be read: {error}");
+                return 2;
+            }
+        }
+    }
+    if let Some(network) = network {
+        match load_network(network) {
+            Some(network) => solver.network = Some(Arc::new(network)),
+            None => return 2,
+        }
+    }
+    let analysis = match Analysis::new(&replay, &solver) {
+        Ok(analysis) => analysis,
+        Err(error) => {
+            println!("Invalid replay: {error}");
+            return 1;
+        }
+    };
+    print!("{analysis}");
+    let report = report.map_or_else(|| path.with_extension("analysis.json"), Path::to_path_buf);
+    match analysis.save(&report) {
+        Ok(()) => {
+            println!("Report saved to {}", report.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("Report could not be saved: {error}");
+            2
+        }
+    }
+}
+
+/// Launches the bot of `command`, split on spaces, printing the error if it does not answer
+pub fn launch_bot(command: &str, timeout: Option<Duration>) -> Option<Bot> {
+    let mut words = command.split_whitespace();
+    let program = words.next()?;
+    let args: Vec<String> = words.map(String::from).collect();
+    Bot::launch(program, &args, timeout.unwrap_or(bot::DEFAULT_TIMEOUT))
+        .inspect_err(|error| eprintln!("Bot could not be started: {error}"))
+        .ok()
+}
+
+/// Plays the games described by `options` with the bot of `command`, prints the results and saves
+/// them as JSON, returns the exit code
+pub fn run_bot(command: &str, options: &Options) -> i32 {
+    let Some(mut bot) = launch_bot(command, options.timeout) else {
+        return 2;
+    };
+    let runner = Runner {
+        games: options.games.unwrap_or(BOT_GAMES).min(u32::MAX as u64) as u32,
+        seed: options.seed.unwrap_or(0),
+        variant: Variant {
+            topology: options.topology.unwrap_or_default(),
+            ..Variant::default()
+        },
+        rules: SpawnRules {
+            spawner: options.spawner.unwrap_or_default(),
+            ..SpawnRules::default()
+        },
+    };
+    println!("{} plays {} games", bot.name, runner.games);
+    let mut played = 0;
+    let report = runner.run(&mut bot, |_| {
+        played += 1;
+        eprint!("\rGame {played}/{}", runner.games);
+    });
+    eprintln!();
+    print!("{report}");
+    let path = options
+        .report
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(BOT_REPORT));
+    if let Err(error) = report.save(&path) {
+        eprintln!("Report could not be saved: {error}");
+        return 2;
+    }
+    println!("Report saved to {}", path.display());
+    // games which did not end on a locked board are forfeits of the bot
+    let forfeits = report.games.iter().any(|game| game.ending != Ending::Over);
+    i32::from(forfeits)
+}
+
+#[cfg(test)]
+mod test_cli {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|&arg| String::from(arg)))
+    }
+
+    #[test]
+    fn misplaced_command() {
+        let error = parse(&["--spawner", "evil", "verify", "replay.json"]).unwrap_err();
+        assert_eq!(error, "Command `verify` must come before the options");
+        let error = parse(&["verify", "replay.json", "analyze", "replay.json"]).unwrap_err();
+        assert_eq!(error, "Unexpected command `analyze` after `verify`");
+    }
+
+    #[test]
+    fn misplaced_options() {
+        let error = parse(&["verify", "replay.json", "--depth", "3"]).unwrap_err();
+        assert_eq!(error, "Option `--depth` does not apply to `verify`");
+        let error = parse(&["bot", "./bot", "--region", "2x2"]).unwrap_err();
+        assert_eq!(error, "Option `--region` does not apply to `bot`");
+        let error = parse(&["--games", "5"]).unwrap_err();
+        assert_eq!(error, "Option `--games` needs a command");
+        let error = parse(&["--watch"]).unwrap_err();
+        assert_eq!(error, "Option `--watch` needs a command");
+        let error = parse(&["--verbose"]).unwrap_err();
+        assert_eq!(error, "Unknown argument `--verbose`");
+    }
+
+    #[test]
+    fn commands() {
+        let notation = Game::from_seed(1).to_string();
+        let options = parse(&["--spawner", "kind", "--position", &notation]).unwrap();
+        assert_eq!(options.spawner, Some(SpawnerKind::Kind));
+        assert_eq!(
+            options.position.unwrap().copy_grid(),
+            Game::from_seed(1).copy_grid()
+        );
+        let options = parse(&["--connect", "localhost:2048", "--name", "ada"]).unwrap();
+        assert_eq!(options.connect.as_deref(), Some("localhost:2048"));
+        assert_eq!(options.name.as_deref(), Some("ada"));
+
+        let options = parse(&["verify", "replay.json", "--rules", "hard"]).unwrap();
+        assert_eq!(options.verify, Some(PathBuf::from("replay.json")));
+        assert_eq!(options.rules, Some("hard"));
+
+        let options = parse(&["analyze", "replay.json", "--depth", "3", "--json", "a.json"]);
+        let options = options.unwrap();
+        assert_eq!(options.analyze, Some(PathBuf::from("replay.json")));
+        assert_eq!(options.depth, Some(3));
+        assert_eq!(options.report, Some(PathBuf::from("a.json")));
+
+        let args = ["tablebase", "tb.bin", "--region", "2x2", "--target", "32"];
+        let options = parse(&args).unwrap();
+        assert_eq!(options.generate, Some(PathBuf::from("tb.bin")));
+        assert!(options.region.is_some());
+        assert_eq!(options.target, Some(32));
+
+        let options = parse(&[
+            "train",
+            "weights.bin",
+            "--games",
+            "100",
+            "--tuples",
+            "small",
+        ]);
+        let options = options.unwrap();
+        assert_eq!(options.train, Some(PathBuf::from("weights.bin")));
+        assert_eq!(options.games, Some(100));
+        assert_eq!(options.tuples, Some("small"));
+
+        let args = [
+            "bot",
+            "./bot",
+            "--seed",
+            "7",
+            "--topology",
+            "hexagonal",
+            "--watch",
+        ];
+        let options = parse(&args).unwrap();
+        assert_eq!(options.bot.as_deref(), Some("./bot"));
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.topology, Some(Topology::Hexagonal));
+        assert!(options.watch);
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+/// Measures the time spent playing a game, the clock can be stopped and started again
+#[derive(Debug, Default)]
+pub struct Clock {
+    elapsed: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    /// Creates a stopped clock which already measured `elapsed`
+    pub fn from_elapsed(elapsed: Duration) -> Clock {
+        Clock {
+            elapsed,
+            running_since: None,
+        }
+    }
+
+    /// Starts or stops the clock
+    pub fn set_running(&mut self, running: bool) {
+        match (self.running_since, running) {
+            (None, true) => self.running_since = Some(Instant::now()),
+            (Some(since), false) => {
+                self.elapsed += since.elapsed();
+                self.running_since = None;
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the measured time
+    pub fn elapsed(&self) -> Duration {
+        match self.running_since {
+            Some(since) => self.elapsed + since.elapsed(),
+            None => self.elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_clock {
+    use super::*;
+    use std::thread;
+
+    const PAUSE: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn paused() {
+        let mut clock = Clock::from_elapsed(Duration::from_secs(5));
+        thread::sleep(PAUSE);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+        clock.set_running(true);
+        thread::sleep(PAUSE);
+        clock.set_running(false);
+        let elapsed = clock.elapsed();
+        assert!(elapsed >= Duration::from_secs(5) + PAUSE);
+        thread::sleep(PAUSE);
+        assert_eq!(clock.elapsed(), elapsed);
+        clock.set_running(true);
+        clock.set_running(true);
+        thread::sleep(PAUSE);
+        assert!(clock.elapsed() >= elapsed + PAUSE);
+    }
+}
@@ -2,8 +2,17 @@ use std::ops::Index;
 
 use ggez::graphics::Color;
 
+use crate::game::Cell;
+
 pub const BACKGROUND: ([u8; 3], u32, [u8; 3]) = ([183, 173, 160], 0, [119, 110, 101]);
-// background color, size, font color
+// translucent layer drawn behind menus and screens
+pub const OVERLAY: Color = Color::new(0.98, 0.97, 0.94, 0.85);
+// dark layer drawn over the board while the game is paused
+pub const DIM: Color = Color::new(0.1, 0.1, 0.1, 0.75);
+// evaluation bar of a move in the review, and of a blunder
+pub const EVALUATION: Color = Color::new(0.36, 0.69, 0.36, 1.);
+pub const BLUNDER: Color = Color::new(0.86, 0.26, 0.2, 1.);
+// background color, size, font color, indexed by rank (value of a classic tile in comments)
 pub const GAMEDATA: [([u8; 3], u32, [u8; 3]); 18] = [
     ([205, 193, 180], 0, [119, 110, 101]),  // 0
     ([238, 228, 218], 56, [119, 110, 101]), // 2
@@ -25,6 +34,13 @@ pub const GAMEDATA: [([u8; 3], u32, [u8; 3]); 18] = [
     ([59, 58, 53], 26, [255, 255, 255]),    // 131072
 ];
 
+// colors of special tiles: stone, wildcard and bomb
+pub const SPECIALS: [([u8; 3], u32, [u8; 3]); 3] = [
+    ([94, 86, 77], 0, [72, 65, 58]),        // stone
+    ([142, 110, 196], 56, [255, 255, 255]), // wildcard
+    ([205, 193, 180], 0, [40, 40, 40]),     // bomb
+];
+
 pub fn as_color(rgb: [u8; 3]) -> Color {
     let [r, g, b] = rgb;
     Color::from_rgb(r, g, b)
@@ -61,23 +77,29 @@ impl GameColor {
 }
 
 #[derive(Debug)]
-pub struct GameColors([GameColor; 18]);
+pub struct GameColors([GameColor; 18], [GameColor; 3]);
 
 impl GameColors {
     pub fn new() -> Self {
-        Self(GAMEDATA.map(|game_data| GameColor::from(game_data)))
+        Self(GAMEDATA.map(GameColor::from), SPECIALS.map(GameColor::from))
+    }
+
+    /// Colors of a special tile, `None` for numbers and empty cells
+    pub fn special(&self, cell: Cell) -> Option<&GameColor> {
+        match cell {
+            Cell::Stone => Some(&self.1[0]),
+            Cell::Wildcard => Some(&self.1[1]),
+            Cell::Bomb => Some(&self.1[2]),
+            _ => None,
+        }
     }
 }
 
-impl Index<&u32> for GameColors {
+/// Colors are indexed by the rank of a tile in the sequence of its ruleset, see
+/// `Ruleset::rank`
+impl Index<usize> for GameColors {
     type Output = GameColor;
-    fn index(&self, number: &u32) -> &Self::Output {
-        let index = if *number == 0 {
-            0
-        } else {
-            let n = (*number as f32).log2();
-            n as usize
-        };
-        &self.0[index]
+    fn index(&self, rank: usize) -> &Self::Output {
+        &self.0[rank.min(self.0.len() - 1)]
     }
 }
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{Cell, Game, SpawnRules};
+use crate::ruleset::Ruleset;
+
+/// Calendar date of a daily challenge
+///
+/// Dates are taken in UTC so that every player has the same challenge at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Current date
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Date::from_days(seconds / 86400)
+    }
+
+    /// Date `days` days after January 1st, 1970
+    pub fn from_days(days: u64) -> Date {
+        // eras of 400 years starting on March 1st, 0000, the leap day ends each year
+        let days = days + 719468;
+        let era = days / 146097;
+        let day_of_era = days % 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        Date {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Seed of the challenge of the date, the date written as `YYYYMMDD`
+    pub fn seed(&self) -> u64 {
+        self.year as u64 * 10000 + self.month as u64 * 100 + self.day as u64
+    }
+
+    /// Date of the challenge played with `seed`
+    pub fn from_seed(seed: u64) -> Date {
+        Date {
+            year: (seed / 10000) as u32,
+            month: (seed / 100 % 100) as u32,
+            day: (seed % 100) as u32,
+        }
+    }
+
+    /// Game of the challenge of the date, it is a classic game following the original spawn
+    /// rules whatever the settings are
+    pub fn game(&self) -> Game {
+        Game::with_rules(self.seed(), SpawnRules::original())
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Square standing for a cell in the shared summary, warmer colors for higher tiles
+fn emoji(value: u32) -> char {
+    match Cell::from(value) {
+        Cell::Empty => '⬜',
+        Cell::Number(number) => match Ruleset::Classic.rank(number) {
+            0..=2 => '🟫',
+            3..=4 => '🟦',
+            5..=6 => '🟩',
+            7..=8 => '🟨',
+            9..=10 => '🟧',
+            11 => '🟥',
+            _ => '🟪',
+        },
+        _ => '⬛',
+    }
+}
+
+/// Text summary of a daily challenge which players share with each other, the final board is
+/// drawn with emojis
+pub fn summary(date: Date, score: u32, max_tile: u32, moves: u32, grid: [u32; 16]) -> String {
+    let rows: Vec<String> = grid
+        .chunks(4)
+        .map(|row| row.iter().map(|&value| emoji(value)).collect())
+        .collect();
+    format!(
+        "2048 daily {date}\nScore {score} · Max tile {max_tile} · {moves} moves\n{}",
+        rows.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod test_daily {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        let first = Date::from_days(0);
+        assert_eq!(first.to_string(), "1970-01-01");
+        // 2024 is a leap year
+        assert_eq!(Date::from_days(19782).to_string(), "2024-02-29");
+        assert_eq!(Date::from_days(19783).to_string(), "2024-03-01");
+        let date = Date::from_days(20745);
+        assert_eq!(date.to_string(), "2026-10-19");
+        assert_eq!(date.seed(), 20261019);
+        assert_eq!(Date::from_seed(date.seed()), date);
+    }
+
+    #[test]
+    fn same_challenge_for_everyone() {
+        let date = Date::from_days(20745);
+        assert_eq!(date.game().copy_grid(), date.game().copy_grid());
+        let next = Date::from_days(20746);
+        assert_ne!(date.game().seed(), next.game().seed());
+    }
+
+    #[test]
+    fn shared_summary() {
+        let mut grid = [0; 16];
+        grid[0] = 2048;
+        grid[1] = 8;
+        grid[15] = 2;
+        let summary = summary(Date::from_days(20745), 20000, 2048, 900, grid);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "2048 daily 2026-10-19");
+        assert_eq!(lines[1], "Score 20000 · Max tile 2048 · 900 moves");
+        assert_eq!(lines[2], "🟥🟦⬜⬜");
+        assert_eq!(lines[5], "⬜⬜⬜🟫");
+    }
+}
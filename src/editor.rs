@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+
+use crate::game::{Cell, Game, SpawnRules, Variant, BOMB, STONE, WILDCARD};
+use crate::puzzle::{Goal, Puzzle};
+use crate::ruleset::Ruleset;
+
+// number of tiles offered when cycling the value of a cell
+const TILES: usize = 17;
+// typed numbers restart from the typed digit once they reach this value
+const MAX_TYPED: u32 = 1_000_000;
+// moves allowed by an exported puzzle, to be tuned by hand in the puzzle file
+const PUZZLE_MOVES: u32 = 10;
+
+/// Values taken in turn by a cell of the editor, in increasing order: empty, the tiles of
+/// `ruleset` then the special tiles
+fn values(ruleset: Ruleset) -> Vec<u32> {
+    let tiles: Vec<u32> = match ruleset {
+        // 1 and 2 both come before 3, which is not the promotion of 2
+        Ruleset::Threes => [1, 2]
+            .into_iter()
+            .chain(iter::successors(Some(3), |&value| Some(value * 2)))
+            .take(TILES)
+            .collect(),
+        Ruleset::Classic => iter::successors(Some(2), |&value| Some(ruleset.promote(value)))
+            .take(TILES)
+            .collect(),
+        _ => iter::successors(Some(1), |&value| Some(ruleset.promote(value)))
+            .take(TILES)
+            .collect(),
+    };
+    iter::once(0)
+        .chain(tiles)
+        .chain([BOMB, WILDCARD, STONE])
+        .collect()
+}
+
+/// Name of `value` in Rust code
+fn constant(value: u32) -> String {
+    match Cell::from(value) {
+        Cell::Stone => String::from("STONE"),
+        Cell::Wildcard => String::from("WILDCARD"),
+        Cell::Bomb => String::from("BOMB"),
+        _ => value.to_string(),
+    }
+}
+
+/// Board set up in the level editor, which the player saves, loads, plays or exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub grid: [u32; 16],
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+impl Position {
+    /// Position of the board of `game`
+    pub fn from_game(game: &Game) -> Position {
+        Position {
+            grid: game.copy_grid(),
+            variant: game.variant(),
+        }
+    }
+
+    /// Gives `cell` the next value of the editor, or the previous one if `forward` is `false`,
+    /// going around from the stone to the empty cell
+    pub fn cycle(&mut self, cell: usize, forward: bool) {
+        let values = values(self.variant.ruleset);
+        let value = self.grid[cell];
+        self.grid[cell] = if forward {
+            values
+                .iter()
+                .copied()
+                .find(|&next| next > value)
+                .unwrap_or(0)
+        } else {
+            values
+                .iter()
+                .copied()
+                .rfind(|&previous| previous < value)
+                .unwrap_or(STONE)
+        };
+    }
+
+    /// Writes `digit` at the end of the number in `cell`
+    ///
+    /// The number restarts from `digit` if `append` is `false`, if the cell holds no number or if
+    /// the number becomes too large.
+    pub fn type_digit(&mut self, cell: usize, digit: u32, append: bool) {
+        let typed = match Cell::from(self.grid[cell]) {
+            Cell::Number(number) if append => number * 10 + digit,
+            _ => digit,
+        };
+        self.grid[cell] = if typed < MAX_TYPED { typed } else { digit };
+    }
+
+    /// Empties every cell
+    pub fn clear(&mut self) {
+        self.grid = [0; 16];
+    }
+
+    /// Game starting from the position, tiles spawn following `rules` and are fully determined by
+    /// `seed`
+    pub fn game(&self, seed: u64, rules: SpawnRules) -> Game {
+        Game::with_grid(seed, self.grid, self.variant, rules)
+    }
+
+    /// Puzzle named `name` starting from the position, without scripted spawns
+    ///
+    /// Its goal is to reach the tile following the highest one in a few moves, the puzzle file is
+    /// meant to be edited afterwards.
+    pub fn puzzle(&self, name: &str) -> Puzzle {
+        let ruleset = self.variant.ruleset;
+        let highest = (self.grid.iter())
+            .filter_map(|&value| match Cell::from(value) {
+                Cell::Number(number) => Some(number),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(values(ruleset)[1]);
+        Puzzle {
+            name: name.to_string(),
+            variant: self.variant,
+            grid: self.grid,
+            spawns: Vec::new(),
+            goal: Goal::Tile(ruleset.promote(highest)),
+            moves: PUZZLE_MOVES,
+        }
+    }
+
+    /// Rust code creating a game at the position, to paste in a unit test of `game`
+    pub fn fixture(&self) -> String {
+        // a trailing comment keeps rustfmt from joining the rows
+        let rows: Vec<String> = (self.grid.chunks(4))
+            .map(|row| {
+                let values: Vec<String> = row.iter().map(|&value| constant(value)).collect();
+                format!("    {}, //\n", values.join(", "))
+            })
+            .collect();
+        let variant = if self.variant == Variant::default() {
+            String::from("Variant::default()")
+        } else {
+            format!(
+                "Variant {{\n    ruleset: Ruleset::{:?},\n    topology: Topology::{:?},\n}}",
+                self.variant.ruleset, self.variant.topology
+            )
+        };
+        format!(
+            "let grid = [\n{}];\nlet variant = {variant};\n\
+            let mut game = Game::from_position(grid, variant, Vec::new());\n",
+            rows.concat()
+        )
+    }
+
+    /// Loads the position stored at `path`
+    pub fn load(path: &Path) -> io::Result<Position> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Saves the position at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(self)?;
+        fs::write(path, content)
+    }
+}
+
+#[cfg(test)]
+mod test_editor {
+    use super::*;
+    use crate::puzzle;
+    use crate::topology::Topology;
+
+    #[test]
+    fn cycle_values() {
+        let mut position = Position::default();
+        position.cycle(0, true);
+        assert_eq!(position.grid[0], 2);
+        position.cycle(0, false);
+        position.cycle(0, false);
+        assert_eq!(position.grid[0], STONE);
+        position.cycle(0, false);
+        position.cycle(0, false);
+        assert_eq!(position.grid[0], BOMB);
+        position.cycle(0, false);
+        assert_eq!(position.grid[0], 131072);
+        position.cycle(0, true);
+        position.cycle(0, true);
+        position.cycle(0, true);
+        position.cycle(0, true);
+        assert_eq!(position.grid[0], 0);
+        // a typed value goes to the closest tile
+        position.grid[1] = 5;
+        position.cycle(1, true);
+        assert_eq!(position.grid[1], 8);
+
+        position.variant.ruleset = Ruleset::Threes;
+        let threes: Vec<u32> = (0..4)
+            .map(|_| {
+                position.cycle(2, true);
+                position.grid[2]
+            })
+            .collect();
+        assert_eq!(threes, vec![1, 2, 3, 6]);
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut position = Position::default();
+        position.type_digit(3, 1, false);
+        position.type_digit(3, 2, true);
+        position.type_digit(3, 8, true);
+        assert_eq!(position.grid[3], 128);
+        position.type_digit(3, 4, false);
+        assert_eq!(position.grid[3], 4);
+        position.grid[3] = 131072;
+        position.type_digit(3, 2, true);
+        assert_eq!(position.grid[3], 2);
+        position.grid[3] = STONE;
+        position.type_digit(3, 2, true);
+        assert_eq!(position.grid[3], 2);
+        position.clear();
+        assert_eq!(position, Position::default());
+    }
+
+    #[test]
+    fn play_and_export() {
+        let mut grid = [0; 16];
+        grid[0] = 2;
+        grid[1] = 2;
+        grid[4] = STONE;
+        let position = Position {
+            grid,
+            variant: Variant::default(),
+        };
+        let mut game = position.game(3, SpawnRules::default());
+        assert_eq!(game.copy_grid(), grid);
+        game.action(-1);
+        game.random();
+        assert_eq!(game.max_tile(), 4);
+        assert_eq!(
+            game.copy_grid().iter().filter(|&&value| value != 0).count(),
+            3
+        );
+        assert_eq!(
+            Position::from_game(&position.game(3, SpawnRules::default())),
+            position
+        );
+
+        let exported = position.puzzle("a");
+        assert_eq!(exported.goal, Goal::Tile(4));
+        let content = serde_json::to_string(std::slice::from_ref(&exported)).unwrap();
+        assert_eq!(puzzle::parse(&content), Ok(vec![exported]));
+
+        let fixture = position.fixture();
+        assert!(fixture.starts_with("let grid = [\n    2, 2, 0, 0, //\n    STONE, 0, 0, 0, //\n"));
+        assert!(fixture.contains("let variant = Variant::default();"));
+        let position = Position {
+            grid,
+            variant: Variant {
+                ruleset: Ruleset::Fibonacci,
+                topology: Topology::Hexagonal,
+            },
+        };
+        assert!(position
+            .fixture()
+            .contains("topology: Topology::Hexagonal,"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Environment in the style of gym to train agents on the game
+//!
+//! An agent calls `Env::reset` to start an episode, then `Env::step` with the index of an action
+//! in `Env::actions` until the episode is done. `VecEnv` steps many environments at once, spread
+//! over the available threads.
+use std::thread;
+
+use crate::game::{Cell, Game, SpawnRules, Variant};
+
+// smallest number of environments stepped by each thread of a `VecEnv`
+const ENVS_PER_THREAD: usize = 64;
+
+/// Weights of the terms summed into the reward of a step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shaping {
+    /// Weight of the points scored by the action
+    pub score: f32,
+    /// Weight of the sum of the ranks of the tiles created by merges, the logarithm of the tiles
+    /// in the classic ruleset
+    pub rank: f32,
+    /// Reward of every step which moves tiles
+    pub survival: f32,
+    /// Reward of a step whose action does not move any tile, usually negative
+    pub invalid: f32,
+}
+
+impl Default for Shaping {
+    /// The reward is the score of the action
+    fn default() -> Self {
+        Shaping {
+            score: 1.,
+            rank: 0.,
+            survival: 0.,
+            invalid: 0.,
+        }
+    }
+}
+
+/// State of the game seen by the agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// Values of the cells row by row, special tiles included
+    pub grid: [u32; 16],
+    /// Rank of the tile of each cell in the ruleset, `0` for an empty cell or a special tile
+    pub ranks: [u8; 16],
+    /// Whether each action of `Env::actions` moves tiles
+    pub mask: Vec<bool>,
+}
+
+/// Details of a step which are not part of the reward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+    /// Whether the action moved tiles, the game does not change otherwise
+    pub moved: bool,
+}
+
+/// Game played by an agent, episodes start from a seed so that they can be reproduced
+pub struct Env {
+    game: Game,
+    variant: Variant,
+    rules: SpawnRules,
+    shaping: Shaping,
+}
+
+impl Env {
+    /// Creates an environment playing games with `variant` and `rules`, rewarded following
+    /// `shaping`, an episode starts with `reset`
+    pub fn new(variant: Variant, rules: SpawnRules, shaping: Shaping) -> Env {
+        Env {
+            game: Game::with_variant(0, variant, rules.clone()),
+            variant,
+            rules,
+            shaping,
+        }
+    }
+
+    /// Starts a new episode whose spawns are fully determined by `seed`
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::with_variant(seed, self.variant, self.rules.clone());
+        self.observation()
+    }
+
+    /// Actions of the board, an action is given to `step` by its index in this list
+    pub fn actions(&self) -> &'static [i8] {
+        self.variant.topology.actions()
+    }
+
+    /// Whether each action moves tiles
+    pub fn mask(&self) -> Vec<bool> {
+        (self.actions().iter())
+            .map(|&action| self.game.partial_move(action))
+            .collect()
+    }
+
+    /// Current state of the game
+    pub fn observation(&self) -> Observation {
+        let grid = self.game.copy_grid();
+        let ruleset = self.game.ruleset();
+        Observation {
+            grid,
+            ranks: grid.map(|value| match Cell::from(value) {
+                Cell::Number(number) => ruleset.rank(number) as u8,
+                _ => 0,
+            }),
+            mask: self.mask(),
+        }
+    }
+
+    /// Game being played
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Plays the action number `action` of `actions` and spawns tiles, returns the new state, the
+    /// reward, whether the episode is done and details of the step
+    ///
+    /// An action which does not move tiles leaves the game unchanged and gets the `invalid`
+    /// reward. Once the episode is done, steps do nothing until the next `reset`.
+    pub fn step(&mut self, action: usize) -> (Observation, f32, bool, Info) {
+        let done = self.game.is_gameover();
+        let action = self.actions().get(action).copied();
+        let moved = !done && action.is_some_and(|action| self.game.partial_move(action));
+        let reward = match action {
+            Some(action) if moved => {
+                let score = self.game.score();
+                let report = self.game.action(action);
+                self.game.random();
+                let ruleset = self.game.ruleset();
+                let ranks: usize = (report.merges.iter())
+                    .map(|&(_, value)| ruleset.rank(value))
+                    .sum();
+                self.shaping.score * (self.game.score() - score) as f32
+                    + self.shaping.rank * ranks as f32
+                    + self.shaping.survival
+            }
+            _ if done => 0.,
+            _ => self.shaping.invalid,
+        };
+        let info = Info {
+            score: self.game.score(),
+            max_tile: self.game.max_tile(),
+            moves: self.game.moves(),
+            moved,
+        };
+        (self.observation(), reward, self.game.is_gameover(), info)
+    }
+}
+
+/// Batch of environments stepped together, for throughput
+pub struct VecEnv {
+    envs: Vec<Env>,
+}
+
+impl VecEnv {
+    /// Creates `count` environments, see `Env::new`
+    pub fn new(count: usize, variant: Variant, rules: SpawnRules, shaping: Shaping) -> VecEnv {
+        VecEnv {
+            envs: (0..count)
+                .map(|_| Env::new(variant, rules.clone(), shaping))
+                .collect(),
+        }
+    }
+
+    /// Environments of the batch
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    /// Starts a new episode in every environment, the environment number `i` with the seed
+    /// `seed + i`
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        (self.envs.iter_mut().enumerate())
+            .map(|(i, env)| env.reset(seed.wrapping_add(i as u64)))
+            .collect()
+    }
+
+    /// Starts a new episode in the environment number `index` only, usually once it is done
+    pub fn reset_one(&mut self, index: usize, seed: u64) -> Observation {
+        self.envs[index].reset(seed)
+    }
+
+    /// Plays `actions[i]` in the environment number `i`, see `Env::step`
+    ///
+    /// Large batches are split between threads, the results do not depend on the split.
+    pub fn step(&mut self, actions: &[usize]) -> Vec<(Observation, f32, bool, Info)> {
+        assert_eq!(actions.len(), self.envs.len(), "One action per environment");
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let threads = threads.min(self.envs.len() / ENVS_PER_THREAD).max(1);
+        let chunk = self.envs.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (self.envs.chunks_mut(chunk))
+                .zip(actions.chunks(chunk))
+                .map(|(envs, actions)| {
+                    scope.spawn(move || {
+                        (envs.iter_mut().zip(actions))
+                            .map(|(env, &action)| env.step(action))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("Environment thread panicked"))
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_env {
+    use super::*;
+    use crate::game::{STONE, WILDCARD};
+    use crate::topology::Topology;
+
+    #[test]
+    fn episodes() {
+        let mut env = Env::new(
+            Variant::default(),
+            SpawnRules::default(),
+            Shaping::default(),
+        );
+        let first = env.reset(5);
+        assert_eq!(env.reset(5), first);
+        assert_eq!(first.grid.iter().filter(|&&value| value != 0).count(), 2);
+        assert_eq!(first.mask.len(), 4);
+        let mut game = Game::from_seed(5);
+        let mut total = 0.;
+        for step in 0..10000 {
+            let observation = env.observation();
+            let Some(action) = observation.mask.iter().position(|&legal| legal) else {
+                panic!("No legal action at step {step}");
+            };
+            game.action(env.actions()[action]);
+            game.random();
+            let (observation, reward, done, info) = env.step(action);
+            total += reward;
+            assert_eq!(observation.grid, game.copy_grid());
+            assert!(info.moved);
+            if done {
+                break;
+            }
+        }
+        assert!(env.game().is_gameover());
+        assert_eq!(total, env.game().score() as f32);
+        let (_, reward, done, info) = env.step(0);
+        assert_eq!((reward, done, info.moved), (0., true, false));
+    }
+
+    #[test]
+    fn reward_shaping() {
+        let shaping = Shaping {
+            score: 0.5,
+            rank: 2.,
+            survival: 1.,
+            invalid: -10.,
+        };
+        let mut env = Env::new(Variant::default(), SpawnRules::default(), shaping);
+        let mut grid = [0; 16];
+        grid[0] = 4;
+        grid[1] = 4;
+        grid[2] = 2;
+        grid[3] = 2;
+        env.game = Game::with_grid(1, grid, Variant::default(), SpawnRules::default());
+        let observation = env.observation();
+        assert_eq!(observation.ranks[..4], [2, 2, 1, 1]);
+        // up does not move anything on the first row
+        assert_eq!(observation.mask, vec![false, true, true, true]);
+        let (_, reward, done, info) = env.step(0);
+        assert_eq!((reward, done, info.moved), (-10., false, false));
+        // 8 and 4 are created: 12 points, ranks 3 and 2
+        let (_, reward, _, info) = env.step(3);
+        assert_eq!(reward, 0.5 * 12. + 2. * 5. + 1.);
+        assert_eq!((info.score, info.max_tile, info.moves), (12, 8, 1));
+        assert_eq!(env.step(7).1, -10.);
+
+        grid[0] = STONE;
+        grid[1] = WILDCARD;
+        env.game = Game::with_grid(1, grid, Variant::default(), SpawnRules::default());
+        assert_eq!(env.observation().ranks[..4], [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn batches() {
+        let variant = Variant {
+            topology: Topology::Hexagonal,
+            ..Variant::default()
+        };
+        let mut batch = VecEnv::new(130, variant, SpawnRules::default(), Shaping::default());
+        let mut single = Env::new(variant, SpawnRules::default(), Shaping::default());
+        let observations = batch.reset(100);
+        assert_eq!(observations.len(), 130);
+        assert_eq!(single.reset(103), observations[3]);
+        for step in 0..20 {
+            let actions: Vec<usize> = (0..130).map(|i| (i + step) % 6).collect();
+            let results = batch.step(&actions);
+            assert_eq!(results.len(), 130);
+            assert_eq!(single.step(actions[3]), results[3]);
+        }
+        assert_eq!(
+            batch.envs()[3].game().copy_grid(),
+            single.game().copy_grid()
+        );
+        assert_eq!(batch.reset_one(3, 7), single.reset(7));
+    }
+}
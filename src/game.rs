@@ -1,65 +1,382 @@
-use rand;
-
-const ORDERS: [[[usize; 4]; 4]; 4] = [
-    [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]],
-    [[3, 2, 1, 0], [7, 6, 5, 4], [11, 10, 9, 8], [15, 14, 13, 12]],
-    [[0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15]],
-    [[12, 8, 4, 0], [13, 9, 5, 1], [14, 10, 6, 2], [15, 11, 7, 3]],
-];
-
-const MOVEMENTS: [[usize; 4]; 4] = [
-    [0, 1, 2, 3],
-    [12, 13, 14, 15],
-    [0, 4, 8, 12],
-    [3, 7, 11, 15],
-];
-
-struct OrderIndex(i8);
-
-impl OrderIndex {
-    fn neg(&self) -> Self {
-        OrderIndex(-self.0)
-    }
-
-    fn value(&self) -> [[usize; 4]; 4] {
-        let i = match self.0 {
-            1 => 0, // right
-            -1 => 1, // left
-            4 => 2, // down
-            -4 => 3, // up
-            unknown => panic!("Index {unknown} not found in ORDERS")
-        };
-        ORDERS[i]
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ruleset::Ruleset;
+use crate::topology::Topology;
+
+/// Value of the tile to reach in order to win a classic game
+pub const WIN_TILE: u32 = 2048;
+
+/// Number of rows and of columns of the board
+pub const SIZE: usize = 4;
+
+/// Every action of the square topology: up, down, left and right
+pub const ACTIONS: [i8; 4] = [-4, 4, -1, 1];
+
+/// Immovable block, tiles slide up to it
+pub const STONE: u32 = u32::MAX;
+/// Tile which merges with any number
+pub const WILDCARD: u32 = u32::MAX - 1;
+/// Tile which clears the tile it collides with, and itself
+pub const BOMB: u32 = u32::MAX - 2;
+
+/// Content of a cell of the grid, special tiles are stored in the grid as reserved values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Number(u32),
+    Stone,
+    Wildcard,
+    Bomb,
+}
+
+impl From<u32> for Cell {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Cell::Empty,
+            STONE => Cell::Stone,
+            WILDCARD => Cell::Wildcard,
+            BOMB => Cell::Bomb,
+            number => Cell::Number(number),
+        }
     }
 }
 
-struct MovementIndex(i8);
+// cells whose tiles ended up in each cell during an action
+type Tracks = [Vec<usize>; 16];
+
+/// Tiles moved and created by an action, used to animate it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionReport {
+    /// Start cell, end cell and value before the action of every tile of the grid
+    pub moves: Vec<(usize, usize, u32)>,
+    /// Cell and value of every tile created by a merge
+    pub merges: Vec<(usize, u32)>,
+}
+
+/// Names of spawn rules presets, see `SpawnRules::preset`
+pub const SPAWN_PRESETS: [&str; 3] = ["original", "hard", "double"];
+
+/// Rules used to spawn tiles at the beginning of a game and after each action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpawnRules {
+    /// Values which can spawn with their weights
+    pub values: Vec<(u32, u32)>,
+    /// Number of tiles spawned after each action
+    pub per_move: usize,
+    /// Number of tiles on the grid at the beginning of a game
+    pub initial: usize,
+    /// Strategy choosing where tiles appear
+    #[serde(default)]
+    pub spawner: SpawnerKind,
+    /// Special tiles (`STONE`, `WILDCARD` or `BOMB`) which can spawn with their probability in
+    /// percent, a number from `values` spawns otherwise
+    #[serde(default)]
+    pub specials: Vec<(u32, u32)>,
+}
+
+impl Default for SpawnRules {
+    fn default() -> Self {
+        SpawnRules::original()
+    }
+}
 
-impl MovementIndex {
-    fn value(&self) -> [usize; 4] {
-        let i = match self.0 {
-            -4 => 0, // up
-            4 => 1, // down
-            -1 => 2, // left
-            1 => 3, // right
-            unknown => panic!("Index {unknown} not found in MOVEMENTS")
+impl SpawnRules {
+    /// Rules of the original game: `2` with 90% of probability else `4`, one spawn per action
+    /// and two tiles at the beginning
+    pub fn original() -> SpawnRules {
+        SpawnRules {
+            values: vec![(2, 9), (4, 1)],
+            per_move: 1,
+            initial: 2,
+            spawner: SpawnerKind::Uniform,
+            specials: Vec::new(),
+        }
+    }
+
+    /// Returns the rules of a preset from `SPAWN_PRESETS`
+    ///
+    /// - `original`: see `SpawnRules::original`
+    /// - `hard`: `2` (60%), `4` (30%) or `8` (10%)
+    /// - `double`: original values but two tiles are spawned after each action
+    pub fn preset(name: &str) -> Option<SpawnRules> {
+        match name {
+            "original" => Some(SpawnRules::original()),
+            "hard" => Some(SpawnRules {
+                values: vec![(2, 6), (4, 3), (8, 1)],
+                ..SpawnRules::original()
+            }),
+            "double" => Some(SpawnRules {
+                per_move: 2,
+                ..SpawnRules::original()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Writes the rules of a game of `ruleset` in the text notation of `Game`: the name of the
+    /// preset giving them, or `values/per_move/initial/spawner/specials` where values are written
+    /// `value:weight` and specials `symbol:percent`, both separated by `,`, and `/specials` is
+    /// left out without special tiles
+    ///
+    /// The spawned values of the ruleset replace the ones of the presets if it has its own, as in
+    /// `Game::with_variant`.
+    pub fn notation(&self, ruleset: Ruleset) -> String {
+        let preset = SPAWN_PRESETS.into_iter().find(|name| {
+            let mut rules = SpawnRules::preset(name).expect("Presets are valid");
+            if let Some(values) = ruleset.spawn_values() {
+                rules.values = values;
+            }
+            rules == *self
+        });
+        if let Some(preset) = preset {
+            return String::from(preset);
+        }
+        let pairs = |pairs: &[(u32, u32)]| {
+            (pairs.iter())
+                .map(|&(value, weight)| format!("{}:{weight}", symbol(value)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let mut notation = format!(
+            "{}/{}/{}/{}",
+            pairs(&self.values),
+            self.per_move,
+            self.initial,
+            self.spawner.name()
+        );
+        if !self.specials.is_empty() {
+            notation = format!("{notation}/{}", pairs(&self.specials));
+        }
+        notation
+    }
+
+    /// Reads rules written in the text notation of `Game`, see `SpawnRules::notation`
+    pub fn from_notation(notation: &str) -> Result<SpawnRules, String> {
+        if let Some(rules) = SpawnRules::preset(notation) {
+            return Ok(rules);
+        }
+        let invalid = || format!("Invalid spawn rules `{notation}`");
+        let pairs = |pairs: &str| -> Result<Vec<(u32, u32)>, String> {
+            (pairs.split(','))
+                .map(|pair| {
+                    let (value, weight) = pair.split_once(':').ok_or_else(invalid)?;
+                    let weight = weight.parse().map_err(|_| invalid())?;
+                    Ok((parse_symbol(value)?, weight))
+                })
+                .collect()
+        };
+        let fields: Vec<&str> = notation.split('/').collect();
+        let ([values, per_move, initial, spawner] | [values, per_move, initial, spawner, _]) =
+            fields[..]
+        else {
+            return Err(invalid());
+        };
+        let values = pairs(values)?;
+        if values
+            .iter()
+            .any(|&(value, _)| Cell::from(value) != Cell::Number(value))
+        {
+            return Err(invalid());
+        }
+        let specials = match fields.get(4) {
+            Some(specials) => pairs(specials)?,
+            None => Vec::new(),
         };
-        MOVEMENTS[i]
+        if (specials.iter()).any(|&(value, _)| matches!(Cell::from(value), Cell::Number(_))) {
+            return Err(invalid());
+        }
+        Ok(SpawnRules {
+            values,
+            per_move: per_move.parse().map_err(|_| invalid())?,
+            initial: initial.parse().map_err(|_| invalid())?,
+            spawner: SpawnerKind::from_name(spawner).ok_or_else(invalid)?,
+            specials,
+        })
+    }
+
+    /// Draws a special tile according to the probabilities of `self.specials`, or a value
+    /// according to the weights of `self.values`
+    pub fn random_value(&self, rng: &mut ChaCha8Rng) -> u32 {
+        if !self.specials.is_empty() {
+            let mut r = rng.random_range(0..100);
+            for &(special, percent) in self.specials.iter() {
+                if r < percent {
+                    return special;
+                }
+                r -= percent;
+            }
+        }
+        let total: u32 = self.values.iter().map(|&(_, weight)| weight).sum();
+        let mut r = rng.random_range(0..total.max(1));
+        for &(value, weight) in self.values.iter() {
+            if r < weight {
+                return value;
+            }
+            r -= weight;
+        }
+        self.values.last().map_or(2, |&(value, _)| value)
+    }
+}
+
+/// Chooses the empty cell where a tile appears and its value
+pub trait Spawner {
+    /// Returns the index of an empty cell of `game` and the value to place, `None` if the grid is
+    /// full
+    fn spawn(&self, game: &Game, rng: &mut ChaCha8Rng) -> Option<(usize, u32)>;
+}
+
+/// Spawns a value drawn from the spawn rules in a cell selected uniformly at random
+pub struct UniformSpawner;
+
+impl Spawner for UniformSpawner {
+    fn spawn(&self, game: &Game, rng: &mut ChaCha8Rng) -> Option<(usize, u32)> {
+        let cell = *game.zero.choose(rng)? as usize;
+        Some((cell, game.rules.random_value(rng)))
+    }
+}
+
+/// Returns every possible spawn of `game` with the value of the best answer of the player
+fn rate_spawns(game: &Game) -> Vec<(usize, u32, f32)> {
+    let mut values: Vec<u32> = game
+        .rules
+        .values
+        .iter()
+        .chain(game.rules.specials.iter())
+        .filter(|&&(_, weight)| weight > 0)
+        .map(|&(value, _)| value)
+        .collect();
+    values.dedup();
+    let mut rates = Vec::new();
+    for &cell in game.zero.iter() {
+        for &value in values.iter() {
+            let mut next = game.clone();
+            next.place(cell as usize, value);
+            let best = game
+                .topology
+                .actions()
+                .iter()
+                .filter_map(|&action| next.after_action(action))
+                .map(|after| after.heuristic())
+                .fold(f32::NEG_INFINITY, f32::max);
+            rates.push((cell as usize, value, best));
+        }
     }
+    rates
 }
 
+/// Spawns the tile which leaves the player with the worst best answer
+pub struct AdversarialSpawner;
+
+impl Spawner for AdversarialSpawner {
+    fn spawn(&self, game: &Game, _rng: &mut ChaCha8Rng) -> Option<(usize, u32)> {
+        rate_spawns(game)
+            .into_iter()
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(cell, value, _)| (cell, value))
+    }
+}
+
+/// Spawns the tile which leaves the player with the best answer, useful to practice
+pub struct KindSpawner;
+
+impl Spawner for KindSpawner {
+    fn spawn(&self, game: &Game, _rng: &mut ChaCha8Rng) -> Option<(usize, u32)> {
+        rate_spawns(game)
+            .into_iter()
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(cell, value, _)| (cell, value))
+    }
+}
+
+/// Spawners which can be selected from the command line or the settings
+pub const SPAWNERS: [SpawnerKind; 3] = [SpawnerKind::Uniform, SpawnerKind::Evil, SpawnerKind::Kind];
+
+/// Spawner used by a game, it is stored with the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpawnerKind {
+    /// See `UniformSpawner`
+    #[default]
+    Uniform,
+    /// See `AdversarialSpawner`
+    Evil,
+    /// See `KindSpawner`
+    Kind,
+}
+
+impl SpawnerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpawnerKind::Uniform => "uniform",
+            SpawnerKind::Evil => "evil",
+            SpawnerKind::Kind => "kind",
+        }
+    }
+
+    /// Returns the spawner kind called `name`
+    pub fn from_name(name: &str) -> Option<SpawnerKind> {
+        SPAWNERS.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn spawner(&self) -> Box<dyn Spawner> {
+        match self {
+            SpawnerKind::Uniform => Box::new(UniformSpawner),
+            SpawnerKind::Evil => Box::new(AdversarialSpawner),
+            SpawnerKind::Kind => Box::new(KindSpawner),
+        }
+    }
+}
+
+/// Board and merge rules of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Variant {
+    pub ruleset: Ruleset,
+    pub topology: Topology,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
     grid: [u32; 16],
     zero: Vec<u32>,
     score: u32,
+    moves: u32,
+    seed: u64,
+    rng: ChaCha8Rng,
+    #[serde(default)]
+    rules: SpawnRules,
+    #[serde(default)]
+    ruleset: Ruleset,
+    #[serde(default)]
+    topology: Topology,
+    // every action played since the beginning of the game, to replay it
+    #[serde(default)]
+    actions: Vec<i8>,
+    // cells and values spawned in this order instead of random spawns, for puzzles
+    #[serde(default)]
+    script: Option<VecDeque<(usize, u32)>>,
 }
 
 impl Game {
-    fn new() -> Game {
+    /// Creates an empty game whose random generator is initialized by `seed`
+    fn with_seed(seed: u64) -> Game {
         Game {
             grid: [0; 16],
             zero: (0..=15).collect(),
             score: 0,
+            moves: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            rules: SpawnRules::default(),
+            ruleset: Ruleset::default(),
+            topology: Topology::default(),
+            actions: Vec::new(),
+            script: None,
         }
     }
 
@@ -68,6 +385,9 @@ impl Game {
         self.grid = [0; 16];
         self.zero = (0..=15).collect();
         self.score = 0;
+        self.moves = 0;
+        self.actions.clear();
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
     }
 
     /// Since `self.zero` stores indices of zero, removes zero value from `self.zero`
@@ -75,56 +395,230 @@ impl Game {
         self.zero.retain(|&x| x != zero_value as u32);
     }
 
-    /// Initializes first elements which are selected randomly and their values are `2` or `4`
-    pub fn init_first_elements() -> Game {
-        let mut game = Game::new();
-        let a = rand::random_range(0..=15) as usize;
-        game.grid[a] = game.random_2_4();
-        let b = rand::random_range(0..=15) as usize;
-        game.grid[b] = game.random_2_4();
-        let c = rand::random_range(0..=15) as usize;
-        game.grid[c] = game.random_2_4();
-        game.remove_zero(a);
+    /// Initializes first elements which are selected randomly, following `variant` and `rules`
+    #[cfg(feature = "os-rng")]
+    pub fn init_first_elements(variant: Variant, rules: SpawnRules) -> Game {
+        Game::with_variant(rand::random(), variant, rules)
+    }
+
+    /// Initializes first elements following the original spawn rules, spawns are fully
+    /// determined by `seed`
+    pub fn from_seed(seed: u64) -> Game {
+        Game::with_rules(seed, SpawnRules::default())
+    }
+
+    /// Initializes first elements following `rules`, spawns are fully determined by `seed`
+    pub fn with_rules(seed: u64, rules: SpawnRules) -> Game {
+        Game::with_variant(seed, Variant::default(), rules)
+    }
 
-        if a != b {
-            game.remove_zero(b);
+    /// Initializes first elements of a game played with `variant`, spawns are fully determined by
+    /// `seed`
+    ///
+    /// Values spawned by `rules` are replaced by the ones of the ruleset if it has its own.
+    pub fn with_variant(seed: u64, variant: Variant, mut rules: SpawnRules) -> Game {
+        if let Some(values) = variant.ruleset.spawn_values() {
+            rules.values = values;
         }
-        if a != c && b != c {
-            game.remove_zero(c);
+        let mut game = Game::with_seed(seed);
+        game.rules = rules;
+        game.ruleset = variant.ruleset;
+        game.topology = variant.topology;
+        game.place_first_elements();
+        game
+    }
+
+    /// Creates a game played with `variant` from `grid`, then tiles spawn following `rules`,
+    /// spawns are fully determined by `seed`
+    pub fn with_grid(seed: u64, grid: [u32; 16], variant: Variant, mut rules: SpawnRules) -> Game {
+        if let Some(values) = variant.ruleset.spawn_values() {
+            rules.values = values;
         }
+        let mut game = Game::from_position(grid, variant, Vec::new());
+        game.seed = seed;
+        game.rng = ChaCha8Rng::seed_from_u64(seed);
+        game.rules = rules;
+        game.script = None;
         game
     }
 
-    /// Generates the number 2 with 80% of probability else it gives 4
-    fn random_2_4(&self) -> u32 {
-        if rand::random::<f32>() < 0.8 {
-            2
+    /// Creates a game played with `variant` from `grid`, then tiles spawn in the order of `spawns`
+    /// (cell and value) instead of randomly and nothing spawns once they are all used
+    ///
+    /// A spawn in an occupied cell is skipped.
+    pub fn from_position(grid: [u32; 16], variant: Variant, spawns: Vec<(usize, u32)>) -> Game {
+        let mut game = Game::with_seed(0);
+        game.grid = grid;
+        game.zero = (0..16).filter(|&cell| grid[cell as usize] == 0).collect();
+        game.ruleset = variant.ruleset;
+        game.topology = variant.topology;
+        game.script = Some(spawns.into());
+        game
+    }
+
+    /// Places `self.rules.initial` elements on an empty grid
+    fn place_first_elements(&mut self) {
+        for _ in 0..self.rules.initial {
+            self.spawn();
+        }
+    }
+
+    /// Generates a number in an empty cell of the grid with the spawner of the spawn rules, does
+    /// nothing if the grid is full
+    fn spawn(&mut self) {
+        if let Some(script) = &mut self.script {
+            if let Some((cell, value)) = script.pop_front() {
+                if self.grid[cell] == 0 {
+                    self.place(cell, value);
+                }
+            }
+            return;
+        }
+        let spawner = self.rules.spawner.spawner();
+        self.spawn_with(spawner.as_ref());
+    }
+
+    /// Generates a number in an empty cell of the grid with `spawner`, does nothing if the grid is
+    /// full
+    pub fn spawn_with(&mut self, spawner: &dyn Spawner) {
+        let mut rng = self.rng.clone();
+        if let Some((cell, value)) = spawner.spawn(self, &mut rng) {
+            self.place(cell, value);
+        }
+        self.rng = rng;
+    }
+
+    /// Puts `value` in the empty cell `cell`
+    fn place(&mut self, cell: usize, value: u32) {
+        self.grid[cell] = value;
+        self.remove_zero(cell);
+    }
+
+    /// Drops a stone in a random empty cell, chosen with `rng` so that the spawns of the game are
+    /// not changed, and returns the cell
+    pub fn add_blocker(&mut self, rng: &mut impl Rng) -> Option<usize> {
+        let cell = *self.zero.choose(rng)? as usize;
+        self.place(cell, STONE);
+        Some(cell)
+    }
+
+    /// Returns a copy of the game after `action`, `None` if the action is not possible
+    pub fn after_action(&self, action: i8) -> Option<Game> {
+        let mut game = self.clone();
+        game.action(action);
+        (game.grid != self.grid).then_some(game)
+    }
+
+    /// Every game which can follow a spawn in `self` with its probability, the cell is chosen
+    /// uniformly and the value following the spawn rules, empty if the grid is full
+    ///
+    /// Only one spawn is considered, even if the spawn rules spawn more tiles after each action.
+    pub fn spawn_outcomes(&self) -> Vec<(f32, Game)> {
+        let specials: u32 = self
+            .rules
+            .specials
+            .iter()
+            .map(|&(_, percent)| percent)
+            .sum();
+        let numbers = 100u32.saturating_sub(specials) as f32 / 100.;
+        let total: u32 = self.rules.values.iter().map(|&(_, weight)| weight).sum();
+        let values: Vec<(u32, f32)> = (self.rules.specials.iter())
+            .map(|&(special, percent)| (special, percent as f32 / 100.))
+            .chain(
+                (self.rules.values.iter())
+                    .map(|&(value, weight)| (value, numbers * weight as f32 / total.max(1) as f32)),
+            )
+            .filter(|&(_, probability)| probability > 0.)
+            .collect();
+        let cells = self.zero.len() as f32;
+        let mut outcomes = Vec::new();
+        for &cell in self.zero.iter() {
+            for &(value, probability) in values.iter() {
+                let mut next = self.clone();
+                next.place(cell as usize, value);
+                outcomes.push((probability / cells, next));
+            }
+        }
+        outcomes
+    }
+
+    /// Rates how comfortable the grid is for the player, the higher the better
+    ///
+    /// Empty cells and pairs of neighbours which can merge together are rewarded, as well as
+    /// keeping the highest tile in a corner.
+    pub fn heuristic(&self) -> f32 {
+        let empty = self.zero.len() as f32;
+        let mut pairs = 0.;
+        for i in 0..16 {
+            if self.grid[i] == 0 {
+                continue;
+            }
+            // each pair of neighbours is counted once, from its first cell
+            for &action in self.topology.actions().iter().filter(|&&action| action > 0) {
+                if let Some(j) = self.topology.neighbour(i, action) {
+                    if self.related(self.grid[i], self.grid[j]) {
+                        pairs += 1.;
+                    }
+                }
+            }
+        }
+        let max_tile = self.max_tile();
+        let corner = [0, 3, 12, 15].iter().any(|&i| self.grid[i] == max_tile);
+        let corner = if corner { (max_tile as f32).log2() } else { 0. };
+        10. * empty + 5. * pairs + corner
+    }
+
+    /// Returns `true` if two neighbours can take part in the same merge
+    fn related(&self, a: u32, b: u32) -> bool {
+        match (Cell::from(a), Cell::from(b)) {
+            (Cell::Number(a), Cell::Number(b)) => self.ruleset.related(a, b),
+            (Cell::Number(_), Cell::Wildcard) | (Cell::Wildcard, Cell::Number(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the value created by merging `values`, `None` if they do not merge
+    ///
+    /// Numbers merge following the ruleset, wildcards complete a merge of equal numbers.
+    fn merge_values(&self, values: &[u32]) -> Option<u32> {
+        let mut numbers = Vec::new();
+        for &value in values {
+            match Cell::from(value) {
+                Cell::Number(number) => numbers.push(number),
+                Cell::Wildcard => (),
+                _ => return None,
+            }
+        }
+        if numbers.len() == values.len() {
+            return self.ruleset.merge(values);
+        }
+        let &first = numbers.first()?;
+        if numbers.iter().all(|&number| number == first) {
+            Some(self.ruleset.promote(first))
         } else {
-            4
+            None
         }
     }
 
     /// Separates zero values to non zero values by following the order
-    pub fn move_zero(&mut self, order: &[[usize; 4]; 4]) {
+    ///
+    /// Stones split lines into segments, tiles only slide inside their segment.
+    pub fn move_zero(&mut self, order: &[Vec<usize>], tracks: &mut Tracks) {
         for suborder in order {
-            let mut index: i8 = 3;
-            let mut end: i8 = 0;
-            while self.grid[suborder[end as usize]] != 0 && index > -1 {
-                end = index;
-                index -= 1;
-            }
-            if index == -1 {
-                continue;
-            } else {
-                self.moving(0, end, &suborder);
+            let segments: Vec<&[usize]> =
+                suborder.split(|&cell| self.grid[cell] == STONE).collect();
+            for segment in segments {
+                if segment.iter().any(|&cell| self.grid[cell] == 0) {
+                    self.moving(0, segment, tracks);
+                }
             }
         }
     }
 
     /// Makes one movement of the separation between zero values and non zeros values
-    fn moving(&mut self, start: i8, end: i8, suborder: &[usize; 4]) {
+    fn moving(&mut self, start: i8, suborder: &[usize], tracks: &mut Tracks) {
         let next = start + 1;
-        if next > 3 {
+        if next as usize >= suborder.len() {
             return;
         }
         let suborder_next = suborder[next as usize];
@@ -132,127 +626,325 @@ impl Game {
         let grid_next = self.grid[suborder_next];
         let grid_start = self.grid[suborder_start];
         if grid_next != 0 || grid_next == grid_start {
-            self.moving(next, end, suborder);
+            self.moving(next, suborder, tracks);
         } else {
             self.grid[suborder_start] = grid_next;
             self.grid[suborder_next] = grid_start;
+            tracks.swap(suborder_start, suborder_next);
             self.remove_zero(suborder_next);
             self.zero.push(suborder_start as u32);
             if start > 0 && self.grid[suborder[start as usize - 1]] != 0 {
-                self.moving(start - 1, end, suborder);
+                self.moving(start - 1, suborder, tracks);
             } else {
-                self.moving(next, end, suborder);
+                self.moving(next, suborder, tracks);
             }
         }
     }
 
-    /// Compares values and adds them if they are the same
-    pub fn compare(&mut self, order: &[[usize; 4]; 4]) {
+    /// Compares values and merges them following the ruleset, returns the cells and values of
+    /// merged tiles
+    ///
+    /// A bomb clears the tile it collides with and itself, stones never merge.
+    pub fn compare(&mut self, order: &[Vec<usize>], tracks: &mut Tracks) -> Vec<(usize, u32)> {
+        let size = self.ruleset.merge_size();
+        let mut merges = Vec::new();
         for suborder in order {
-            for i in 0..3 {
-                let start = suborder[i];
-                let end = suborder[i + 1];
-                let grid_start = self.grid[start];
-                if grid_start != 0 && grid_start == self.grid[end] {
-                    self.grid[start] += grid_start;
-                    self.score += grid_start * 2;
-                    self.grid[end] = 0;
-                    self.zero.push(end as u32);
+            let len = suborder.len();
+            for i in 0..len {
+                if i + 1 < len {
+                    let pair = [self.grid[suborder[i]], self.grid[suborder[i + 1]]];
+                    if pair.contains(&BOMB) && !pair.contains(&0) && !pair.contains(&STONE) {
+                        for &cell in suborder[i..i + 2].iter() {
+                            self.grid[cell] = 0;
+                            self.zero.push(cell as u32);
+                            tracks[cell].clear();
+                        }
+                        continue;
+                    }
+                }
+                if i + size > len {
+                    continue;
+                }
+                let cells = &suborder[i..i + size];
+                let values: Vec<u32> = cells.iter().map(|&cell| self.grid[cell]).collect();
+                if let Some(merged) = self.merge_values(&values) {
+                    let start = cells[0];
+                    self.grid[start] = merged;
+                    self.score += merged;
+                    for &end in cells[1..].iter() {
+                        self.grid[end] = 0;
+                        self.zero.push(end as u32);
+                        let moved = std::mem::take(&mut tracks[end]);
+                        tracks[start].extend(moved);
+                    }
+                    merges.push((start, merged));
                 }
             }
         }
+        merges
     }
 
-    /// Generates a random number in the grid (`2` or `4`)
+    /// Generates random numbers in the grid after an action, following the spawn rules
     pub fn random(&mut self) {
-        use rand::seq::IndexedRandom;
-        let mut rng = rand::rng();
-        let r = *(self.zero.choose(&mut rng).unwrap()) as usize;
-        self.grid[r] = self.random_2_4();
-        self.remove_zero(r);
+        for _ in 0..self.rules.per_move {
+            self.spawn();
+        }
     }
 
-    /// Checks if there is a possible movement (`left`, `right`, `up` or `down`)
+    /// Checks if there is a possible movement in one of the directions of the topology
+    ///
+    /// Empty cells are not enough since they may be walled in by stones.
     fn r#move(&self) -> bool {
-        if self.zero.len() != 0 {
-            return true;
-        }
-        let mut i = 0;
-        let mut condition = false;
-        let right_border = [3, 7, 11];
-        let bottom_border = [12, 13, 14];
-        while i < 15 && !condition {
-            if right_border.contains(&i) {
-                if self.grid[i] == self.grid[i + 4] {
-                    condition = true;
-                } else {
-                    i += 1;
-                }
-            } else if bottom_border.contains(&i) {
-                if self.grid[i] == self.grid[i + 1] {
-                    condition = true;
-                } else {
-                    i += 1;
-                }
-            } else if self.grid[i] == self.grid[i + 1] || self.grid[i] == self.grid[i + 4] {
-                condition = true;
-            } else {
-                i += 1;
-            }
-        }
-        condition
+        self.topology
+            .actions()
+            .iter()
+            .any(|&action| self.partial_move(action))
     }
 
-    /// Generates a condition function which checks if a located addition or located movement is
-    /// possible
-    fn condition<'a>(&'a self, suborder: &'a [usize; 4], n: i8) -> impl Fn(&usize) -> bool + 'a {
-        return move |&i| {
-            !suborder.contains(&i)
-                && (self.grid[i] == self.grid[(i as i8 + n) as usize]
-                    || self.grid[(i as i8 + n) as usize] == 0)
-        };
+    /// Checks if the action is possible, that is if it changes the grid
+    pub fn partial_move(&self, movement: i8) -> bool {
+        self.after_action(movement).is_some()
     }
 
-    /// Checks if the action is possible
-    pub fn partial_move(&self, movement: i8) -> bool {
-        let movement_index = MovementIndex(movement);
-        let filled_cells: Vec<usize> = (0..16)
-            .filter(|&i| !self.zero.contains(&(i as u32)))
-            .collect();
-        let mut condition = false;
-        let mut j = 0;
-        while j < filled_cells.len() && !condition {
-            let i = filled_cells[j];
-            if self.condition(&movement_index.value(), movement)(&i) {
-                condition = true;
+    /// Applies an action of the topology (`up`, `down`, `left`, `right`, ...) and returns where
+    /// tiles went
+    pub fn action(&mut self, action: i8) -> ActionReport {
+        let before = self.grid;
+        let mut tracks: Tracks = std::array::from_fn(|cell| {
+            if before[cell] != 0 {
+                vec![cell]
             } else {
-                j += 1;
+                vec![]
             }
-        }
-        condition
-    }
-
-    /// Applies the action `up`, `down`, `left` or `right`
-    pub fn action(&mut self, action: i8) {
-        let order_index = OrderIndex(action);
-        self.move_zero(&order_index.value());
-        self.compare(&order_index.neg().value());
-        self.move_zero(&order_index.value());
+        });
+        let lines = self.topology.lines(action);
+        let reversed: Vec<Vec<usize>> = lines
+            .iter()
+            .map(|line| line.iter().rev().copied().collect())
+            .collect();
+        self.move_zero(&lines, &mut tracks);
+        let merges = self.compare(&reversed, &mut tracks);
+        // merged tiles may slide after the merge, they are found again from one of their sources
+        let merges: Vec<(usize, u32)> = merges
+            .into_iter()
+            .map(|(cell, value)| (tracks[cell][0], value))
+            .collect();
+        self.move_zero(&lines, &mut tracks);
+        // empty cells are kept in order so that spawns only depend on the grid and the generator
+        self.zero.sort_unstable();
+        self.moves += 1;
+        self.actions.push(action);
+        let moves = tracks
+            .iter()
+            .enumerate()
+            .flat_map(|(end, starts)| starts.iter().map(move |&start| (start, end, before[start])))
+            .collect();
+        let merges = merges
+            .into_iter()
+            .map(|(source, value)| {
+                let end = tracks.iter().position(|starts| starts.contains(&source));
+                (end.unwrap_or(source), value)
+            })
+            .collect();
+        ActionReport { moves, merges }
     }
 
     /// Checks is the game is over
     pub fn is_gameover(&self) -> bool {
-        self.zero.len() == 0 && !self.r#move()
+        !self.r#move()
+    }
+
+    /// Returns the highest number of the grid
+    pub fn max_tile(&self) -> u32 {
+        self.grid
+            .iter()
+            .filter_map(|&value| match Cell::from(value) {
+                Cell::Number(number) => Some(number),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Returns the number of actions played since the beginning of the game
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    /// Returns the seed which determines every spawn of the game
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
 
     /// Copies the game grid
     pub fn copy_grid(&self) -> [u32; 16] {
-        self.grid.clone()
+        self.grid
+    }
+
+    /// Returns the ruleset deciding which tiles merge together
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
     }
 
-    /// Returns the order / direction of an action
-    pub fn direction(&self, action: i8) -> [[usize; 4]; 4] {
-        OrderIndex(action).value()
+    /// Returns the shape of the board
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Returns the ruleset and the topology of the game
+    pub fn variant(&self) -> Variant {
+        Variant {
+            ruleset: self.ruleset,
+            topology: self.topology,
+        }
+    }
+
+    /// Returns the rules deciding which tiles spawn
+    pub fn rules(&self) -> &SpawnRules {
+        &self.rules
+    }
+
+    /// Returns every action played since the beginning of the game
+    pub fn actions(&self) -> &[i8] {
+        &self.actions
+    }
+}
+
+/// Symbol of a cell in the text notation
+pub fn symbol(value: u32) -> String {
+    match Cell::from(value) {
+        Cell::Empty => String::from("-"),
+        Cell::Number(number) => number.to_string(),
+        Cell::Stone => String::from("#"),
+        Cell::Wildcard => String::from("?"),
+        Cell::Bomb => String::from("*"),
+    }
+}
+
+/// Value of a cell written `symbol` in the text notation
+pub fn parse_symbol(symbol: &str) -> Result<u32, String> {
+    match symbol {
+        "-" => Ok(0),
+        "#" => Ok(STONE),
+        "?" => Ok(WILDCARD),
+        "*" => Ok(BOMB),
+        number => number
+            .parse()
+            .ok()
+            .filter(|&number| number < BOMB)
+            .ok_or_else(|| format!("Invalid cell `{number}`")),
+    }
+}
+
+/// Writes the game in text notation, a compact form which `Game::from_str` reads back
+///
+/// The notation is made of fields separated by spaces:
+///
+/// 1. the grid, its rows from top to bottom separated by `/` and the cells of a row separated by
+///    `,`: a number, `-` for an empty cell, `#` for a stone, `?` for a wildcard or `*` for a bomb
+/// 2. the score
+/// 3. the number of moves
+/// 4. the next spawns, written `seed+words`: the seed of the random generator and the number of
+///    words already drawn from it, `+words` can be left out at the start of a game
+/// 5. the name of the ruleset, `classic` if it is left out
+/// 6. the name of the topology, `square` if it is left out
+/// 7. the size of the board, `4x4`, the only size of the engine, which the grid must match
+/// 8. the spawn rules, see `SpawnRules::notation`, `original` if they are left out
+///
+/// For instance `2,2,-,-/-,-,-,-/-,-,-,-/4,-,-,# 12 3 42+2 classic square 4x4 original`. Scripted
+/// spawns and played actions are not part of the notation.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows: Vec<String> = (self.grid.chunks(SIZE))
+            .map(|row| {
+                row.iter()
+                    .map(|&value| symbol(value))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        write!(
+            f,
+            "{} {} {} {}+{} {} {} {SIZE}x{SIZE} {}",
+            rows.join("/"),
+            self.score,
+            self.moves,
+            self.seed,
+            self.rng.get_word_pos(),
+            self.ruleset.name(),
+            self.topology.name(),
+            self.rules.notation(self.ruleset)
+        )
+    }
+}
+
+/// Reads a game written in text notation, see `Display`
+impl FromStr for Game {
+    type Err = String;
+
+    fn from_str(notation: &str) -> Result<Game, String> {
+        let fields: Vec<&str> = notation.split_whitespace().collect();
+        let [grid, score, moves, spawns, names @ ..] = &fields[..] else {
+            return Err(String::from(
+                "Expected a grid, a score, a number of moves and spawns",
+            ));
+        };
+        if let Some(unexpected) = names.get(4) {
+            return Err(format!("Unexpected `{unexpected}`"));
+        }
+        if let Some(size) = names
+            .get(2)
+            .filter(|&&size| size != format!("{SIZE}x{SIZE}"))
+        {
+            return Err(format!(
+                "Board size `{size}` is not supported, only {SIZE}x{SIZE} is"
+            ));
+        }
+        let rows: Vec<Vec<&str>> = grid
+            .split('/')
+            .map(|row| row.split(',').collect())
+            .collect();
+        if rows.len() != SIZE || rows.iter().any(|row| row.len() != SIZE) {
+            return Err(format!(
+                "Grid `{grid}` does not have {SIZE} rows of {SIZE} cells"
+            ));
+        }
+        let mut values = [0; 16];
+        for (value, symbol) in values.iter_mut().zip(rows.concat()) {
+            *value = parse_symbol(symbol)?;
+        }
+        let score = (score.parse()).map_err(|_| format!("Invalid score `{score}`"))?;
+        let moves = (moves.parse()).map_err(|_| format!("Invalid number of moves `{moves}`"))?;
+        let (seed, words) = spawns.split_once('+').unwrap_or((spawns, "0"));
+        let (Ok(seed), Ok(words)) = (seed.parse(), words.parse()) else {
+            return Err(format!("Invalid spawns `{spawns}`"));
+        };
+        let ruleset = match names.first() {
+            Some(name) => {
+                Ruleset::from_name(name).ok_or_else(|| format!("Unknown ruleset `{name}`"))?
+            }
+            None => Ruleset::default(),
+        };
+        let topology = match names.get(1) {
+            Some(name) => {
+                Topology::from_name(name).ok_or_else(|| format!("Unknown topology `{name}`"))?
+            }
+            None => Topology::default(),
+        };
+        let rules = match names.get(3) {
+            Some(rules) => SpawnRules::from_notation(rules)?,
+            None => SpawnRules::default(),
+        };
+        let variant = Variant { ruleset, topology };
+        let mut game = Game::with_grid(seed, values, variant, rules);
+        game.score = score;
+        game.moves = moves;
+        game.rng.set_word_pos(words);
+        Ok(game)
     }
 }
 
@@ -276,7 +968,7 @@ mod test_game {
         // [0, 0, 0, 0]
         // [0, 0, 0, 0]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         for i in 0..4 {
             game.grid[i] = 2;
             game.grid[i + 12] = 2;
@@ -284,7 +976,9 @@ mod test_game {
             game.remove_zero(i + 12);
         }
         let action = -4; // Up
-        game.action(action);
+        let report = game.action(action);
+        let merges: Vec<u32> = report.merges.iter().map(|&(_, value)| value).collect();
+        assert_eq!(merges, vec![4; 4]);
         for i in 0..4 {
             assert_eq!(game.grid[i], 4);
         }
@@ -311,7 +1005,7 @@ mod test_game {
         // [0, 0, 0, 0]
         // [4, 4, 4, 4]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         for i in 0..4 {
             game.grid[i] = 2;
             game.grid[i + 12] = 2;
@@ -346,7 +1040,7 @@ mod test_game {
         // [4, 0, 0, 0]
         // [4, 0, 0, 0]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         for i in 0..4 {
             game.grid[4 * i] = 2;
             game.grid[4 * i + 3] = 2;
@@ -386,7 +1080,7 @@ mod test_game {
         // [0, 0, 0, 4]
         // [0, 0, 0, 4]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         for i in 0..4 {
             game.grid[4 * i] = 2;
             game.grid[4 * i + 3] = 2;
@@ -428,7 +1122,7 @@ mod test_game {
         // [4, 0, 0, 0]
         // [4, 8, 0, 0]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         game.grid[11] = 4;
         game.grid[14] = 4;
         game.grid[15] = 8;
@@ -464,7 +1158,7 @@ mod test_game {
         // [0, 0, 0, 0]
         // [0, 0, 4, 2]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         game.grid = [0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 4, 2];
         game.zero = vec![0, 1, 2, 3, 4, 5, 6, 8, 9, 10, 11, 12, 13];
         let action = -1; // Left
@@ -487,7 +1181,7 @@ mod test_game {
         // [4, 4, 4, 4]
         // [4, 4, 4, 4]
 
-        let mut game = Game::new();
+        let mut game = Game::with_seed(0);
         for i in 0..16 {
             game.grid[i] = 2;
             game.remove_zero(i);
@@ -503,4 +1197,333 @@ mod test_game {
         game.zero.sort();
         assert_eq!(game.zero, (0..8).collect::<Vec<u32>>());
     }
+
+    #[test]
+    fn same_seed_same_spawns() {
+        let mut game_a = Game::from_seed(2048);
+        let mut game_b = Game::from_seed(2048);
+        assert_eq!(game_a.grid, game_b.grid);
+        for action in [-1, 4, 1, -4, -1, 4] {
+            if game_a.partial_move(action) {
+                game_a.action(action);
+                game_b.action(action);
+                game_a.random();
+                game_b.random();
+            }
+        }
+        assert_eq!(game_a.grid, game_b.grid);
+        assert_eq!(game_a.moves(), game_b.moves());
+    }
+
+    #[test]
+    fn spawn_rules() {
+        let game = Game::from_seed(7);
+        assert_eq!(game.zero.len(), 14);
+        assert!(game.grid.iter().all(|&v| v == 0 || v == 2 || v == 4));
+
+        let rules = SpawnRules {
+            values: vec![(8, 1)],
+            per_move: 3,
+            initial: 5,
+            spawner: SpawnerKind::Uniform,
+            specials: Vec::new(),
+        };
+        let mut game = Game::with_rules(7, rules);
+        assert_eq!(game.zero.len(), 11);
+        assert!(game.grid.iter().all(|&v| v == 0 || v == 8));
+        game.random();
+        assert_eq!(game.zero.len(), 8);
+        for _ in 0..3 {
+            game.random();
+        }
+        // the grid is full, spawning does nothing
+        assert!(game.zero.is_empty());
+        assert_eq!(game.grid.iter().filter(|&&v| v == 8).count(), 16);
+    }
+
+    #[test]
+    fn adversarial_spawner() {
+        // Grid input
+        //
+        // [4, 2, 4, 2]
+        // [2, 4, 2, 4]
+        // [4, 2, 4, 2]
+        // [2, 4, 0, 0]
+        //
+        // A `2` in the last cell lets the player merge it with the `2` above, a `4` does not: it
+        // can only be merged with the `4` on its left after moving left.
+
+        let mut game = Game::with_seed(0);
+        game.grid = [4, 2, 4, 2, 2, 4, 2, 4, 4, 2, 4, 2, 2, 4, 0, 0];
+        game.zero = vec![14, 15];
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let evil = AdversarialSpawner.spawn(&game, &mut rng).unwrap();
+        let kind = KindSpawner.spawn(&game, &mut rng).unwrap();
+        assert_ne!(evil, kind);
+        let mut after_evil = game.clone();
+        after_evil.place(evil.0, evil.1);
+        let mut after_kind = game.clone();
+        after_kind.place(kind.0, kind.1);
+        let best = |game: &Game| {
+            ACTIONS
+                .iter()
+                .filter_map(|&action| game.after_action(action))
+                .map(|after| after.heuristic())
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+        assert!(best(&after_evil) < best(&after_kind));
+    }
+
+    #[test]
+    fn rulesets() {
+        // Move : Left
+        //
+        // Grid input
+        //
+        // [1, 1, 3, 5]   Fibonacci
+        // [9, 9, 9, 9]   2187
+        // [1, 2, 1, 1]   Threes
+        //
+        // Expected output
+        //
+        // [2, 8, 0, 0]
+        // [27, 9, 0, 0]
+        // [3, 1, 1, 0]
+
+        let row = |ruleset: Ruleset, values: [u32; 4]| {
+            let mut game = Game::with_seed(0);
+            game.ruleset = ruleset;
+            game.grid[..4].copy_from_slice(&values);
+            game.zero = (4..16).collect();
+            game.action(-1);
+            [game.grid[0], game.grid[1], game.grid[2], game.grid[3]]
+        };
+        assert_eq!(row(Ruleset::Fibonacci, [1, 1, 3, 5]), [2, 8, 0, 0]);
+        assert_eq!(row(Ruleset::PowersOfThree, [9, 9, 9, 9]), [27, 9, 0, 0]);
+        assert_eq!(row(Ruleset::Threes, [1, 2, 1, 1]), [3, 1, 1, 0]);
+
+        let variant = Variant {
+            ruleset: Ruleset::Fibonacci,
+            ..Variant::default()
+        };
+        let game = Game::with_variant(3, variant, SpawnRules::original());
+        assert!(game.grid.iter().all(|&value| value <= 2));
+    }
+
+    #[test]
+    fn action_report() {
+        // Move : Right
+        //
+        // Grid input
+        //
+        // [2, 2, 0, 4]
+        //
+        // Expected output
+        //
+        // [0, 0, 4, 4]
+
+        let mut game = Game::with_seed(0);
+        game.grid[..4].copy_from_slice(&[2, 2, 0, 4]);
+        game.zero = vec![2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let mut report = game.action(1);
+        report.moves.sort();
+        assert_eq!(report.moves, vec![(0, 2, 2), (1, 2, 2), (3, 3, 4)]);
+        assert_eq!(report.merges, vec![(2, 4)]);
+    }
+
+    #[test]
+    fn special_tiles() {
+        // Move : Left
+        //
+        // Grid input
+        //
+        // [0, 2, S, 2]
+        // [W, 8, 4, 0]
+        // [4, B, 2, 2]
+        // [S, 0, S, S]
+        //
+        // Expected output
+        //
+        // [2, 0, S, 2]
+        // [16, 4, 0, 0]
+        // [4, 0, 0, 0]   the bomb clears the `4` and itself, both `2` merge
+        // [S, 0, S, S]   nothing moves, empty cells are walled in
+
+        let mut game = Game::with_seed(0);
+        game.grid = [
+            0, 2, STONE, 2, WILDCARD, 8, 4, 0, 4, BOMB, 2, 2, STONE, 0, STONE, STONE,
+        ];
+        game.zero = vec![0, 7, 13];
+        let report = game.action(-1);
+        assert_eq!(
+            game.grid,
+            [2, 0, STONE, 2, 16, 4, 0, 0, 4, 0, 0, 0, STONE, 0, STONE, STONE]
+        );
+        assert_eq!(report.merges, vec![(4, 16), (8, 4)]);
+        assert_eq!(game.max_tile(), 16);
+
+        // only a stone wall and a walled in empty cell: no move is possible
+        let mut game = Game::with_seed(0);
+        game.grid = [STONE; 16];
+        game.grid[5] = 0;
+        game.zero = vec![5];
+        assert!(game.is_gameover());
+        assert_eq!(Cell::from(BOMB), Cell::Bomb);
+    }
+
+    #[test]
+    fn hexagonal_action() {
+        // Move : down right (4)
+        //
+        // Grid input, each row is shifted by half a cell to the right
+        //
+        // [2, 0, 0, 0]
+        //  [0, 0, 0, 0]
+        //   [2, 0, 0, 0]
+        //    [0, 0, 4, 0]
+        //
+        // Expected output
+        //
+        // [0, 0, 0, 0]
+        //  [0, 0, 0, 0]
+        //   [0, 0, 0, 0]
+        //    [4, 0, 4, 0]
+
+        let mut game = Game::with_seed(0);
+        game.topology = Topology::Hexagonal;
+        game.grid[0] = 2;
+        game.grid[8] = 2;
+        game.grid[14] = 4;
+        game.zero = (0..16).filter(|&i| i != 0 && i != 8 && i != 14).collect();
+        game.action(4);
+        let mut expected = [0; 16];
+        expected[12] = 4;
+        expected[14] = 4;
+        assert_eq!(game.grid, expected);
+    }
+
+    #[test]
+    fn blockers_keep_spawns() {
+        let mut game = Game::from_seed(0);
+        let mut attacked = Game::from_seed(0);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let cell = attacked.add_blocker(&mut rng).unwrap();
+        assert_eq!(attacked.grid[cell], STONE);
+        assert!(!attacked.zero.contains(&(cell as u32)));
+        game.random();
+        attacked.random();
+        assert_eq!(game.rng, attacked.rng);
+        attacked.grid = [STONE; 16];
+        attacked.zero.clear();
+        assert_eq!(attacked.add_blocker(&mut rng), None);
+    }
+
+    #[test]
+    fn text_notation() {
+        let mut game: Game = "2,2,-,-/-,-,-,-/-,-,-,-/4,-,-,# 12 3 42".parse().unwrap();
+        let mut expected = [0; 16];
+        expected[0] = 2;
+        expected[1] = 2;
+        expected[12] = 4;
+        expected[15] = STONE;
+        assert_eq!(game.grid, expected);
+        assert_eq!((game.score, game.moves), (12, 3));
+        assert_eq!(game.variant(), Variant::default());
+        game.action(-1);
+        game.random();
+        let notation = game.to_string();
+        assert!(notation.starts_with("4,"));
+        assert!(notation.ends_with(" 16 4 42+2 classic square 4x4 original"));
+
+        // the next spawns are the ones of the original game
+        let mut original = Game::from_seed(7);
+        for action in ACTIONS {
+            original.action(action);
+            original.random();
+        }
+        let mut copy: Game = original.to_string().parse().unwrap();
+        assert_eq!(copy.to_string(), original.to_string());
+        original.action(-4);
+        original.random();
+        copy.action(-4);
+        copy.random();
+        assert_eq!(copy.grid, original.grid);
+
+        let hexagonal: Game = "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,1 0 0 1 fibonacci hexagonal"
+            .parse()
+            .unwrap();
+        assert_eq!(hexagonal.topology(), Topology::Hexagonal);
+        assert_eq!(hexagonal.rules.values, vec![(1, 9), (2, 1)]);
+        assert!(hexagonal
+            .to_string()
+            .ends_with(" fibonacci hexagonal 4x4 original"));
+        for invalid in [
+            "",
+            "2,2,-,-/-,-,-,-/-,-,-,- 0 0 1",
+            "2,2,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1",
+            "x,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- a 0 1",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1+b",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic round",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic square 5x5",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic square 4x4 easy",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic square 4x4 #:1/1/2/uniform",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic square 4x4 2:1/1/2/nice",
+            "-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- 0 0 1 classic square 4x4 original more",
+        ] {
+            assert!(invalid.parse::<Game>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn spawn_rules_notation() {
+        let custom = [
+            SpawnRules::preset("hard").unwrap(),
+            SpawnRules {
+                spawner: SpawnerKind::Evil,
+                ..SpawnRules::original()
+            },
+            SpawnRules {
+                values: vec![(2, 1)],
+                per_move: 3,
+                initial: 5,
+                spawner: SpawnerKind::Kind,
+                specials: vec![(STONE, 5), (WILDCARD, 3), (BOMB, 2)],
+            },
+        ];
+        for rules in custom {
+            let mut game = Game::with_rules(3, rules.clone());
+            game.action(-4);
+            game.random();
+            let copy: Game = game.to_string().parse().unwrap();
+            assert_eq!(copy.rules, rules);
+            assert_eq!(copy.to_string(), game.to_string());
+        }
+        let rules = SpawnRules {
+            specials: vec![(STONE, 5)],
+            ..SpawnRules::original()
+        };
+        assert_eq!(rules.notation(Ruleset::Classic), "2:9,4:1/1/2/uniform/#:5");
+        let hard = SpawnRules::preset("hard").unwrap();
+        assert_eq!(hard.notation(Ruleset::Classic), "hard");
+    }
+
+    #[test]
+    fn scripted_spawns() {
+        let mut grid = [0; 16];
+        grid[0] = 2;
+        grid[1] = 2;
+        let mut game = Game::from_position(grid, Variant::default(), vec![(0, 4), (3, 8)]);
+        assert_eq!(game.copy_grid(), grid);
+        game.action(1);
+        game.random();
+        // the second spawn is skipped since its cell is occupied, then the script is used up
+        game.random();
+        game.random();
+        let mut expected = [0; 16];
+        expected[0] = 4;
+        expected[3] = 4;
+        assert_eq!(game.copy_grid(), expected);
+        assert_eq!(game.zero.len(), 14);
+    }
 }
@@ -1,360 +1,1534 @@
 use ggez::event;
 use ggez::glam::*;
-use ggez::graphics::{
-    Canvas, DrawMode, Drawable, Mesh, PxScale, Rect, Text, TextFragment,
-};
-use ggez::input::keyboard::KeyCode;
+use ggez::graphics::{Canvas, Drawable, PxScale, Text, TextFragment};
+use ggez::input::keyboard::{KeyCode, KeyMods};
+use ggez::input::mouse::MouseButton;
 use ggez::{Context, GameResult};
-use std::usize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::colors::{GameColors, GameColor, BACKGROUND, as_color};
-use crate::game::Game;
+use crate::analysis::Analysis;
+use crate::audio::Audio;
+use crate::board::BoardView;
+use crate::bot::{Bot, Ending};
+use crate::cli::{self, Options};
+use crate::clock::Clock;
+use crate::colors::{as_color, GameColor, GameColors, BACKGROUND};
+use crate::daily::{self, Date};
+use crate::editor::Position;
+use crate::game::{Cell, Game, SpawnRules};
+use crate::mode::{GameMode, Outcome, MODES};
+use crate::net::{Client, ServerMessage};
+use crate::ntuple::Network;
+use crate::progress::PuzzleProgress;
+use crate::puzzle::{self, Goal, Puzzle};
+use crate::replay::Replay;
+use crate::ruleset::RULESETS;
+use crate::save::SavedGame;
+use crate::screens;
+use crate::settings::{self, Settings, ENTRIES};
+use crate::solver::Solver;
+use crate::stats::{self, GameRecord, Statistics};
+use crate::topology::{Topology, TOPOLOGIES};
 
-// number of how many images will be drawn for an animation
-const NB_I: f32 = 8.;
+// height of the score, moves and time display above the board
+pub const HUD_HEIGHT: f32 = 80.;
+// number of games shown by the high scores screen
+const HIGH_SCORES: usize = 10;
+// directory of the replays of finished games, in the user data directory
+const REPLAYS_DIR: &str = "replays";
+// directory of the positions saved in the editor, in the user data directory
+const POSITIONS_DIR: &str = "positions";
+// directory of the puzzles exported from the editor, in the user data directory
+const PUZZLES_DIR: &str = "puzzles";
+// depth of the solver analyzing a game for the review, low enough to answer at once
+const REVIEW_DEPTH: u32 = 1;
+// depth of the solver suggesting an action
+const HINT_DEPTH: u32 = 2;
 
-#[derive(Debug)]
-pub struct Movement {
-    number: usize,
-    start: Vec2,
-    q: Vec2,
-    r: Vec2,
+/// Screen displayed over the board
+enum Screen {
+    Board,
+    // index of the selected entry
+    Settings(usize),
+    Statistics,
+    // index of the mode in `MODES`
+    HighScores(usize),
+    // index of the selected mode in `MODES`
+    Modes(usize),
+    Pause,
+    // whether the summary of the daily challenge was copied to the clipboard
+    Daily(bool),
+    // index of the selected puzzle
+    Puzzles(usize),
+    // level editor, `typing` tells if digits are added to the number of the selected cell
+    Editor { cursor: usize, typing: bool },
+    // review of the analyzed game, index of the move
+    Review(usize),
 }
 
-fn draw_cell(
-    canvas: &mut Canvas,
-    ctx: &mut Context,
-    number: u32,
-    game_color: &GameColor,
-    location: Vec2
-) -> GameResult<()> {
-    let rect = Mesh::new_rounded_rectangle(
-        ctx,
-        DrawMode::fill(),
-        Rect::new(0., 0., 105., 105.),
-        5.,
-        game_color.rgb,
-    )?;
-    let text = Text::new(
-        TextFragment::new(format!("{}", number))
-            .font("ClearSans-Bold")
-            .color(game_color.font_color)
-            .scale(PxScale::from(game_color.size as f32)),
-    );
-    let [w, h] = text.dimensions(ctx).unwrap().center().into();
-    canvas.draw(&rect, location);
-    canvas.draw(
-        &text,
-        location + Vec2::new((53 - w as i32 - 2) as f32, (53 - h as i32 - 5) as f32),
-    );
-    Ok(())
+/// Action of a key on a board with `topology`, `0` if the key does not move tiles
+///
+/// Q, E, Z and C move tiles up left, up right, down left and down right.
+fn key_action(topology: Topology, keycode: KeyCode) -> i8 {
+    match (topology, keycode) {
+        (_, KeyCode::Left) => -1,
+        (_, KeyCode::Right) => 1,
+        (Topology::Hexagonal, KeyCode::Q) => -4,
+        (Topology::Hexagonal, KeyCode::E) => -3,
+        (Topology::Hexagonal, KeyCode::Z) => 3,
+        (Topology::Hexagonal, KeyCode::C) => 4,
+        (Topology::Hexagonal, _) => 0,
+        (_, KeyCode::Up) => -4,
+        (_, KeyCode::Down) => 4,
+        (Topology::Diagonal, KeyCode::Q) => -5,
+        (Topology::Diagonal, KeyCode::E) => -3,
+        (Topology::Diagonal, KeyCode::Z) => 3,
+        (Topology::Diagonal, KeyCode::C) => 5,
+        _ => 0,
+    }
+}
+
+/// Player of a versus game pressing `keycode` and the key it stands for on a single board, `None`
+/// if the key does not move tiles
+///
+/// The left player moves with W, A, S, D and Q, E, Z, C, the right player with the arrows and 7,
+/// 9, 1, 3 on the numeric keypad.
+fn player_key(keycode: KeyCode) -> Option<(usize, KeyCode)> {
+    match keycode {
+        KeyCode::W => Some((0, KeyCode::Up)),
+        KeyCode::A => Some((0, KeyCode::Left)),
+        KeyCode::S => Some((0, KeyCode::Down)),
+        KeyCode::D => Some((0, KeyCode::Right)),
+        KeyCode::Q | KeyCode::E | KeyCode::Z | KeyCode::C => Some((0, keycode)),
+        KeyCode::Up | KeyCode::Left | KeyCode::Down | KeyCode::Right => Some((1, keycode)),
+        KeyCode::Numpad7 => Some((1, KeyCode::Q)),
+        KeyCode::Numpad9 => Some((1, KeyCode::E)),
+        KeyCode::Numpad1 => Some((1, KeyCode::Z)),
+        KeyCode::Numpad3 => Some((1, KeyCode::C)),
+        _ => None,
+    }
+}
+
+/// Creates the boards of a game following the rules chosen in `settings` and the special tiles
+/// of `mode`, each board has its own spawns except in versus games where boards share a seed
+fn new_games(settings: &Settings, mode: GameMode) -> Vec<Game> {
+    if mode == GameMode::Daily {
+        return vec![Date::today().game()];
+    }
+    let rules = SpawnRules {
+        specials: mode.specials(),
+        ..settings.spawn_rules()
+    };
+    let seed = rand::random();
+    (0..mode.boards())
+        .map(|_| {
+            if mode.is_versus() {
+                Game::with_variant(seed, settings.variant(), rules.clone())
+            } else {
+                Game::init_first_elements(settings.variant(), rules.clone())
+            }
+        })
+        .collect()
+}
+
+/// Digit typed with `keycode`
+fn digit(keycode: KeyCode) -> Option<u32> {
+    let digit = match keycode {
+        KeyCode::Key0 | KeyCode::Numpad0 => 0,
+        KeyCode::Key1 | KeyCode::Numpad1 => 1,
+        KeyCode::Key2 | KeyCode::Numpad2 => 2,
+        KeyCode::Key3 | KeyCode::Numpad3 => 3,
+        KeyCode::Key4 | KeyCode::Numpad4 => 4,
+        KeyCode::Key5 | KeyCode::Numpad5 => 5,
+        KeyCode::Key6 | KeyCode::Numpad6 => 6,
+        KeyCode::Key7 | KeyCode::Numpad7 => 7,
+        KeyCode::Key8 | KeyCode::Numpad8 => 8,
+        KeyCode::Key9 | KeyCode::Numpad9 => 9,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+/// Puzzles exported in `dir`, in the order of their files, invalid files are skipped
+fn custom_puzzles(dir: &Path) -> Vec<Puzzle> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .flat_map(|path| match puzzle::load(path) {
+            Ok(puzzles) => puzzles,
+            Err(error) => {
+                eprintln!("Puzzles of {} could not be loaded: {error}", path.display());
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+/// Creates views of `games`: one board fills the board area, two boards are drawn side by side
+/// and four boards in a 2 x 2 grid
+fn board_views(games: Vec<Game>) -> Vec<BoardView> {
+    let count = games.len();
+    games
+        .into_iter()
+        .enumerate()
+        .map(|(i, game)| {
+            let (column, row) = ((i % 2) as f32, (i / 2) as f32);
+            match count {
+                1 => BoardView::new(game, Vec2::new(0., HUD_HEIGHT), 1.),
+                2 => BoardView::new(game, Vec2::new(250. * column, HUD_HEIGHT + 125.), 0.5),
+                _ => BoardView::new(game, Vec2::new(250. * column, HUD_HEIGHT + 250. * row), 0.5),
+            }
+        })
+        .collect()
+}
+
+/// Connection to a race server, from the lobby to the end of the race
+struct Online {
+    client: Client,
+    // index of the local board once the race started
+    player: Option<usize>,
+    // players of the lobby, then of the race
+    players: Vec<String>,
+    needed: usize,
+    // action of the local player waiting for its spawns to be sent to the server
+    pending: Option<i8>,
 }
 
 pub struct MainState {
-    game: Game,
+    // every board moved by the player, most modes have only one
+    boards: Vec<BoardView>,
     key: i8,
-    counter_1: u32,
-    counter_2: u32,
-    locations: Vec<Vec2>,
+    // board moved by `key` in a versus game, every board otherwise
+    player: Option<usize>,
     background: GameColor,
     game_colors: GameColors,
-    has_moved: bool,
-    before_grid: [u32; 16],
-    after_grid: [u32; 16],
-    static_locs: Vec<(usize, u32)>,
-    moves: Vec<(usize, usize, u32)>,
-    additions: Vec<(usize, u32)>,
-    direction: Option<[[usize; 4]; 4]>,
-    movements: Vec<Movement>,
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    settings: Settings,
+    statistics: Statistics,
+    audio: Audio,
+    screen: Screen,
+    mode: GameMode,
+    outcome: Outcome,
+    won: bool,
+    // boards before each action, for undo
+    history: Vec<Vec<Game>>,
+    undos: u32,
+    clock: Clock,
+    focused: bool,
+    // whether the current game is already stored in statistics
+    recorded: bool,
+    online: Option<Online>,
+    // kept open since the clipboard of some systems is emptied when it is closed
+    clipboard: Option<arboard::Clipboard>,
+    puzzles: Vec<Puzzle>,
+    // index of the puzzle being played
+    puzzle: Option<usize>,
+    progress: PuzzleProgress,
+    // position being edited in the level editor
+    editor: Position,
+    // result of the last command of the editor
+    note: Option<String>,
+    // analysis of the game shown by the review
+    review: Option<Analysis>,
+    // network given on the command line, rating boards for the review and hints
+    network: Option<Arc<Network>>,
+    // action suggested for the board holding this grid
+    hint: Option<([u32; 16], i8)>,
+    // bot playing the board instead of the player, its games are not recorded
+    bot: Option<Bot>,
 }
 
 impl MainState {
-    pub fn new() -> Self {
-        let game = Game::init_first_elements();
+    pub fn new(ctx: &mut Context, options: Options) -> Self {
+        let config_dir = ctx.fs.user_config_dir().to_path_buf();
+        let data_dir = ctx.fs.user_data_dir().to_path_buf();
+        let mut settings = Settings::load(&config_dir);
+        if let Some(spawner) = options.spawner {
+            settings.spawner = spawner;
+        }
+        let statistics = Statistics::load(&data_dir);
+        let progress = PuzzleProgress::load(&data_dir);
+        let puzzles = [
+            puzzle::bundled(),
+            custom_puzzles(&data_dir.join(PUZZLES_DIR)),
+        ]
+        .concat();
+        let bot = (options.bot.as_deref().filter(|_| options.watch))
+            .and_then(|command| cli::launch_bot(command, options.timeout));
+        // a game started from a position or played by a bot leaves the saved game for the next
+        // launch
+        let position = options.position;
+        let saved = SavedGame::load(&data_dir).filter(|saved| {
+            let elapsed = Duration::from_secs_f64(saved.duration);
+            let games = [&saved.game].into_iter().chain(saved.others.iter());
+            position.is_none()
+                && bot.is_none()
+                && saved.mode.outcome_of_boards(games, elapsed) == Outcome::Playing
+        });
+        let (games, mode, clock, undos, won) = match saved {
+            Some(saved) => (
+                [saved.game].into_iter().chain(saved.others).collect(),
+                saved.mode,
+                Clock::from_elapsed(Duration::from_secs_f64(saved.duration)),
+                saved.undos,
+                saved.won,
+            ),
+            None => (
+                (position.clone().map(|game| vec![game]))
+                    .unwrap_or_else(|| new_games(&settings, GameMode::Classic)),
+                GameMode::Classic,
+                Clock::default(),
+                0,
+                false,
+            ),
+        };
+        let audio = Audio::new(ctx, &settings);
+        let network = (options.network.as_deref()).and_then(cli::load_network);
+        let online = options.connect.and_then(|address| {
+            let name = options.name.unwrap_or_else(|| String::from("player"));
+            match Client::connect(&address, &name) {
+                Ok(client) => Some(Online {
+                    client,
+                    player: None,
+                    players: Vec::new(),
+                    needed: 0,
+                    pending: None,
+                }),
+                Err(error) => {
+                    eprintln!("Could not connect to {address}: {error}");
+                    None
+                }
+            }
+        });
 
         Self {
-            before_grid: game.copy_grid(),
-            after_grid: game.copy_grid(),
-            game,
-            counter_1: NB_I as u32 + 1, // for movement animations
-            counter_2: NB_I as u32 + 1, // for addition animations
+            boards: board_views(games),
             key: 0,
-            locations: (0..4)
-                .flat_map(|i| {
-                    (0..4).map(move |j| Vec2::new((15 + 121 * j) as f32, (15 + 121 * i) as f32))
-                })
-                .collect(),
+            player: None,
             background: GameColor::from(BACKGROUND),
             game_colors: GameColors::new(),
-            has_moved: false,
-            moves: Vec::new(),
-            additions: Vec::new(),
-            direction: None,
-            movements: Vec::new(),
-            static_locs: Vec::new(),
+            config_dir,
+            data_dir,
+            settings,
+            statistics,
+            audio,
+            screen: Screen::Board,
+            mode,
+            outcome: Outcome::Playing,
+            won,
+            history: Vec::new(),
+            undos,
+            clock,
+            focused: true,
+            // like positions of the editor, a game given in text notation is not recorded
+            recorded: position.is_some() || bot.is_some(),
+            online,
+            clipboard: None,
+            puzzles,
+            puzzle: None,
+            progress,
+            editor: Position::default(),
+            note: None,
+            review: None,
+            network: network.map(Arc::new),
+            hint: None,
+            bot,
         }
     }
 
-    /// Resets animation
-    fn reset_animations(&mut self) {
-        self.moves.clear();
-        self.additions.clear();
-        self.movements.clear();
-        self.static_locs.clear();
+    /// Games of every board
+    fn games(&self) -> impl Iterator<Item = &Game> {
+        self.boards.iter().map(|board| &board.game)
     }
 
-    /// Updates static locations by comparing the grid before and after an action
-    fn update_static_locations(&mut self) {
-        self.static_locs = self
-            .before_grid
-            .iter()
-            .zip(self.after_grid.iter())
-            .enumerate()
-            .filter_map(|(loc_i, (&before, &after))| {
-                if before == after && before != 0 {
-                    Some((loc_i, before))
-                } else {
-                    None
+    /// Total score of every board
+    fn score(&self) -> u32 {
+        self.games().map(|game| game.score()).sum()
+    }
+
+    /// Number of actions played, a board does not count an action which cannot move its tiles
+    fn moves(&self) -> u32 {
+        self.games().map(|game| game.moves()).max().unwrap_or(0)
+    }
+
+    /// Highest tile of every board
+    fn max_tile(&self) -> u32 {
+        self.games().map(|game| game.max_tile()).max().unwrap_or(0)
+    }
+
+    /// Checks if an animation is running on a board
+    fn is_animating(&self) -> bool {
+        self.boards.iter().any(|board| board.is_animating())
+    }
+
+    /// Stores the current game in statistics if it was played and not stored yet
+    fn record_game(&mut self) {
+        let online = matches!(self.mode, GameMode::OnlineRace { .. });
+        if self.recorded || self.moves() == 0 || online {
+            return;
+        }
+        // puzzles are tracked by the puzzle progress instead of statistics
+        if let (GameMode::Puzzle { .. }, Some(index)) = (self.mode, self.puzzle) {
+            if self.outcome == Outcome::Won {
+                let name = &self.puzzles[index].name;
+                if let Err(error) = self.progress.record(&self.data_dir, name, self.moves()) {
+                    eprintln!("Puzzle progress could not be saved: {error}");
                 }
-            })
-            .collect();
-    }
-
-    /// Updates `self.moves` for movements to animate and `self.additions` for additions to
-    /// animate
-    fn update_moves(&mut self, directions: [[usize; 4]; 4]) {
-        for direction in directions {
-            let before = direction.map(|i| self.before_grid[i]);
-            let after = direction.map(|i| self.after_grid[i]);
-            self.moving(before, after, direction);
-        }
-    }
-
-    /// Fills `self.moves` and `self.additions` based on one layer of the grid before and after
-    /// an action
-    fn moving(&mut self, before: [u32; 4], after: [u32; 4], direction: [usize; 4]) {
-        let mut var_i: i8 = 3;
-        let mut before = before;
-        while var_i > -1 {
-            let i = var_i as usize;
-            if after[i] == 0 {
-                break;
-            } else if after[i] == before[i] {
-                var_i -= 1;
-                continue;
-            }
-            let reference = after[i];
-            let mut var_j = var_i.clone();
-            while var_j > -1 {
-                let j = var_j as usize;
-                let item = before[j];
-                if item == reference {
-                    self.moves.push((direction[j], direction[i], before[j]));
-                    break;
-                } else if item != 0 && item < reference {
-                    let mut k = 1;
-                    let mut found = false;
-                    while k < j + 1 {
-                        if before[j - k] != 0 && before[j - k] != item {
-                            break;
-                        } else if before[j - k] == item {
-                            self.additions.push((direction[i], reference));
-                            if i != j {
-                                self.moves.push((direction[j], direction[i], item));
-                            }
-                            self.moves.push((direction[j - k], direction[i], item));
-                            let mut idx = 0;
-                            before = before.map(|x| {
-                                let r = if idx < j - k { x } else { 0 };
-                                idx += 1;
-                                r
-                            });
-                            found = true;
-                            break;
-                        }
-                        k += 1;
-                    }
-                    if found {
-                        break;
+            }
+            self.recorded = true;
+            return;
+        }
+        let first = &self.boards[0].game;
+        // a versus game is recorded with the board of its winner instead of the sum of both boards
+        let (score, max_tile) = if self.mode.is_versus() {
+            let best = match self.outcome {
+                Outcome::Winner(winner) => &self.boards[winner].game,
+                _ => self
+                    .games()
+                    .max_by_key(|game| game.score())
+                    .unwrap_or(first),
+            };
+            (best.score(), best.max_tile())
+        } else {
+            (self.score(), self.max_tile())
+        };
+        let record = GameRecord {
+            score,
+            max_tile,
+            moves: self.moves(),
+            duration: self.clock.elapsed().as_secs_f64(),
+            seed: first.seed(),
+            mode: self.mode,
+            undos: self.undos,
+            ruleset: first.ruleset(),
+            finished_at: stats::now(),
+            grid: (self.mode == GameMode::Daily).then(|| first.copy_grid()),
+        };
+        // replays prove the score of single board games, see the `verify` command
+        if let [board] = &self.boards[..] {
+            let name = format!("{}-{}.json", record.finished_at, record.seed);
+            let path = self.data_dir.join(REPLAYS_DIR).join(name);
+            if let Err(error) = Replay::from_game(&board.game).save(&path) {
+                eprintln!("Replay could not be saved: {error}");
+            }
+        }
+        if let Err(error) = self.statistics.record(&self.data_dir, record) {
+            eprintln!("Statistics could not be saved: {error}");
+        }
+        if let Err(error) = SavedGame::delete(&self.data_dir) {
+            eprintln!("Saved game could not be removed: {error}");
+        }
+        self.recorded = true;
+    }
+
+    /// Saves the game in progress so that it is resumed at the next launch
+    fn save_game(&self) {
+        let saved = SavedGame {
+            game: self.boards[0].game.clone(),
+            others: self.games().skip(1).cloned().collect(),
+            mode: self.mode,
+            duration: self.clock.elapsed().as_secs_f64(),
+            undos: self.undos,
+            won: self.won,
+        };
+        if let Err(error) = saved.save(&self.data_dir) {
+            eprintln!("Game could not be saved: {error}");
+        }
+    }
+
+    /// Replaces the current game by `games` played with `mode` and clears everything related to
+    /// the previous one
+    fn start_game(&mut self, games: Vec<Game>, mode: GameMode) {
+        self.record_game();
+        if let (Some(bot), [board]) = (&mut self.bot, &self.boards[..]) {
+            if bot.is_playing() {
+                let _ = bot.finish(&board.game, &Ending::Stopped);
+            }
+        }
+        self.boards = board_views(games);
+        self.mode = mode;
+        self.outcome = Outcome::Playing;
+        self.won = false;
+        self.history.clear();
+        self.undos = 0;
+        self.clock = Clock::default();
+        self.recorded = self.bot.is_some();
+        self.puzzle = None;
+    }
+
+    /// Starts the puzzle number `index` from its starting position
+    fn start_puzzle(&mut self, index: usize) {
+        self.online = None;
+        let puzzle = &self.puzzles[index];
+        let mode = GameMode::Puzzle {
+            goal: puzzle.goal,
+            moves: puzzle.moves,
+        };
+        self.start_game(vec![puzzle.game()], mode);
+        self.puzzle = Some(index);
+    }
+
+    /// Starts a new game with `mode`, leaving the network race
+    ///
+    /// Once today's daily challenge is played, a classic game starts instead and its result is
+    /// shown.
+    fn new_game(&mut self, mode: GameMode) {
+        if let (GameMode::Puzzle { .. }, Some(index)) = (mode, self.puzzle) {
+            self.start_puzzle(index);
+            return;
+        }
+        self.online = None;
+        self.record_game();
+        let today = Date::today().seed();
+        let mode = match mode {
+            GameMode::Daily if self.statistics.daily(today).is_some() => {
+                self.screen = Screen::Daily(self.share_daily());
+                GameMode::Classic
+            }
+            GameMode::OnlineRace { .. } | GameMode::Puzzle { .. } => GameMode::Classic,
+            mode => mode,
+        };
+        self.start_game(new_games(&self.settings, mode), mode);
+    }
+
+    /// Summary of today's daily challenge, `None` if it was not played yet
+    fn daily_summary(&self) -> Option<String> {
+        let date = Date::today();
+        let record = self.statistics.daily(date.seed())?;
+        let grid = record.grid.unwrap_or_default();
+        let summary = daily::summary(date, record.score, record.max_tile, record.moves, grid);
+        Some(summary)
+    }
+
+    /// Copies the summary of today's daily challenge to the clipboard, returns `true` if it did
+    fn share_daily(&mut self) -> bool {
+        match self.daily_summary() {
+            Some(summary) => self.copy(summary),
+            None => false,
+        }
+    }
+
+    /// Clipboard of the system, opened the first time it is used
+    fn clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(error) => eprintln!("Clipboard could not be opened: {error}"),
+            }
+        }
+        self.clipboard.as_mut()
+    }
+
+    /// Copies `text` to the clipboard, returns `true` if it did
+    fn copy(&mut self, text: String) -> bool {
+        let Some(clipboard) = self.clipboard() else {
+            return false;
+        };
+        match clipboard.set_text(text) {
+            Ok(()) => true,
+            Err(error) => {
+                eprintln!("Text could not be copied: {error}");
+                false
+            }
+        }
+    }
+
+    /// Copies the first board in text notation to the clipboard
+    fn copy_position(&mut self) {
+        let notation = self.boards[0].game.to_string();
+        println!("{notation}");
+        self.copy(notation);
+    }
+
+    /// Starts a game from the position in text notation found in the clipboard
+    fn paste_position(&mut self) {
+        let Some(clipboard) = self.clipboard() else {
+            return;
+        };
+        let notation = match clipboard.get_text() {
+            Ok(notation) => notation,
+            Err(error) => {
+                eprintln!("Clipboard could not be read: {error}");
+                return;
+            }
+        };
+        match notation.parse() {
+            Ok(game) => self.play_from(game),
+            Err(error) => eprintln!("Invalid position `{}`: {error}", notation.trim()),
+        }
+    }
+
+    /// Restores the game as it was before the last action
+    ///
+    /// Games compared with other players cannot be undone: versus, network races and the daily
+    /// challenge whose summary is shared.
+    fn undo(&mut self) {
+        let compared = matches!(self.mode, GameMode::OnlineRace { .. } | GameMode::Daily);
+        if self.is_finished() || self.mode.is_versus() || compared {
+            return;
+        }
+        if let Some(games) = self.history.pop() {
+            for (board, game) in self.boards.iter_mut().zip(games) {
+                board.set_game(game);
+            }
+            self.undos += 1;
+        }
+    }
+
+    /// Checks if the end condition of the mode is reached
+    fn is_finished(&self) -> bool {
+        self.outcome != Outcome::Playing
+    }
+
+    /// Updates the outcome of the game, plays the final sound and records the game when it ends
+    fn update_outcome(&mut self, ctx: &mut Context) {
+        // the server decides the end of network races
+        if self.is_finished() || matches!(self.mode, GameMode::OnlineRace { .. }) {
+            return;
+        }
+        let elapsed = self.clock.elapsed();
+        self.outcome = self.mode.outcome_of_boards(self.games(), elapsed);
+        match self.outcome {
+            Outcome::Playing => return,
+            Outcome::Won
+                if !matches!(
+                    self.mode,
+                    GameMode::Classic | GameMode::SpecialTiles | GameMode::MultiBoard { .. }
+                ) =>
+            {
+                self.audio.win(ctx, &self.settings)
+            }
+            Outcome::Winner(_) => self.audio.win(ctx, &self.settings),
+            _ => self.audio.gameover(ctx, &self.settings),
+        }
+        self.record_game();
+        if self.mode == GameMode::Daily {
+            self.screen = Screen::Daily(self.share_daily());
+        }
+    }
+
+    /// Items displayed above the board, they depend on the mode
+    fn hud_items(&self) -> [(&str, String); 3] {
+        let elapsed = self.clock.elapsed();
+        let score = ("SCORE", self.score().to_string());
+        let time = ("TIME", screens::format_duration(elapsed.as_secs_f64()));
+        match self.mode {
+            GameMode::Classic
+            | GameMode::Daily
+            | GameMode::Sprint
+            | GameMode::SpecialTiles
+            | GameMode::MultiBoard { .. } => [score, ("MOVES", self.moves().to_string()), time],
+            GameMode::TimeAttack { .. } => {
+                let left = self.mode.time_left(elapsed).unwrap_or_default();
+                let left = screens::format_duration(left.as_secs_f64());
+                [
+                    score,
+                    ("MOVES", self.moves().to_string()),
+                    ("TIME LEFT", left),
+                ]
+            }
+            GameMode::MoveLimited { moves, target } => [
+                score,
+                ("MOVES", format!("{}/{moves}", self.moves())),
+                ("TARGET", target.to_string()),
+            ],
+            GameMode::VersusRace { target, .. } => [
+                ("PLAYER 1", self.boards[0].game.score().to_string()),
+                ("PLAYER 2", self.boards[1].game.score().to_string()),
+                ("TARGET", target.to_string()),
+            ],
+            GameMode::Puzzle { goal, moves } => {
+                let goal = match goal {
+                    Goal::Tile(value) => ("TARGET", value.to_string()),
+                    Goal::Clear(tiles) => {
+                        let left = puzzle::tiles_left(&self.boards[0].game);
+                        ("TILES", format!("{left}/{tiles}"))
                     }
+                    Goal::Score(score) => ("TARGET", score.to_string()),
+                };
+                [
+                    ("SCORE", self.score().to_string()),
+                    ("MOVES", format!("{}/{moves}", self.moves())),
+                    goal,
+                ]
+            }
+            GameMode::OnlineRace { target } => {
+                let player = self.online.as_ref().and_then(|online| online.player);
+                let player = player.unwrap_or(0);
+                let rival = (self.games().enumerate())
+                    .filter(|&(i, _)| i != player)
+                    .map(|(_, game)| game.score())
+                    .max()
+                    .unwrap_or(0);
+                [
+                    ("YOU", self.boards[player].game.score().to_string()),
+                    ("BEST RIVAL", rival.to_string()),
+                    ("TARGET", target.to_string()),
+                ]
+            }
+            GameMode::VersusTime { .. } => {
+                let left = self.mode.time_left(elapsed).unwrap_or_default();
+                [
+                    ("PLAYER 1", self.boards[0].game.score().to_string()),
+                    ("PLAYER 2", self.boards[1].game.score().to_string()),
+                    ("TIME LEFT", screens::format_duration(left.as_secs_f64())),
+                ]
+            }
+        }
+    }
+
+    /// Message displayed at the end of the game
+    fn end_message(&self) -> &str {
+        match (self.outcome, self.mode) {
+            (Outcome::Won, GameMode::MoveLimited { .. } | GameMode::Sprint) => "You Win!",
+            (Outcome::Won, GameMode::Puzzle { .. }) => "Solved!",
+            (Outcome::Winner(winner), GameMode::OnlineRace { .. }) => {
+                let player = self.online.as_ref().and_then(|online| online.player);
+                if player == Some(winner) {
+                    "You Win!"
+                } else {
+                    "You Lose"
                 }
-                var_j -= 1;
             }
-            var_i -= 1;
+            (Outcome::Winner(0), _) => "P1 Wins!",
+            (Outcome::Winner(_), _) => "P2 Wins!",
+            (Outcome::Draw, _) => "Draw",
+            (_, GameMode::TimeAttack { .. }) if !self.games().any(Game::is_gameover) => "Time's Up",
+            _ => "Game Over",
         }
     }
 
-    /// Returns a vector of prepared information for movement animations
-    fn prepare_movements(&self) -> Vec<Movement> {
-        self.moves
+    /// Handles keys of the mode selection screen
+    fn modes_key(&mut self, keycode: KeyCode, selected: usize) {
+        self.screen = match keycode {
+            KeyCode::Up => Screen::Modes((selected + MODES.len() - 1) % MODES.len()),
+            KeyCode::Down => Screen::Modes((selected + 1) % MODES.len()),
+            KeyCode::Return => {
+                self.screen = Screen::Board;
+                self.new_game(MODES[selected]);
+                return;
+            }
+            KeyCode::Escape | KeyCode::G => Screen::Board,
+            _ => Screen::Modes(selected),
+        };
+    }
+
+    /// Handles keys of the puzzle selection screen
+    fn puzzles_key(&mut self, keycode: KeyCode, selected: usize) {
+        let count = self.puzzles.len();
+        self.screen = match keycode {
+            KeyCode::Up => Screen::Puzzles((selected + count - 1) % count),
+            KeyCode::Down => Screen::Puzzles((selected + 1) % count),
+            KeyCode::Return => {
+                self.start_puzzle(selected);
+                Screen::Board
+            }
+            KeyCode::Escape | KeyCode::K => Screen::Board,
+            _ => Screen::Puzzles(selected),
+        };
+    }
+
+    /// Index of the current mode in `MODES`
+    fn mode_index(&self) -> usize {
+        MODES
             .iter()
-            .map(|&(start, end, number)| {
-                let start = self.locations[start];
-                let end = self.locations[end];
-                let diff = end - start;
-                let q = Vec2::new(diff[0] / NB_I, diff[1] / NB_I);
-                let r = Vec2::new(diff[0] % NB_I, diff[1] / NB_I) / NB_I;
-                Movement {
-                    number: number as usize,
-                    start,
-                    q,
-                    r,
-                }
-            })
-            .collect()
+            .position(|&mode| mode == self.mode)
+            .unwrap_or(0)
+    }
+    /// Saves settings and applies them to the audio
+    fn apply_settings(&mut self, ctx: &mut Context) {
+        if let Err(error) = self.settings.save(&self.config_dir) {
+            eprintln!("Settings could not be saved: {error}");
+        }
+        self.audio.apply(ctx, &self.settings);
     }
 
-    /// Prepare movement animations and addition animations
-    fn prepare_animations(&mut self, directions: [[usize; 4]; 4]) {
-        self.update_static_locations();
-        self.update_moves(directions);
-        self.movements = self.prepare_movements();
-        self.counter_1 = 0;
-        self.counter_2 = 0;
+    /// Handles keys of the settings screen
+    fn settings_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        let entry = ENTRIES[selected];
+        self.screen = match keycode {
+            KeyCode::Up => Screen::Settings((selected + ENTRIES.len() - 1) % ENTRIES.len()),
+            KeyCode::Down => Screen::Settings((selected + 1) % ENTRIES.len()),
+            KeyCode::Left | KeyCode::Right | KeyCode::Return => {
+                let step = if keycode == KeyCode::Left { -1 } else { 1 };
+                self.settings.adjust(entry, step);
+                self.apply_settings(ctx);
+                Screen::Settings(selected)
+            }
+            KeyCode::Escape | KeyCode::O => Screen::Board,
+            _ => Screen::Settings(selected),
+        };
     }
 
-    /// Animates one frame of movement animations
-    fn animate_movements(&self, ctx: &mut Context, i: u32) -> GameResult<()> {
-        let mut canvas = Canvas::from_frame(ctx, self.background.rgb);
+    /// Handles the messages of the race server and notices when it closes the connection
+    fn poll_online(&mut self, ctx: &mut Context) {
+        let Some(online) = &mut self.online else {
+            return;
+        };
+        let messages = online.client.poll();
+        let connected = online.client.is_connected();
+        for message in messages {
+            self.server_message(ctx, message);
+        }
+        let Some(online) = &self.online else {
+            return;
+        };
+        if !connected && online.player.is_none() {
+            eprintln!("Connection to the server lost before the race");
+            self.online = None;
+        } else if !connected && !self.is_finished() {
+            eprintln!("Connection to the server lost");
+            self.outcome = Outcome::Lost;
+            self.audio.gameover(ctx, &self.settings);
+        }
+    }
 
-        for &location in self.locations.iter() {
-            let game_color = &self.game_colors[&0];
-            draw_cell(&mut canvas, ctx, 0, game_color, location)?;
+    /// Applies a message of the race server
+    fn server_message(&mut self, ctx: &mut Context, message: ServerMessage) {
+        let Some(online) = &mut self.online else {
+            return;
+        };
+        match message {
+            ServerMessage::Lobby { players, needed } => {
+                online.players = players;
+                online.needed = needed;
+            }
+            ServerMessage::Start {
+                seed,
+                variant,
+                rules,
+                target,
+                players,
+                you,
+            } => {
+                online.player = Some(you);
+                let games = players
+                    .iter()
+                    .map(|_| Game::with_variant(seed, variant, rules.clone()))
+                    .collect();
+                online.players = players;
+                self.start_game(games, GameMode::OnlineRace { target });
+                self.screen = Screen::Board;
+            }
+            // opponents are replayed from the seed, the local board is already up to date
+            ServerMessage::Moved { player, action, .. } if online.player != Some(player) => {
+                // spawns of the previous move come first when both moves arrive together
+                self.boards[player].spawn();
+                if self.boards[player].play(action).is_none() {
+                    eprintln!("Move of {} could not be replayed", online.players[player]);
+                }
+            }
+            ServerMessage::Moved { .. } => (),
+            ServerMessage::Rejected { reason } => eprintln!("Rejected by the server: {reason}"),
+            ServerMessage::Left { player } => {
+                eprintln!("{} left the race", online.players[player]);
+            }
+            ServerMessage::Finished { winner } => {
+                if winner.is_some() && winner == online.player {
+                    self.audio.win(ctx, &self.settings);
+                } else {
+                    self.audio.gameover(ctx, &self.settings);
+                }
+                self.outcome = winner.map_or(Outcome::Draw, Outcome::Winner);
+            }
         }
+    }
 
-        for &(pos, number) in self.static_locs.iter() {
-            let location = self.locations[pos];
-            let game_color = &self.game_colors[&number];
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Opens the level editor at the position of the first board
+    fn open_editor(&mut self) {
+        self.editor = Position::from_game(&self.boards[0].game);
+        self.note = None;
+        self.screen = Screen::Editor {
+            cursor: 0,
+            typing: false,
+        };
+    }
+
+    /// View of the position of the editor
+    fn editor_board(&self) -> BoardView {
+        let game = Game::from_position(self.editor.grid, self.editor.variant, Vec::new());
+        board_views(vec![game]).remove(0)
+    }
+
+    /// Items displayed above the board in the editor, the result of the last command replaces the
+    /// value of the selected cell
+    fn editor_items(&self, cursor: usize) -> [(&str, String); 3] {
+        let value = match Cell::from(self.editor.grid[cursor]) {
+            Cell::Empty => String::from("-"),
+            Cell::Number(number) => number.to_string(),
+            Cell::Stone => String::from("STONE"),
+            Cell::Wildcard => String::from("WILD"),
+            Cell::Bomb => String::from("BOMB"),
+        };
+        let variant = self.editor.variant;
+        [
+            ("RULESET", variant.ruleset.name().to_uppercase()),
+            ("BOARD", variant.topology.name().to_uppercase()),
+            match &self.note {
+                Some(note) => ("EDITOR", note.clone()),
+                None => ("CELL", value),
+            },
+        ]
+    }
+
+    /// Path of the position saved in `slot`
+    fn position_path(&self, slot: u32) -> PathBuf {
+        self.data_dir
+            .join(POSITIONS_DIR)
+            .join(format!("{slot}.json"))
+    }
+
+    /// Starts a classic game from `game`, set up in the editor or given in text notation
+    fn play_from(&mut self, game: Game) {
+        self.online = None;
+        self.start_game(vec![game], GameMode::Classic);
+        // the position is not dealt by the seed, so the game is neither recorded nor replayed
+        self.recorded = true;
+        self.screen = Screen::Board;
+    }
+
+    /// Saves the position of the editor as a puzzle in the puzzles directory and adds it to the
+    /// puzzles
+    fn export_puzzle(&mut self) -> String {
+        let now = stats::now();
+        let puzzle = self.editor.puzzle(&format!("Custom {now}"));
+        let path = self.data_dir.join(PUZZLES_DIR).join(format!("{now}.json"));
+        match puzzle::save(std::slice::from_ref(&puzzle), &path) {
+            Ok(()) => {
+                println!("Puzzle exported to {}", path.display());
+                self.puzzles.push(puzzle);
+                String::from("EXPORTED")
+            }
+            Err(error) => {
+                eprintln!("Puzzle could not be exported: {error}");
+                String::from("FAILED")
+            }
         }
+    }
 
-        for movement in self.movements.iter() {
-            let location = movement.start + (i as f32) * (movement.q + movement.r);
-            let number = movement.number as u32;
-            let game_color = &self.game_colors[&number];
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Handles keys of the level editor, the command keys of the editor follow `cursor`, the
+    /// selected cell
+    ///
+    /// Ctrl and a digit save the position in the slot of the digit, Alt and a digit load it.
+    fn editor_key(&mut self, keycode: KeyCode, mods: KeyMods, cursor: usize, typing: bool) {
+        self.note = None;
+        let mut typed = false;
+        match (keycode, digit(keycode)) {
+            (_, Some(slot)) if mods.contains(KeyMods::CTRL) => {
+                let note = match self.editor.save(&self.position_path(slot)) {
+                    Ok(()) => format!("SAVED {slot}"),
+                    Err(error) => {
+                        eprintln!("Position could not be saved: {error}");
+                        String::from("FAILED")
+                    }
+                };
+                self.note = Some(note);
+            }
+            (_, Some(slot)) if mods.contains(KeyMods::ALT) => {
+                let note = match Position::load(&self.position_path(slot)) {
+                    Ok(position) => {
+                        self.editor = position;
+                        format!("LOADED {slot}")
+                    }
+                    Err(error) => {
+                        eprintln!("Position could not be loaded: {error}");
+                        format!("EMPTY {slot}")
+                    }
+                };
+                self.note = Some(note);
+            }
+            (_, Some(digit)) => {
+                self.editor.type_digit(cursor, digit, typing);
+                typed = true;
+            }
+            (KeyCode::Space | KeyCode::Equals | KeyCode::Plus | KeyCode::NumpadAdd, _) => {
+                self.editor.cycle(cursor, true)
+            }
+            (KeyCode::Minus | KeyCode::NumpadSubtract, _) => self.editor.cycle(cursor, false),
+            (KeyCode::Back | KeyCode::Delete, _) => self.editor.grid[cursor] = 0,
+            // a modifier avoids emptying the whole board by mistake
+            (KeyCode::C, _) if mods.contains(KeyMods::CTRL | KeyMods::SHIFT) => {
+                self.editor.clear();
+                self.note = Some(String::from("CLEARED"));
+            }
+            (KeyCode::R, _) => {
+                let variant = &mut self.editor.variant;
+                variant.ruleset = settings::cycle(&RULESETS, variant.ruleset, 1);
+            }
+            (KeyCode::T, _) => {
+                let variant = &mut self.editor.variant;
+                variant.topology = settings::cycle(&TOPOLOGIES, variant.topology, 1);
+            }
+            (KeyCode::F, _) => {
+                let copied = self.copy(self.editor.fixture());
+                self.note = Some(String::from(if copied { "COPIED" } else { "FAILED" }));
+            }
+            (KeyCode::X, _) => self.note = Some(self.export_puzzle()),
+            (KeyCode::Return, _) => {
+                let game = self
+                    .editor
+                    .game(rand::random(), self.settings.spawn_rules());
+                self.play_from(game);
+                return;
+            }
+            (KeyCode::Escape | KeyCode::B, _) => {
+                self.screen = Screen::Board;
+                return;
+            }
+            _ => (),
         }
+        let cursor = match keycode {
+            KeyCode::Left if !cursor.is_multiple_of(4) => cursor - 1,
+            KeyCode::Right if cursor % 4 < 3 => cursor + 1,
+            KeyCode::Up if cursor >= 4 => cursor - 4,
+            KeyCode::Down if cursor < 12 => cursor + 4,
+            _ => cursor,
+        };
+        self.screen = Screen::Editor {
+            cursor,
+            typing: typed,
+        };
+    }
 
-        for &(pos, number) in self.additions.iter() {
-            let location = self.locations[pos];
-            let game_color = &self.game_colors[&number].scale(0., NB_I);
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Handles a click in the level editor: the left button selects a cell and gives it the next
+    /// value, the right button the previous value
+    fn editor_click(&mut self, button: MouseButton, point: Vec2) {
+        let Some(cell) = self.editor_board().cell_at(point) else {
+            return;
+        };
+        match button {
+            MouseButton::Left => self.editor.cycle(cell, true),
+            MouseButton::Right => self.editor.cycle(cell, false),
+            _ => (),
         }
+        self.note = None;
+        self.screen = Screen::Editor {
+            cursor: cell,
+            typing: false,
+        };
+    }
 
-        canvas.finish(ctx)?;
-        Ok(())
+    /// Lets the bot play the board without blocking the window: the board is sent once the
+    /// animations end and the action of the bot is played as soon as it answers
+    ///
+    /// The bot plays single boards only. A bot which fails is stopped and the player takes over.
+    fn play_bot(&mut self) {
+        let finished = self.is_finished();
+        let busy = self.is_animating() || self.key != 0 || !matches!(self.screen, Screen::Board);
+        let (Some(bot), [board]) = (&mut self.bot, &self.boards[..]) else {
+            return;
+        };
+        let game = &board.game;
+        let answer = match (finished, busy) {
+            (true, _) if bot.is_playing() => bot.finish(game, &Ending::Over).map(|()| None),
+            (true, _) | (false, true) => return,
+            (false, false) => bot.poll(game),
+        };
+        match answer {
+            Ok(Some(action)) => (self.key, self.player) = (action, None),
+            Ok(None) => (),
+            Err(error) => {
+                eprintln!("Bot stopped: {error}");
+                let _ = bot.finish(game, &Ending::from(&error));
+                self.bot = None;
+            }
+        }
     }
 
-    /// Animates one frame of addition animations
-    fn animate_additions(&self, ctx: &mut Context, i: u32) -> GameResult<()> {
-        let mut canvas = Canvas::from_frame(ctx, self.background.rgb);
-        for (pos, &number) in self.after_grid.iter().enumerate() {
-            let location = self.locations[pos];
-            let game_color = &self.game_colors[&number];
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Solver looking `depth` actions ahead, rating boards with the network if there is one and
+    /// if it rates the game
+    fn solver(&self, depth: u32) -> Solver {
+        Solver {
+            network: self.network.clone(),
+            ..Solver::new(depth)
         }
+    }
 
-        for &(pos, number) in self.additions.iter() {
-            let location = self.locations[pos];
-            let game_color = &self.game_colors[&number].scale(i as f32, NB_I);
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Suggests the best action of the solver for the board, which is shown until it moves
+    ///
+    /// Hints are only given for a single board, outside of network races.
+    fn show_hint(&mut self) {
+        let [board] = &self.boards[..] else {
+            return;
+        };
+        if self.online.is_some() {
+            return;
         }
-        canvas.finish(ctx)?;
-        Ok(())
+        let game = &board.game;
+        self.hint =
+            (self.solver(HINT_DEPTH).best_action(game)).map(|action| (game.copy_grid(), action));
     }
 
-    /// Draws the game over
-    fn draw_gameover(&self, ctx: &mut Context) -> GameResult<()> {
-        let mut canvas = Canvas::from_frame(ctx, self.background.rgb);
-        let grid = self.game.copy_grid();
-        for (&location, number) in self.locations.iter().zip(grid) {
-            let game_color = &self.game_colors[&number];
-            draw_cell(&mut canvas, ctx, number, game_color, location)?;
+    /// Name of the action suggested for the current board
+    fn current_hint(&self) -> Option<String> {
+        let (grid, action) = self.hint?;
+        let [board] = &self.boards[..] else {
+            return None;
+        };
+        let game = &board.game;
+        (game.copy_grid() == grid).then(|| game.topology().action_name(action).to_uppercase())
+    }
+
+    /// Analyzes the game of the board and opens the review at its last move
+    ///
+    /// Games which do not follow their seed from an empty board (puzzles, positions) cannot be
+    /// replayed, so they are not reviewed.
+    fn open_review(&mut self) {
+        let [board] = &self.boards[..] else {
+            return;
+        };
+        let replay = Replay::from_game(&board.game);
+        let grid = board.game.copy_grid();
+        if replay.actions.is_empty() || replay.replay().map(|game| game.copy_grid()) != Ok(grid) {
+            eprintln!("Only games dealt by their seed can be reviewed");
+            return;
         }
+        match Analysis::new(&replay, &self.solver(REVIEW_DEPTH)) {
+            Ok(analysis) => {
+                self.screen = Screen::Review(analysis.moves.len() - 1);
+                self.review = Some(analysis);
+            }
+            Err(error) => eprintln!("Game could not be analyzed: {error}"),
+        }
+    }
+
+    /// View of the position before the move number `index` of the review
+    fn review_board(&self, index: usize) -> Option<BoardView> {
+        let analysis = &self.review.as_ref()?.moves[index];
+        let game = analysis.position.parse().ok()?;
+        Some(board_views(vec![game]).remove(0))
+    }
+
+    /// Items displayed above the board in the review: the move, the played and the best actions
+    fn review_items(&self, index: usize) -> Option<[(&str, String); 3]> {
+        let review = self.review.as_ref()?;
+        let analysis = &review.moves[index];
+        // diagonals are written with their initials to fit in the boxes
+        let name = |action| {
+            let name = review.variant.topology.action_name(action).to_uppercase();
+            match name.split_once(' ') {
+                Some((vertical, horizontal)) => format!("{}{}", &vertical[..1], &horizontal[..1]),
+                None => name,
+            }
+        };
+        let title = if analysis.blunder { "BLUNDER" } else { "MOVE" };
+        Some([
+            (title, format!("{}/{}", index + 1, review.moves.len())),
+            ("PLAYED", name(analysis.played)),
+            ("BEST", name(analysis.best)),
+        ])
+    }
+
+    /// Handles keys of the review: left and right go through the moves, up and down jump to the
+    /// previous and next blunders, enter plays again from the position
+    fn review_key(&mut self, keycode: KeyCode, index: usize) {
+        let Some(review) = &self.review else {
+            self.screen = Screen::Board;
+            return;
+        };
+        let blunders: Vec<usize> = review.blunders().map(|(blunder, _)| blunder).collect();
+        let index = match keycode {
+            KeyCode::Left => index.saturating_sub(1),
+            KeyCode::Right => (index + 1).min(review.moves.len() - 1),
+            KeyCode::Home => 0,
+            KeyCode::End => review.moves.len() - 1,
+            KeyCode::Up => (blunders.iter().rev())
+                .find(|&&blunder| blunder < index)
+                .map_or(index, |&blunder| blunder),
+            KeyCode::Down => (blunders.iter())
+                .find(|&&blunder| blunder > index)
+                .map_or(index, |&blunder| blunder),
+            KeyCode::Return => {
+                match review.moves[index].position.parse() {
+                    Ok(game) => self.play_from(game),
+                    Err(error) => eprintln!("Invalid position: {error}"),
+                }
+                return;
+            }
+            KeyCode::Escape | KeyCode::A => {
+                self.screen = Screen::Board;
+                return;
+            }
+            _ => index,
+        };
+        self.screen = Screen::Review(index);
+    }
 
+    /// Draws the game over
+    fn draw_gameover(&self, canvas: &mut Canvas, ctx: &mut Context) -> GameResult<()> {
         let text = Text::new(
-            TextFragment::new("Game Over")
+            TextFragment::new(self.end_message())
                 .font("ClearSans-Bold")
                 .color(as_color([255, 0, 0]))
                 .scale(PxScale::from(78.)),
         );
 
         let [w, h] = text.dimensions(ctx).unwrap().center().into();
-        canvas.draw(&text, Vec2::new(250. - w, 250. - h));
-        canvas.finish(ctx)?;
-
+        canvas.draw(&text, Vec2::new(250. - w, HUD_HEIGHT + 250. - h));
         Ok(())
     }
 }
 
 impl event::EventHandler<ggez::GameError> for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        if !self.game.is_gameover() && self.has_moved {
-            self.game.random();
-            self.has_moved = false;
-        }
-        if !self.game.is_gameover() {
-            if self.key != 0 {
-                if self.game.partial_move(self.key) {
-                    self.before_grid = self.game.copy_grid();
-                    self.game.action(self.key);
-                    self.after_grid = self.game.copy_grid();
-                    self.has_moved = true;
-                    self.direction = Some(self.game.direction(self.key));
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let playing = matches!(self.screen, Screen::Board) && !self.is_finished();
+        self.clock.set_running(playing && self.focused);
+        self.poll_online(ctx);
+        self.update_outcome(ctx);
+        if !self.is_finished() {
+            let mut spawned = false;
+            for board in self.boards.iter_mut() {
+                spawned |= board.spawn();
+            }
+            if spawned {
+                self.audio.spawn(ctx, &self.settings);
+                self.update_outcome(ctx);
+            }
+        }
+        self.play_bot();
+        if let Some(online) = &mut self.online {
+            if let (Some(player), Some(action)) = (online.player, online.pending.take()) {
+                let grid = self.boards[player].game.copy_grid();
+                if let Err(error) = online.client.send_move(action, grid) {
+                    eprintln!("Move could not be sent to the server: {error}");
                 }
-                self.key = 0;
             }
         }
+        if !self.is_finished() && self.key != 0 {
+            let before: Vec<Game> = self.games().cloned().collect();
+            let (key, player) = (self.key, self.player);
+            let reports: Vec<_> = self
+                .boards
+                .iter_mut()
+                .enumerate()
+                .filter(|&(i, _)| player.is_none_or(|player| player == i))
+                .filter_map(|(i, board)| board.play(key).map(|report| (i, report)))
+                .collect();
+            // big merges of a player drop blockers on the board of the opponent
+            for (i, report) in reports.iter() {
+                let blockers = self.mode.blockers(report, self.boards[*i].game.ruleset());
+                let opponent = (i + 1) % self.boards.len();
+                let opponent = &mut self.boards[opponent].game;
+                for _ in 0..blockers {
+                    opponent.add_blocker(&mut rand::rng());
+                }
+            }
+            if let Some(online) = &mut self.online {
+                if reports.iter().any(|&(i, _)| online.player == Some(i)) {
+                    online.pending = Some(key);
+                }
+            }
+            if !reports.is_empty() {
+                self.history.push(before);
+                self.audio.slide(ctx, &self.settings);
+                let merged = reports
+                    .iter()
+                    .flat_map(|(_, report)| report.merges.iter())
+                    .map(|&(_, value)| value)
+                    .max();
+                if let Some(value) = merged {
+                    self.audio.merge(ctx, &self.settings, value);
+                }
+                let won = self
+                    .games()
+                    .any(|game| game.max_tile() >= game.ruleset().win_tile());
+                if !self.won && won {
+                    self.won = true;
+                    self.audio.win(ctx, &self.settings);
+                }
+            }
+            self.key = 0;
+        }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        if let Some(directions) = self.direction {
-            self.prepare_animations(directions);
-            self.direction = None;
-        } else if self.counter_1 <= NB_I as u32 {
-            self.animate_movements(ctx, self.counter_1)?;
-            self.counter_1 += 1;
-        } else if self.counter_2 <= NB_I as u32 {
-            self.animate_additions(ctx, self.counter_2)?;
-            self.counter_2 += 1;
-        } else if self.game.is_gameover() {
-            self.reset_animations();
-            self.draw_gameover(ctx)?;
-        } else {
-            self.reset_animations();
-            let mut canvas = Canvas::from_frame(ctx, self.background.rgb);
-            let grid = self.game.copy_grid();
-            for (&location, number) in self.locations.iter().zip(grid) {
-                let game_color = &self.game_colors[&number];
-                draw_cell(&mut canvas, ctx, number, game_color, location)?;
+        let mut canvas = Canvas::from_frame(ctx, self.background.rgb);
+        let color = self.background.font_color;
+        // the editor replaces the boards of the game
+        if let Screen::Editor { cursor, .. } = self.screen {
+            let items = self.editor_items(cursor);
+            screens::draw_hud(&mut canvas, ctx, &items, color)?;
+            let mut board = self.editor_board();
+            board.draw(&mut canvas, ctx, &self.game_colors)?;
+            board.draw_cursor(&mut canvas, ctx, cursor, color)?;
+            canvas.finish(ctx)?;
+            return Ok(());
+        }
+        // so does the review, with the evaluation bar of the move
+        if let Screen::Review(index) = self.screen {
+            if let (Some(items), Some(mut board)) =
+                (self.review_items(index), self.review_board(index))
+            {
+                screens::draw_hud(&mut canvas, ctx, &items, color)?;
+                board.draw(&mut canvas, ctx, &self.game_colors)?;
+                let analysis = &self.review.as_ref().unwrap().moves[index];
+                let (accuracy, blunder) = (analysis.accuracy(), analysis.blunder);
+                screens::draw_evaluation(&mut canvas, ctx, accuracy, blunder, color)?;
             }
             canvas.finish(ctx)?;
+            return Ok(());
+        }
+        let mut items = self.hud_items();
+        if let Some(hint) = self.current_hint() {
+            items[2] = ("HINT", hint);
+        }
+        screens::draw_hud(&mut canvas, ctx, &items, color)?;
+        for board in self.boards.iter_mut() {
+            board.draw(&mut canvas, ctx, &self.game_colors)?;
+        }
+        if self.is_finished() && !self.is_animating() {
+            self.draw_gameover(&mut canvas, ctx)?;
         }
+        match self.screen {
+            Screen::Board => match &self.online {
+                Some(online) if online.player.is_none() => {
+                    screens::draw_lobby(&mut canvas, ctx, &online.players, online.needed)?
+                }
+                _ => (),
+            },
+            Screen::Settings(selected) => {
+                screens::draw_settings(&mut canvas, ctx, &self.settings, selected, color)?
+            }
+            Screen::Statistics => screens::draw_statistics(
+                &mut canvas,
+                ctx,
+                &self.statistics,
+                &self.game_colors,
+                color,
+            )?,
+            Screen::HighScores(index) => {
+                let games = self.statistics.high_scores(MODES[index], HIGH_SCORES);
+                let title = MODES[index].title(self.settings.ruleset);
+                screens::draw_high_scores(&mut canvas, ctx, &title, &games, color)?
+            }
+            Screen::Modes(selected) => screens::draw_modes(
+                &mut canvas,
+                ctx,
+                self.mode,
+                self.settings.ruleset,
+                selected,
+                color,
+            )?,
+            Screen::Pause => screens::draw_pause(&mut canvas, ctx)?,
+            Screen::Daily(copied) => {
+                let summary = self.daily_summary();
+                screens::draw_daily(&mut canvas, ctx, summary.as_deref(), copied, color)?
+            }
+            Screen::Puzzles(selected) => screens::draw_puzzles(
+                &mut canvas,
+                ctx,
+                &self.puzzles,
+                &self.progress,
+                selected,
+                color,
+            )?,
+            Screen::Editor { .. } | Screen::Review(_) => (),
+        }
+        canvas.finish(ctx)?;
         Ok(())
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         input: ggez::input::keyboard::KeyInput,
         _repeated: bool,
     ) -> Result<(), ggez::GameError> {
-        if self.counter_1 <= NB_I as u32 || self.counter_2 <= NB_I as u32 {
+        match input.keycode {
+            Some(KeyCode::M) => {
+                self.settings.muted = !self.settings.muted;
+                self.apply_settings(ctx);
+                return Ok(());
+            }
+            Some(keycode) => match self.screen {
+                Screen::Settings(selected) => {
+                    self.settings_key(ctx, keycode, selected);
+                    return Ok(());
+                }
+                Screen::Statistics => {
+                    if matches!(keycode, KeyCode::Escape | KeyCode::Tab) {
+                        self.screen = Screen::Board;
+                    }
+                    return Ok(());
+                }
+                Screen::HighScores(index) => {
+                    self.screen = match keycode {
+                        KeyCode::Escape | KeyCode::L => Screen::Board,
+                        KeyCode::Left => {
+                            Screen::HighScores((index + MODES.len() - 1) % MODES.len())
+                        }
+                        KeyCode::Right => Screen::HighScores((index + 1) % MODES.len()),
+                        _ => Screen::HighScores(index),
+                    };
+                    return Ok(());
+                }
+                Screen::Modes(selected) => {
+                    self.modes_key(keycode, selected);
+                    return Ok(());
+                }
+                Screen::Pause => {
+                    if matches!(keycode, KeyCode::Escape | KeyCode::P) {
+                        self.screen = Screen::Board;
+                    }
+                    return Ok(());
+                }
+                Screen::Puzzles(selected) => {
+                    self.puzzles_key(keycode, selected);
+                    return Ok(());
+                }
+                Screen::Editor { cursor, typing } => {
+                    self.editor_key(keycode, input.mods, cursor, typing);
+                    return Ok(());
+                }
+                Screen::Review(index) => {
+                    self.review_key(keycode, index);
+                    return Ok(());
+                }
+                Screen::Daily(copied) => {
+                    self.screen = match keycode {
+                        KeyCode::C => Screen::Daily(self.share_daily()),
+                        KeyCode::Escape | KeyCode::Return => Screen::Board,
+                        _ => Screen::Daily(copied),
+                    };
+                    return Ok(());
+                }
+                Screen::Board => match keycode {
+                    KeyCode::O => {
+                        self.screen = Screen::Settings(0);
+                        return Ok(());
+                    }
+                    KeyCode::Tab => {
+                        self.screen = Screen::Statistics;
+                        return Ok(());
+                    }
+                    KeyCode::L => {
+                        self.screen = Screen::HighScores(self.mode_index());
+                        return Ok(());
+                    }
+                    KeyCode::G => {
+                        self.screen = Screen::Modes(self.mode_index());
+                        return Ok(());
+                    }
+                    KeyCode::K => {
+                        self.screen = Screen::Puzzles(self.puzzle.unwrap_or(0));
+                        return Ok(());
+                    }
+                    KeyCode::B => {
+                        self.open_editor();
+                        return Ok(());
+                    }
+                    // in versus games `A` moves the first player
+                    KeyCode::A if !self.mode.is_versus() => {
+                        self.open_review();
+                        return Ok(());
+                    }
+                    KeyCode::H => {
+                        self.show_hint();
+                        return Ok(());
+                    }
+                    KeyCode::C if input.mods.contains(KeyMods::CTRL) => {
+                        self.copy_position();
+                        return Ok(());
+                    }
+                    KeyCode::V if input.mods.contains(KeyMods::CTRL) => {
+                        self.paste_position();
+                        return Ok(());
+                    }
+                    KeyCode::P | KeyCode::Escape if !self.is_finished() => {
+                        self.screen = Screen::Pause;
+                        return Ok(());
+                    }
+                    KeyCode::N => {
+                        self.new_game(self.mode);
+                        return Ok(());
+                    }
+                    _ => (),
+                },
+            },
+            None => (),
+        }
+        // in a versus game, a player is not blocked by the animations of the other board
+        let player = match input.keycode {
+            Some(keycode) if self.mode.is_versus() => player_key(keycode),
+            _ => None,
+        };
+        let animating = match player {
+            Some((player, _)) => self.boards[player].is_animating(),
+            None => self.is_animating(),
+        };
+        // the player only watches a bot
+        if animating || self.bot.is_some() {
             return Ok(());
         }
         if let Some(keycode) = input.keycode {
-            self.reset_animations();
-            self.key = match keycode {
-                KeyCode::Up => -4,
-                KeyCode::Down => 4,
-                KeyCode::Left => -1,
-                KeyCode::Right => 1,
-                _ => 0,
+            if matches!(keycode, KeyCode::U | KeyCode::Back) {
+                self.undo();
+                return Ok(());
             }
+            let topology = self.boards[0].game.topology();
+            (self.player, self.key) = match player {
+                Some((player, keycode)) => (Some(player), key_action(topology, keycode)),
+                None if self.mode.is_versus() => (None, 0),
+                // only the local board moves in a network race, once it started
+                None => match &self.online {
+                    Some(online) => (
+                        online.player,
+                        online.player.map_or(0, |_| key_action(topology, keycode)),
+                    ),
+                    None => (None, key_action(topology, keycode)),
+                },
+            };
         } else {
             self.key = 0;
         }
         Ok(())
     }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if let Screen::Editor { .. } = self.screen {
+            self.editor_click(button, Vec2::new(x, y));
+        }
+        Ok(())
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> GameResult {
+        self.focused = gained;
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, ggez::GameError> {
+        if self.is_finished() {
+            self.record_game();
+        } else if self.moves() > 0
+            && !self.recorded
+            && !matches!(
+                self.mode,
+                GameMode::OnlineRace { .. } | GameMode::Puzzle { .. }
+            )
+        {
+            self.save_game();
+        }
+        Ok(false)
+    }
 }
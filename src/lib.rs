@@ -0,0 +1,16 @@
+//! Engine of the game: grid, rules, spawns and modes, shared by the game and the race server
+pub mod analysis;
+pub mod bot;
+pub mod daily;
+pub mod editor;
+pub mod env;
+pub mod game;
+pub mod mode;
+pub mod net;
+pub mod ntuple;
+pub mod puzzle;
+pub mod replay;
+pub mod ruleset;
+pub mod solver;
+pub mod tablebase;
+pub mod topology;
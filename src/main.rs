@@ -1,17 +1,61 @@
+mod audio;
+mod board;
+mod cli;
+mod clock;
 mod colors;
-mod game;
 mod graphics;
+mod progress;
+mod save;
+mod screens;
+mod settings;
+mod stats;
 
+use rust_2048::{
+    analysis, bot, daily, editor, game, mode, net, ntuple, puzzle, replay, ruleset, solver,
+    tablebase, topology,
+};
+
+use cli::Options;
 use ggez::conf::WindowMode;
 use ggez::conf::WindowSetup;
 use ggez::graphics::FontData;
 use ggez::event;
-use ggez::GameResult;
-use graphics::MainState;
+use ggez::{GameError, GameResult};
+use graphics::{MainState, HUD_HEIGHT};
 use std::env::current_dir;
 use std::path::PathBuf;
 
 pub fn main() -> GameResult {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{}", cli::usage());
+            std::process::exit(2);
+        }
+    };
+    if let Some(path) = &options.verify {
+        std::process::exit(cli::verify(path, options.rules.unwrap_or("original")));
+    }
+    if let (Some(command), false) = (&options.bot, options.watch) {
+        std::process::exit(cli::run_bot(command, &options));
+    }
+    if let Some(path) = &options.train {
+        std::process::exit(cli::train(path, &options));
+    }
+    if let Some(path) = &options.generate {
+        std::process::exit(cli::generate(path, &options));
+    }
+    if let Some(path) = &options.analyze {
+        let report = options.report.as_deref();
+        let (tablebase, network) = (options.tablebase.as_deref(), options.network.as_deref());
+        std::process::exit(cli::analyze(
+            path,
+            options.depth,
+            report,
+            tablebase,
+            network,
+        ));
+    }
     let current_path = current_dir().unwrap();
     let resources_path = current_path.join(PathBuf::from("resources"));
     if !resources_path.exists() {
@@ -22,12 +66,23 @@ pub fn main() -> GameResult {
     }
     let cb = ggez::ContextBuilder::new("game2048", "bourbonut")
         .add_resource_path(resources_path)
-        .window_mode(WindowMode::default().dimensions(500., 500.)) // vsync(false) to get more FPS
+        .window_mode(WindowMode::default().dimensions(500., 500. + HUD_HEIGHT)) // vsync(false) to get more FPS
         .window_setup(WindowSetup::default().title("2048").icon("/logo.png"));
 
-    let (mut ctx, event_loop) = cb.build()?;
+    let (mut ctx, event_loop) = match cb.build() {
+        Ok(context) => context,
+        // ggez opens the audio device with the window, it can only be left out at build time
+        Err(GameError::AudioError(error)) => {
+            eprintln!(
+                "No audio device: {error}\nRun the game without sound with:\n\
+                $ cargo run --release --no-default-features --features gui"
+            );
+            std::process::exit(1);
+        }
+        Err(error) => return Err(error),
+    };
     let font = FontData::from_path(&ctx.fs, PathBuf::from("/clear-sans.bold.ttf"))?;
     ctx.gfx.add_font("ClearSans-Bold", font);
-    let state = MainState::new();
+    let state = MainState::new(&mut ctx, options);
     event::run(ctx, event_loop, state)
 }
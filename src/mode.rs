@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use crate::game::{ActionReport, Game, BOMB, STONE, WILDCARD};
+use crate::puzzle::Goal;
+use crate::ruleset::Ruleset;
+
+// rank of the smallest merged tile which drops a blocker on the opponent, 128 in classic games
+const ATTACK_RANK: usize = 7;
+
+/// Rules deciding when a game ends and how it is ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameMode {
+    /// Endless play until the board is locked
+    #[default]
+    Classic,
+    /// Classic game whose seed is the date, one attempt per day, see `daily`
+    Daily,
+    /// Highest score in a given number of minutes
+    TimeAttack { minutes: u32 },
+    /// Reach `target` in at most `moves` moves
+    MoveLimited { moves: u32, target: u32 },
+    /// Reach the win tile of the ruleset as fast as possible
+    Sprint,
+    /// Classic game where stones, wildcards and bombs spawn among numbers
+    SpecialTiles,
+    /// One input moves every board, the game ends as soon as one board is locked
+    MultiBoard { boards: u32 },
+    /// Two players with their own board, the first one to reach `target` wins
+    ///
+    /// With `attacks`, big merges drop blockers on the board of the opponent.
+    VersusRace { target: u32, attacks: bool },
+    /// Two players with their own board, the highest score after `minutes` wins
+    VersusTime { minutes: u32, attacks: bool },
+    /// Race to `target` against players connected to a race server, see `net`
+    OnlineRace { target: u32 },
+    /// Meet `goal` from the position of a puzzle in at most `moves` moves, see `puzzle`
+    Puzzle { goal: Goal, moves: u32 },
+}
+
+/// Modes proposed by the mode selection screen
+pub const MODES: [GameMode; 13] = [
+    GameMode::Classic,
+    GameMode::Daily,
+    GameMode::TimeAttack { minutes: 1 },
+    GameMode::TimeAttack { minutes: 3 },
+    GameMode::MoveLimited {
+        moves: 300,
+        target: 512,
+    },
+    GameMode::MoveLimited {
+        moves: 600,
+        target: 1024,
+    },
+    GameMode::Sprint,
+    GameMode::SpecialTiles,
+    GameMode::MultiBoard { boards: 2 },
+    GameMode::MultiBoard { boards: 4 },
+    GameMode::VersusRace {
+        target: 1024,
+        attacks: false,
+    },
+    GameMode::VersusRace {
+        target: 1024,
+        attacks: true,
+    },
+    GameMode::VersusTime {
+        minutes: 2,
+        attacks: true,
+    },
+];
+
+/// State of a game with respect to the end condition of its mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    Won,
+    Lost,
+    /// Index of the board of the player who won a versus game
+    Winner(usize),
+    /// Versus game ended with equal scores
+    Draw,
+}
+
+impl GameMode {
+    /// Name displayed to the player when new games follow `ruleset`
+    pub fn title(&self, ruleset: Ruleset) -> String {
+        match self {
+            GameMode::Classic => String::from("Classic"),
+            GameMode::Daily => String::from("Daily challenge"),
+            GameMode::TimeAttack { minutes } => format!("Time attack ({minutes} min)"),
+            GameMode::MoveLimited { moves, target } => format!("{target} in {moves} moves"),
+            GameMode::Sprint => format!("Sprint to {}", ruleset.win_tile()),
+            GameMode::SpecialTiles => String::from("Special tiles"),
+            GameMode::MultiBoard { boards } => format!("{boards} boards"),
+            GameMode::VersusRace { target, attacks } => {
+                let attacks = if *attacks { " with attacks" } else { "" };
+                format!("Versus: race to {target}{attacks}")
+            }
+            GameMode::VersusTime { minutes, attacks } => {
+                let attacks = if *attacks { " with attacks" } else { "" };
+                format!("Versus: {minutes} min{attacks}")
+            }
+            GameMode::OnlineRace { target } => format!("Online race to {target}"),
+            GameMode::Puzzle { goal, moves } => format!("Puzzle: {}", goal.describe(*moves)),
+        }
+    }
+
+    /// Number of boards played at once
+    pub fn boards(&self) -> usize {
+        match self {
+            GameMode::MultiBoard { boards } => *boards as usize,
+            GameMode::VersusRace { .. } | GameMode::VersusTime { .. } => 2,
+            _ => 1,
+        }
+    }
+
+    /// Checks if two players play against each other, each one on its own board
+    ///
+    /// Both boards start from the same seed so that players get the same spawns.
+    pub fn is_versus(&self) -> bool {
+        matches!(
+            self,
+            GameMode::VersusRace { .. } | GameMode::VersusTime { .. }
+        )
+    }
+
+    /// Number of blockers dropped on the board of the opponent after an action which created the
+    /// tiles of `report`, one per merged tile of rank `ATTACK_RANK` or more
+    pub fn blockers(&self, report: &ActionReport, ruleset: Ruleset) -> usize {
+        match self {
+            GameMode::VersusRace { attacks: true, .. }
+            | GameMode::VersusTime { attacks: true, .. } => report
+                .merges
+                .iter()
+                .filter(|&&(_, value)| ruleset.rank(value) >= ATTACK_RANK)
+                .count(),
+            _ => 0,
+        }
+    }
+
+    /// Special tiles which can spawn with their probability in percent, see
+    /// `SpawnRules::specials`
+    pub fn specials(&self) -> Vec<(u32, u32)> {
+        match self {
+            GameMode::SpecialTiles => vec![(STONE, 2), (WILDCARD, 4), (BOMB, 3)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Time left before the end of the game if the mode is timed
+    pub fn time_left(&self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            GameMode::TimeAttack { minutes } | GameMode::VersusTime { minutes, .. } => {
+                Some(Duration::from_secs(*minutes as u64 * 60).saturating_sub(elapsed))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if a game whose highest tile is `max_tile` after `moves` moves is won, `win_tile` is
+    /// the tile to reach with the ruleset of the game
+    pub fn is_won(&self, max_tile: u32, moves: u32, win_tile: u32) -> bool {
+        match self {
+            GameMode::MoveLimited {
+                moves: limit,
+                target,
+            } => max_tile >= *target && moves <= *limit,
+            GameMode::VersusRace { target, .. } | GameMode::OnlineRace { target } => {
+                max_tile >= *target
+            }
+            GameMode::Puzzle {
+                goal: Goal::Tile(value),
+                moves: limit,
+            } => max_tile >= *value && moves <= *limit,
+            GameMode::Puzzle { .. } => false,
+            _ => max_tile >= win_tile,
+        }
+    }
+
+    /// End condition of the mode over every board of a game, the game ends as soon as one of its
+    /// boards ends
+    pub fn outcome_of_boards<'a>(
+        &self,
+        games: impl IntoIterator<Item = &'a Game>,
+        elapsed: Duration,
+    ) -> Outcome {
+        let games: Vec<&Game> = games.into_iter().collect();
+        if self.is_versus() {
+            return self.versus_outcome(&games, elapsed);
+        }
+        games
+            .into_iter()
+            .map(|game| self.outcome(game, elapsed))
+            .find(|&outcome| outcome != Outcome::Playing)
+            .unwrap_or(Outcome::Playing)
+    }
+
+    /// End condition of a versus game between the players of `games`
+    ///
+    /// A player whose board is locked loses, otherwise the first player to reach the target wins
+    /// a race and the highest score wins when the time is up.
+    fn versus_outcome(&self, games: &[&Game], elapsed: Duration) -> Outcome {
+        if let Some(locked) = games.iter().position(|game| game.is_gameover()) {
+            return Outcome::Winner((locked + 1) % games.len());
+        }
+        if let GameMode::VersusRace { target, .. } = self {
+            if let Some(winner) = games.iter().position(|game| game.max_tile() >= *target) {
+                return Outcome::Winner(winner);
+            }
+        }
+        if self.time_left(elapsed) != Some(Duration::ZERO) {
+            return Outcome::Playing;
+        }
+        match games[0].score().cmp(&games[1].score()) {
+            Ordering::Greater => Outcome::Winner(0),
+            Ordering::Less => Outcome::Winner(1),
+            Ordering::Equal => Outcome::Draw,
+        }
+    }
+
+    /// End condition of the mode, replaces `Game::is_gameover`
+    pub fn outcome(&self, game: &Game, elapsed: Duration) -> Outcome {
+        let won = self.is_won(game.max_tile(), game.moves(), game.ruleset().win_tile());
+        match self {
+            GameMode::Classic
+            | GameMode::Daily
+            | GameMode::SpecialTiles
+            | GameMode::MultiBoard { .. } => {
+                if !game.is_gameover() {
+                    Outcome::Playing
+                } else if won {
+                    Outcome::Won
+                } else {
+                    Outcome::Lost
+                }
+            }
+            GameMode::TimeAttack { .. } | GameMode::VersusTime { .. } => {
+                if game.is_gameover() || self.time_left(elapsed) == Some(Duration::ZERO) {
+                    Outcome::Won
+                } else {
+                    Outcome::Playing
+                }
+            }
+            GameMode::Puzzle { goal, moves } => {
+                if goal.is_reached(game) && game.moves() <= *moves {
+                    Outcome::Won
+                } else if game.is_gameover() || game.moves() >= *moves {
+                    Outcome::Lost
+                } else {
+                    Outcome::Playing
+                }
+            }
+            GameMode::MoveLimited { moves, .. } => {
+                if won {
+                    Outcome::Won
+                } else if game.is_gameover() || game.moves() >= *moves {
+                    Outcome::Lost
+                } else {
+                    Outcome::Playing
+                }
+            }
+            GameMode::Sprint | GameMode::VersusRace { .. } | GameMode::OnlineRace { .. } => {
+                if won {
+                    Outcome::Won
+                } else if game.is_gameover() {
+                    Outcome::Lost
+                } else {
+                    Outcome::Playing
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mode {
+    use super::*;
+    use crate::game::{ACTIONS, WIN_TILE};
+
+    #[test]
+    fn time_attack_ends_with_time() {
+        let mode = GameMode::TimeAttack { minutes: 1 };
+        let game = Game::from_seed(0);
+        assert_eq!(
+            mode.outcome(&game, Duration::from_secs(59)),
+            Outcome::Playing
+        );
+        assert_eq!(mode.outcome(&game, Duration::from_secs(60)), Outcome::Won);
+        assert_eq!(
+            mode.time_left(Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+    }
+
+    #[test]
+    fn move_limited_ends_with_moves() {
+        let mode = GameMode::MoveLimited {
+            moves: 2,
+            target: 512,
+        };
+        let mut game = Game::from_seed(0);
+        for action in [-1, 1, -4, 4] {
+            if game.moves() < 2 && game.partial_move(action) {
+                game.action(action);
+                game.random();
+            }
+        }
+        assert_eq!(game.moves(), 2);
+        assert_eq!(mode.outcome(&game, Duration::ZERO), Outcome::Lost);
+        assert!(mode.is_won(512, 2, WIN_TILE));
+        assert!(!mode.is_won(512, 3, WIN_TILE));
+    }
+
+    #[test]
+    fn serialized_names() {
+        let classic = serde_json::to_string(&GameMode::Classic).unwrap();
+        assert_eq!(classic, "\"classic\"");
+        let sprint: GameMode = serde_json::from_str("\"sprint\"").unwrap();
+        assert_eq!(sprint, GameMode::Sprint);
+    }
+
+    #[test]
+    fn sprint_title_follows_ruleset() {
+        let sprint = GameMode::Sprint;
+        assert_eq!(sprint.title(Ruleset::Classic), "Sprint to 2048");
+        assert_eq!(sprint.title(Ruleset::Threes), "Sprint to 3072");
+    }
+
+    #[test]
+    fn multi_board_ends_with_any_board() {
+        let mode = GameMode::MultiBoard { boards: 2 };
+        let playing = Game::from_seed(0);
+        let mut locked = Game::from_seed(1);
+        for action in ACTIONS.iter().cycle().take(2000) {
+            if locked.partial_move(*action) {
+                locked.action(*action);
+                locked.random();
+            }
+        }
+        assert!(locked.is_gameover());
+        assert_eq!(
+            mode.outcome_of_boards([&playing, &playing], Duration::ZERO),
+            Outcome::Playing
+        );
+        assert_eq!(
+            mode.outcome_of_boards([&playing, &locked], Duration::ZERO),
+            Outcome::Lost
+        );
+    }
+
+    #[test]
+    fn versus_outcomes() {
+        let race = GameMode::VersusRace {
+            target: 4,
+            attacks: false,
+        };
+        let mut first = Game::from_seed(0);
+        let second = Game::from_seed(0);
+        assert_eq!(first.copy_grid(), second.copy_grid());
+        assert!(first.max_tile() < 4);
+        assert_eq!(
+            race.outcome_of_boards([&first, &second], Duration::ZERO),
+            Outcome::Playing
+        );
+        for action in ACTIONS.iter().cycle().take(100) {
+            if first.max_tile() < 4 && first.partial_move(*action) {
+                first.action(*action);
+                first.random();
+            }
+        }
+        assert_eq!(
+            race.outcome_of_boards([&second, &first], Duration::ZERO),
+            Outcome::Winner(1)
+        );
+        let time = GameMode::VersusTime {
+            minutes: 1,
+            attacks: false,
+        };
+        let elapsed = Duration::from_secs(60);
+        assert_eq!(
+            time.outcome_of_boards([&first, &second], elapsed),
+            Outcome::Winner(0)
+        );
+        assert_eq!(
+            time.outcome_of_boards([&second, &second], elapsed),
+            Outcome::Draw
+        );
+    }
+
+    #[test]
+    fn attacks_drop_blockers() {
+        let report = ActionReport {
+            moves: Vec::new(),
+            merges: vec![(0, 128), (1, 64), (2, 256)],
+        };
+        let attacks = GameMode::VersusTime {
+            minutes: 2,
+            attacks: true,
+        };
+        assert_eq!(attacks.blockers(&report, Ruleset::Classic), 2);
+        assert_eq!(attacks.blockers(&report, Ruleset::Threes), 3);
+        let peaceful = GameMode::VersusTime {
+            minutes: 2,
+            attacks: false,
+        };
+        assert_eq!(peaceful.blockers(&report, Ruleset::Classic), 0);
+    }
+
+    #[test]
+    fn puzzle_goals() {
+        let mut grid = [0; 16];
+        grid[0] = 4;
+        grid[4] = 4;
+        grid[8] = 2;
+        let mode = GameMode::Puzzle {
+            goal: Goal::Clear(2),
+            moves: 1,
+        };
+        let mut game = Game::from_position(grid, Default::default(), Vec::new());
+        assert_eq!(mode.outcome(&game, Duration::ZERO), Outcome::Playing);
+        game.action(-4);
+        assert_eq!(mode.outcome(&game, Duration::ZERO), Outcome::Won);
+        let mut game = Game::from_position(grid, Default::default(), Vec::new());
+        game.action(1);
+        assert_eq!(mode.outcome(&game, Duration::ZERO), Outcome::Lost);
+    }
+}
@@ -0,0 +1,380 @@
+//! Network races between players connected to a race server
+//!
+//! # Protocol
+//!
+//! Clients connect to the server over TCP (port `DEFAULT_PORT` by default) and both sides send
+//! messages as JSON objects, one per line. The `type` field gives the kind of message.
+//!
+//! Client to server:
+//!
+//! - `{"type": "join", "name": "alice"}`: enters the lobby of the next race
+//! - `{"type": "move", "action": -1, "grid": [...]}`: plays `action` (see `Topology`), `grid` is
+//!   the board after the action and its spawns
+//!
+//! Server to client:
+//!
+//! - `{"type": "lobby", "players": ["alice"], "needed": 2}`: players waiting for the next race,
+//!   sent whenever the lobby changes
+//! - `{"type": "start", "seed": 42, "variant": {...}, "rules": {...}, "target": 2048,
+//!   "players": ["alice", "bob"], "you": 0}`: every player starts a game with `seed`, `variant`
+//!   and `rules`, `you` is the index of the player receiving the message
+//! - `{"type": "moved", "player": 1, "action": 4, "score": 16}`: a player played an action,
+//!   replaying it on a game created from the seed gives the board of that player
+//! - `{"type": "rejected", "reason": "..."}`: the last message was not valid, a move which does
+//!   not match the seed puts the player out of the race and closes the connection, like a line
+//!   longer than `MAX_LINE` bytes
+//! - `{"type": "left", "player": 1}`: a player disconnected or was rejected
+//! - `{"type": "finished", "winner": 0}`: end of the race, `winner` is `null` if nobody won,
+//!   then the server closes the connection
+//!
+//! The first player to reach the target wins. A player whose board is locked is out, the last
+//! player who is not out wins.
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::game::{Game, SpawnRules, Variant};
+
+/// Port of the race server when none is given
+pub const DEFAULT_PORT: u16 = 7878;
+/// Longest line read from a client, in bytes with its newline, so that a client can not make the
+/// server allocate without bound
+pub const MAX_LINE: u64 = 4096;
+
+/// Message sent by a client to the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    Join { name: String },
+    Move { action: i8, grid: [u32; 16] },
+}
+
+/// Message sent by the server to a client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ServerMessage {
+    Lobby {
+        players: Vec<String>,
+        needed: usize,
+    },
+    Start {
+        seed: u64,
+        variant: Variant,
+        rules: SpawnRules,
+        target: u32,
+        players: Vec<String>,
+        you: usize,
+    },
+    Moved {
+        player: usize,
+        action: i8,
+        score: u32,
+    },
+    Rejected {
+        reason: String,
+    },
+    Left {
+        player: usize,
+    },
+    Finished {
+        winner: Option<usize>,
+    },
+}
+
+/// Writes `message` on one line
+pub fn send<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+/// Reads the next message, `None` if the connection is closed
+pub fn receive<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Reads the messages of a client until the connection is closed or `handle` returns false
+///
+/// `handle` gets the reason why a line is not a message instead of the message. A line longer
+/// than `MAX_LINE` also ends the reading, as what follows it is not a message.
+pub fn read_messages<T: DeserializeOwned>(
+    reader: &mut impl BufRead,
+    mut handle: impl FnMut(Result<T, String>) -> bool,
+) {
+    loop {
+        let mut line = Vec::new();
+        match reader.by_ref().take(MAX_LINE).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if !line.ends_with(b"\n") && line.len() as u64 == MAX_LINE => {
+                handle(Err(format!("Line longer than {MAX_LINE} bytes")));
+                return;
+            }
+            Ok(_) => {
+                let message = serde_json::from_slice(&line)
+                    .map_err(|error| format!("Invalid message: {error}"));
+                if !handle(message) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Race checked by the server, every game is replayed from the seed to detect cheating
+pub struct Race {
+    games: Vec<Game>,
+    // whether each player is out of the race
+    out: Vec<bool>,
+    target: u32,
+}
+
+impl Race {
+    /// Creates a race between `players` players, who all start from `seed`
+    pub fn new(
+        seed: u64,
+        variant: Variant,
+        rules: SpawnRules,
+        players: usize,
+        target: u32,
+    ) -> Race {
+        Race {
+            games: (0..players)
+                .map(|_| Game::with_variant(seed, variant, rules.clone()))
+                .collect(),
+            out: vec![false; players],
+            target,
+        }
+    }
+
+    /// Plays `action` for `player` and returns the new score, `grid` is the board of the player
+    /// after the action and its spawns
+    ///
+    /// The player is out of the race if the action is not possible or if `grid` differs from the
+    /// board given by the seed.
+    pub fn play(&mut self, player: usize, action: i8, grid: [u32; 16]) -> Result<u32, String> {
+        if self.out[player] {
+            return Err(String::from("Player is out of the race"));
+        }
+        let game = &mut self.games[player];
+        if !game.topology().actions().contains(&action) || !game.partial_move(action) {
+            self.out[player] = true;
+            return Err(format!("Action {action} is not possible"));
+        }
+        game.action(action);
+        game.random();
+        if game.copy_grid() != grid {
+            self.out[player] = true;
+            return Err(String::from("Grid does not match the seed"));
+        }
+        if game.is_gameover() && game.max_tile() < self.target {
+            self.out[player] = true;
+        }
+        Ok(game.score())
+    }
+
+    /// Puts `player` out of the race
+    pub fn leave(&mut self, player: usize) {
+        self.out[player] = true;
+    }
+
+    /// Result of the race, `None` while it is not finished, `Some(None)` if nobody won
+    pub fn result(&self) -> Option<Option<usize>> {
+        let reached = (0..self.games.len())
+            .find(|&player| !self.out[player] && self.games[player].max_tile() >= self.target);
+        if reached.is_some() {
+            return Some(reached);
+        }
+        let remaining: Vec<usize> = (0..self.games.len())
+            .filter(|&player| !self.out[player])
+            .collect();
+        match remaining[..] {
+            [] => Some(None),
+            [last] if self.games.len() > 1 => Some(Some(last)),
+            _ => None,
+        }
+    }
+}
+
+/// Connection of the game to a race server
+pub struct Client {
+    stream: TcpStream,
+    receiver: Receiver<ServerMessage>,
+    connected: bool,
+}
+
+impl Client {
+    /// Connects to the server at `address` and joins the lobby as `name`
+    pub fn connect(address: &str, name: &str) -> io::Result<Client> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let name = name.to_string();
+        send(&mut stream, &ClientMessage::Join { name })?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (sender, receiver) = mpsc::channel();
+        // messages are read in the background so that the game never waits for the network
+        thread::spawn(move || {
+            while let Ok(Some(message)) = receive(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Client {
+            stream,
+            receiver,
+            connected: true,
+        })
+    }
+
+    /// Messages received since the last call
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    /// Checks if the server did not close the connection, updated by `poll`
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Sends `action` and `grid`, the board after the action and its spawns
+    pub fn send_move(&mut self, action: i8, grid: [u32; 16]) -> io::Result<()> {
+        send(&mut self.stream, &ClientMessage::Move { action, grid })
+    }
+}
+
+#[cfg(test)]
+mod test_net {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    use crate::game::ACTIONS;
+
+    /// Plays the first possible action on `game` and returns it
+    fn play_first(game: &mut Game) -> i8 {
+        let action = *ACTIONS
+            .iter()
+            .find(|&&action| game.partial_move(action))
+            .unwrap();
+        game.action(action);
+        game.random();
+        action
+    }
+
+    #[test]
+    fn race_checks_moves() {
+        let rules = SpawnRules::default();
+        let mut race = Race::new(7, Variant::default(), rules.clone(), 2, 2048);
+        let mut honest = Game::with_variant(7, Variant::default(), rules.clone());
+        let action = play_first(&mut honest);
+        assert_eq!(race.play(0, action, honest.copy_grid()), Ok(honest.score()));
+        assert_eq!(race.result(), None);
+
+        let mut cheater = Game::with_variant(8, Variant::default(), rules);
+        let action = play_first(&mut cheater);
+        assert!(race.play(1, action, cheater.copy_grid()).is_err());
+        assert!(race.play(1, action, cheater.copy_grid()).is_err());
+        assert_eq!(race.result(), Some(Some(0)));
+    }
+
+    #[test]
+    fn race_rejects_impossible_actions() {
+        let mut race = Race::new(7, Variant::default(), SpawnRules::default(), 1, 2048);
+        assert!(race.play(0, 5, [0; 16]).is_err());
+        assert_eq!(race.result(), Some(None));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let long = "x".repeat(MAX_LINE as usize);
+        let input = format!(
+            "{{\"type\": \"join\", \"name\": \"alice\"}}\nnot json\n{long}\n{{\"type\": \"join\"}}\n"
+        );
+        let mut messages = Vec::new();
+        read_messages(
+            &mut input.as_bytes(),
+            |message: Result<ClientMessage, String>| {
+                messages.push(message);
+                true
+            },
+        );
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0],
+            Ok(ClientMessage::Join {
+                name: String::from("alice")
+            })
+        );
+        assert!(messages[1]
+            .as_ref()
+            .is_err_and(|reason| reason.starts_with("Invalid")));
+        assert!(messages[2]
+            .as_ref()
+            .is_err_and(|reason| reason.starts_with("Line longer")));
+    }
+
+    #[test]
+    fn messages_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut client = Client::connect(&address, "alice").unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let join: Option<ClientMessage> = receive(&mut reader).unwrap();
+        assert_eq!(
+            join,
+            Some(ClientMessage::Join {
+                name: String::from("alice")
+            })
+        );
+
+        let lobby = ServerMessage::Lobby {
+            players: vec![String::from("alice")],
+            needed: 2,
+        };
+        send(&mut &stream, &lobby).unwrap();
+        let start = Instant::now();
+        let mut messages = Vec::new();
+        while messages.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            messages = client.poll();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(messages, vec![lobby]);
+
+        client.send_move(-1, [0; 16]).unwrap();
+        let moved: Option<ClientMessage> = receive(&mut reader).unwrap();
+        assert_eq!(
+            moved,
+            Some(ClientMessage::Move {
+                action: -1,
+                grid: [0; 16]
+            })
+        );
+        drop(reader);
+        drop(stream);
+        while client.is_connected() && start.elapsed() < Duration::from_secs(5) {
+            client.poll();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!client.is_connected());
+    }
+}
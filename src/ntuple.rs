@@ -0,0 +1,384 @@
+//! N-tuple network rating boards, learned by self-play
+//!
+//! A network sums one weight per tuple of cells, looked up by the tiles of those cells, for every
+//! symmetry of the grid. It estimates the points still to come after an action (the afterstate,
+//! before the spawn) and is trained by temporal difference learning: after each action of a game
+//! played greedily by the network, the value of the previous afterstate moves towards the points
+//! of the action plus the value of the new afterstate (TD(0) on afterstates).
+//!
+//! A weights file is little-endian: the magic `T2048NT`, the version, the number of tuples and the
+//! number of games trained, then for each tuple its size and its cells, then the weights of each
+//! tuple as `f32`, `16^size` per tuple.
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::game::{Cell, Game, Variant};
+
+const MAGIC: &[u8; 7] = b"T2048NT";
+const VERSION: u8 = 1;
+// cells of a tuple, a larger tuple does not fit in memory
+const MAX_TUPLE: usize = 6;
+// tuples of a network, their number is stored on one byte
+const MAX_TUPLES: usize = u8::MAX as usize;
+
+/// Share of the error corrected by each update, spread over the weights of the board
+pub const DEFAULT_RATE: f32 = 0.1;
+
+/// Tuples of the networks available by name, see `Network::preset`
+///
+/// - `small`: rows and squares of 4 cells, 1 MB of weights, learns in a few thousand games
+/// - `large`: rectangles and bent lines of 6 cells, 256 MB of weights, plays much better after
+///   a hundred thousand games
+pub const PRESETS: &[(&str, &[&[usize]])] = &[
+    (
+        "small",
+        &[&[0, 1, 2, 3], &[4, 5, 6, 7], &[0, 1, 4, 5], &[1, 2, 5, 6]],
+    ),
+    (
+        "large",
+        &[
+            &[0, 1, 2, 3, 4, 5],
+            &[4, 5, 6, 7, 8, 9],
+            &[0, 1, 2, 4, 5, 6],
+            &[4, 5, 6, 8, 9, 10],
+        ],
+    ),
+];
+
+/// Result of a game played by the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Episode {
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+}
+
+/// Weights of the tuples of a network
+#[derive(Clone, PartialEq)]
+pub struct Network {
+    tuples: Vec<Vec<usize>>,
+    /// Every symmetric copy of the tuples with the index of its weights
+    features: Vec<(usize, Vec<usize>)>,
+    weights: Vec<Vec<f32>>,
+    /// Number of games the network learned from, the next training game is dealt by this seed
+    pub games: u64,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("tuples", &self.tuples)
+            .field("games", &self.games)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Network {
+    /// Network with all weights at zero, `None` if there are no tuples or more than 255, or if a
+    /// tuple is empty, too large or has a cell outside of the grid or twice
+    pub fn new(tuples: &[impl AsRef<[usize]>]) -> Option<Network> {
+        let tuples: Vec<Vec<usize>> = tuples.iter().map(|tuple| tuple.as_ref().to_vec()).collect();
+        if !valid(&tuples) {
+            return None;
+        }
+        let weights = (tuples.iter())
+            .map(|tuple| vec![0.; 1 << (4 * tuple.len())])
+            .collect();
+        Some(Network::with_weights(tuples, weights, 0))
+    }
+
+    /// Network of a preset of `PRESETS` with all weights at zero
+    pub fn preset(name: &str) -> Option<Network> {
+        let (_, tuples) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+        Network::new(tuples)
+    }
+
+    fn with_weights(tuples: Vec<Vec<usize>>, weights: Vec<Vec<f32>>, games: u64) -> Network {
+        let features = (tuples.iter().enumerate())
+            .flat_map(|(index, tuple)| {
+                (0..8).map(move |symmetry| {
+                    let cells = tuple
+                        .iter()
+                        .map(|&cell| symmetric(cell, symmetry))
+                        .collect();
+                    (index, cells)
+                })
+            })
+            .collect();
+        Network {
+            tuples,
+            features,
+            weights,
+            games,
+        }
+    }
+
+    /// Checks if the network can rate `game`: it only learns classic games on the square board,
+    /// where tiles are powers of two and the symmetries of the grid keep the actions
+    pub fn rates(&self, game: &Game) -> bool {
+        game.variant() == Variant::default()
+    }
+
+    /// Expected points still to come in `game`, rated as an afterstate
+    pub fn value(&self, game: &Game) -> f32 {
+        let codes = codes(&game.copy_grid());
+        (self.features.iter())
+            .map(|(index, cells)| self.weights[*index][lookup(&codes, cells)])
+            .sum()
+    }
+
+    /// Action of `game` with the most points plus value of its afterstate, with the afterstate,
+    /// `None` if the board is locked
+    pub fn best_action(&self, game: &Game) -> Option<(i8, Game)> {
+        let mut best: Option<(f32, i8, Game)> = None;
+        for &action in game.topology().actions() {
+            let Some(after) = game.after_action(action) else {
+                continue;
+            };
+            let rating = (after.score() - game.score()) as f32 + self.value(&after);
+            if best.as_ref().is_none_or(|&(best, _, _)| rating > best) {
+                best = Some((rating, action, after));
+            }
+        }
+        best.map(|(_, action, after)| (action, after))
+    }
+
+    /// Plays the next training game, dealt by the seed `games`, and learns from each of its
+    /// actions with the learning `rate`
+    pub fn train(&mut self, rate: f32) -> Episode {
+        let mut game = Game::from_seed(self.games);
+        let mut previous: Option<Game> = None;
+        while let Some((_, after)) = self.best_action(&game) {
+            if let Some(previous) = previous {
+                let target = (after.score() - game.score()) as f32 + self.value(&after);
+                self.learn(&previous, target, rate);
+            }
+            game = after.clone();
+            game.random();
+            previous = Some(after);
+        }
+        // nothing follows the last afterstate
+        if let Some(previous) = previous {
+            self.learn(&previous, 0., rate);
+        }
+        self.games += 1;
+        Episode {
+            score: game.score(),
+            max_tile: game.max_tile(),
+            moves: game.moves(),
+        }
+    }
+
+    /// Plays a game dealt by `seed` with the actions of the network, without learning
+    pub fn play(&self, seed: u64) -> Episode {
+        let mut game = Game::from_seed(seed);
+        while let Some((_, after)) = self.best_action(&game) {
+            game = after;
+            game.random();
+        }
+        Episode {
+            score: game.score(),
+            max_tile: game.max_tile(),
+            moves: game.moves(),
+        }
+    }
+
+    /// Moves the value of the afterstate `game` towards `target`
+    fn learn(&mut self, game: &Game, target: f32, rate: f32) {
+        let codes = codes(&game.copy_grid());
+        let error = target - self.value(game);
+        let delta = rate / self.features.len() as f32 * error;
+        for (index, cells) in self.features.iter() {
+            self.weights[*index][lookup(&codes, cells)] += delta;
+        }
+    }
+
+    /// Loads the network stored at `path`
+    pub fn load(path: &Path) -> io::Result<Network> {
+        Network::read(&mut io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Saves the network at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the network in its file format
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, self.tuples.len() as u8])?;
+        writer.write_all(&self.games.to_le_bytes())?;
+        for tuple in self.tuples.iter() {
+            writer.write_all(&[tuple.len() as u8])?;
+            let cells: Vec<u8> = tuple.iter().map(|&cell| cell as u8).collect();
+            writer.write_all(&cells)?;
+        }
+        for weights in self.weights.iter() {
+            let bytes: Vec<u8> = weights
+                .iter()
+                .flat_map(|weight| weight.to_le_bytes())
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a network written by `write`
+    pub fn read(reader: &mut impl Read) -> io::Result<Network> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut header = [0; 17];
+        reader.read_exact(&mut header)?;
+        if &header[..7] != MAGIC || header[7] != VERSION {
+            return Err(invalid("Not a network of this version"));
+        }
+        let games = u64::from_le_bytes(header[9..17].try_into().unwrap());
+        let mut tuples = Vec::new();
+        for _ in 0..header[8] {
+            let mut size = [0];
+            reader.read_exact(&mut size)?;
+            let mut cells = vec![0; size[0] as usize];
+            reader.read_exact(&mut cells)?;
+            tuples.push(cells.into_iter().map(usize::from).collect::<Vec<usize>>());
+        }
+        if !valid(&tuples) {
+            return Err(invalid("Invalid tuples"));
+        }
+        let mut weights = Vec::new();
+        for tuple in tuples.iter() {
+            let mut bytes = vec![0; 4 << (4 * tuple.len())];
+            reader.read_exact(&mut bytes)?;
+            let chunks = bytes.chunks_exact(4);
+            weights.push(
+                chunks
+                    .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+                    .collect(),
+            );
+        }
+        Ok(Network::with_weights(tuples, weights, games))
+    }
+}
+
+/// Checks if there are between 1 and `MAX_TUPLES` tuples and if each one has at most `MAX_TUPLE`
+/// different cells of the grid
+fn valid(tuples: &[Vec<usize>]) -> bool {
+    let valid = |tuple: &Vec<usize>| {
+        (1..=MAX_TUPLE).contains(&tuple.len())
+            && (tuple.iter().enumerate()).all(|(i, &cell)| cell < 16 && !tuple[..i].contains(&cell))
+    };
+    (1..=MAX_TUPLES).contains(&tuples.len()) && tuples.iter().all(valid)
+}
+
+/// Cell matching `cell` in one of the 8 symmetries of the grid, rotations and reflections
+fn symmetric(cell: usize, symmetry: u8) -> usize {
+    let (mut x, mut y) = (cell % 4, cell / 4);
+    if symmetry & 1 != 0 {
+        x = 3 - x;
+    }
+    if symmetry & 2 != 0 {
+        y = 3 - y;
+    }
+    if symmetry & 4 != 0 {
+        (x, y) = (y, x);
+    }
+    y * 4 + x
+}
+
+/// Code of each cell on 4 bits: `0` when empty, the exponent of the tile rounded down up to 14
+/// and `15` for stones and special tiles
+fn codes(grid: &[u32; 16]) -> [usize; 16] {
+    grid.map(|value| match Cell::from(value) {
+        Cell::Empty => 0,
+        Cell::Number(number) => number.ilog2().clamp(1, 14) as usize,
+        _ => 15,
+    })
+}
+
+/// Index of the weight of the tuple of `cells`
+fn lookup(codes: &[usize; 16], cells: &[usize]) -> usize {
+    cells
+        .iter()
+        .fold(0, |index, &cell| index << 4 | codes[cell])
+}
+
+#[cfg(test)]
+mod test_ntuple {
+    use super::*;
+    use crate::game::{SpawnRules, STONE};
+
+    #[test]
+    fn symmetries() {
+        let network = Network::preset("small").unwrap();
+        assert_eq!(network.features.len(), 4 * 8);
+        let corners: Vec<usize> = (0..8).map(|symmetry| symmetric(0, symmetry)).collect();
+        assert_eq!(corners, [0, 3, 12, 15, 0, 12, 3, 15]);
+        assert_eq!(symmetric(1, 4), 4);
+        assert_eq!(Network::new(&[[0, 1, 1]]), None);
+        assert_eq!(Network::new(&[[0, 16]]), None);
+        assert!(Network::new(&[[0]; MAX_TUPLES]).is_some());
+        assert_eq!(Network::new(&[[0]; MAX_TUPLES + 1]), None);
+        assert_eq!(Network::preset("huge"), None);
+
+        let mut grid = [0; 16];
+        grid[0] = 2;
+        grid[5] = 2048;
+        grid[6] = 3;
+        grid[7] = STONE;
+        let codes = codes(&grid);
+        assert_eq!(codes[..8], [1, 0, 0, 0, 0, 11, 1, 15]);
+    }
+
+    #[test]
+    fn learning() {
+        let mut network = Network::preset("small").unwrap();
+        let game = Game::from_seed(0);
+        assert_eq!(network.value(&game), 0.);
+        for _ in 0..100 {
+            network.learn(&game, 100., 0.5);
+        }
+        assert!((network.value(&game) - 100.).abs() < 1.);
+
+        // a mirrored board shares the same weights
+        let grid = game.copy_grid();
+        let mirrored: Vec<u32> = (0..16).map(|cell| grid[symmetric(cell, 1)]).collect();
+        let variant = Variant::default();
+        let mirrored = Game::with_grid(
+            0,
+            mirrored.try_into().unwrap(),
+            variant,
+            SpawnRules::default(),
+        );
+        assert_eq!(network.value(&mirrored), network.value(&game));
+
+        // self-play improves on the untrained network
+        let mut network = Network::preset("small").unwrap();
+        let before: u32 = (1000..1005).map(|seed| network.play(seed).score).sum();
+        for _ in 0..60 {
+            network.train(DEFAULT_RATE);
+        }
+        assert_eq!(network.games, 60);
+        let after: u32 = (1000..1005).map(|seed| network.play(seed).score).sum();
+        assert!(after > before, "{before} {after}");
+    }
+
+    #[test]
+    fn weights_file() {
+        let mut network = Network::new(&[vec![0, 1], vec![0, 4, 5]]).unwrap();
+        for _ in 0..3 {
+            network.train(DEFAULT_RATE);
+        }
+        let mut file = Vec::new();
+        network.write(&mut file).unwrap();
+        assert_eq!(file.len(), 17 + 3 + 4 + 4 * (256 + 4096));
+        assert_eq!(Network::read(&mut file.as_slice()).unwrap(), network);
+        assert!(Network::read(&mut &file[..file.len() - 1]).is_err());
+        file[0] = b'x';
+        assert!(Network::read(&mut file.as_slice()).is_err());
+    }
+}
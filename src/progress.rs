@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const PROGRESS_FILE: &str = "puzzles.json";
+
+/// Puzzles solved by the player, by name, with the fewest moves used to solve each one
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PuzzleProgress {
+    pub solved: BTreeMap<String, u32>,
+}
+
+impl PuzzleProgress {
+    /// Loads the progress stored in `dir`, nothing is solved if there is none
+    pub fn load(dir: &Path) -> PuzzleProgress {
+        fs::read_to_string(dir.join(PROGRESS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Marks the puzzle `name` as solved in `moves` moves and saves the progress in `dir`
+    pub fn record(&mut self, dir: &Path, name: &str, moves: u32) -> io::Result<()> {
+        let best = self.solved.entry(name.to_string()).or_insert(moves);
+        *best = (*best).min(moves);
+        fs::create_dir_all(dir)?;
+        let content = serde_json::to_string(self)?;
+        fs::write(dir.join(PROGRESS_FILE), content)
+    }
+
+    /// Fewest moves used to solve the puzzle `name`, `None` if it is not solved
+    pub fn best(&self, name: &str) -> Option<u32> {
+        self.solved.get(name).copied()
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{self, Cell, Game, Variant};
+
+// puzzles shipped with the game, in the format read by `parse`
+const BUNDLED: &str = include_str!("../puzzles/bundled.json");
+
+/// Condition to meet in order to solve a puzzle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Goal {
+    /// Reach a tile of at least this value
+    Tile(u32),
+    /// Leave at most this number of tiles on the board
+    Clear(u32),
+    /// Score at least this number of points
+    Score(u32),
+}
+
+impl Goal {
+    /// Checks if `game` meets the goal, whatever its number of moves
+    pub fn is_reached(&self, game: &Game) -> bool {
+        match self {
+            Goal::Tile(value) => game.max_tile() >= *value,
+            Goal::Clear(tiles) => tiles_left(game) <= *tiles,
+            Goal::Score(score) => game.score() >= *score,
+        }
+    }
+
+    /// Sentence describing the goal to reach in `moves` moves
+    pub fn describe(&self, moves: u32) -> String {
+        match self {
+            Goal::Tile(value) => format!("reach {value} in {moves} moves"),
+            Goal::Clear(1) => format!("leave 1 tile in {moves} moves"),
+            Goal::Clear(tiles) => format!("leave {tiles} tiles in {moves} moves"),
+            Goal::Score(score) => format!("score {score} in {moves} moves"),
+        }
+    }
+}
+
+/// Number of tiles on the board of `game`
+pub fn tiles_left(game: &Game) -> u32 {
+    game.copy_grid()
+        .iter()
+        .filter(|&&value| Cell::from(value) != Cell::Empty)
+        .count() as u32
+}
+
+/// Hand-authored starting position with a fixed sequence of spawns and a goal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub name: String,
+    #[serde(default)]
+    pub variant: Variant,
+    #[serde(with = "grid")]
+    pub grid: [u32; 16],
+    /// Cell and value of the tile spawned after each action, nothing spawns once they are all
+    /// used
+    #[serde(default, with = "spawns")]
+    pub spawns: Vec<(usize, u32)>,
+    pub goal: Goal,
+    /// Number of actions allowed to reach the goal
+    pub moves: u32,
+}
+
+/// Cell as written in a puzzle file, a number or a symbol of the text notation
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FileCell {
+    Number(u32),
+    Symbol(String),
+}
+
+impl From<u32> for FileCell {
+    fn from(value: u32) -> Self {
+        match Cell::from(value) {
+            Cell::Empty | Cell::Number(_) => FileCell::Number(value),
+            _ => FileCell::Symbol(game::symbol(value)),
+        }
+    }
+}
+
+impl FileCell {
+    /// Value of the cell in the grid, the values reserved for special tiles are only written
+    /// with their symbol
+    fn value(&self) -> Result<u32, String> {
+        match self {
+            FileCell::Number(number) => game::parse_symbol(&number.to_string()),
+            FileCell::Symbol(symbol) => game::parse_symbol(symbol),
+        }
+    }
+}
+
+mod grid {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(grid: &[u32; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        let cells: Vec<FileCell> = grid.iter().map(|&value| FileCell::from(value)).collect();
+        cells.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u32; 16], D::Error> {
+        let cells = <[FileCell; 16]>::deserialize(deserializer)?;
+        let mut grid = [0; 16];
+        for (value, cell) in grid.iter_mut().zip(cells) {
+            *value = cell.value().map_err(serde::de::Error::custom)?;
+        }
+        Ok(grid)
+    }
+}
+
+mod spawns {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        spawns: &[(usize, u32)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let spawns: Vec<(usize, FileCell)> = (spawns.iter())
+            .map(|&(cell, value)| (cell, FileCell::from(value)))
+            .collect();
+        spawns.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(usize, u32)>, D::Error> {
+        (Vec::<(usize, FileCell)>::deserialize(deserializer)?.into_iter())
+            .map(|(cell, value)| Ok((cell, value.value().map_err(serde::de::Error::custom)?)))
+            .collect()
+    }
+}
+
+impl Puzzle {
+    /// Creates the game of the puzzle, at its starting position
+    pub fn game(&self) -> Game {
+        Game::from_position(self.grid, self.variant, self.spawns.clone())
+    }
+}
+
+/// Reads a list of puzzles written in JSON
+///
+/// A puzzle gives its `name`, its starting `grid` row by row, the `spawns` as `[cell, value]`
+/// pairs, its `goal` (`{"tile": 256}`, `{"clear": 2}` or `{"score": 500}`), the number of
+/// `moves` allowed and optionally its `variant`.
+///
+/// A cell of the grid or a spawned value is a number, `0` for an empty cell, or a special tile
+/// written with its symbol of the text notation of `Game`: `"#"` for a stone, `"?"` for a
+/// wildcard and `"*"` for a bomb. The values the game stores for special tiles are not valid
+/// numbers.
+pub fn parse(content: &str) -> Result<Vec<Puzzle>, String> {
+    let puzzles: Vec<Puzzle> = serde_json::from_str(content).map_err(|error| error.to_string())?;
+    for puzzle in puzzles.iter() {
+        if let Some(&(cell, _)) = puzzle.spawns.iter().find(|&&(cell, _)| cell >= 16) {
+            return Err(format!("Puzzle `{}` spawns in cell {cell}", puzzle.name));
+        }
+        if puzzle.moves == 0 {
+            return Err(format!("Puzzle `{}` allows no move", puzzle.name));
+        }
+    }
+    Ok(puzzles)
+}
+
+/// Loads the puzzles of the file at `path`
+pub fn load(path: &Path) -> Result<Vec<Puzzle>, String> {
+    parse(&fs::read_to_string(path).map_err(|error| error.to_string())?)
+}
+
+/// Saves `puzzles` at `path` in the format read by `parse`, creating its directory if needed
+pub fn save(puzzles: &[Puzzle], path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(puzzles)?;
+    fs::write(path, content)
+}
+
+/// Puzzles shipped with the game
+pub fn bundled() -> Vec<Puzzle> {
+    parse(BUNDLED).expect("Bundled puzzles are not valid")
+}
+
+#[cfg(test)]
+mod test_puzzle {
+    use super::*;
+
+    /// Fewest actions solving the puzzle from `game`, at most `moves`
+    fn solve(game: &Game, goal: Goal, moves: u32) -> Option<u32> {
+        if goal.is_reached(game) {
+            return Some(0);
+        }
+        if moves == 0 {
+            return None;
+        }
+        game.topology()
+            .actions()
+            .iter()
+            .filter_map(|&action| {
+                let mut next = game.after_action(action)?;
+                next.random();
+                solve(&next, goal, moves - 1)
+            })
+            .min()
+            .map(|solution| solution + 1)
+    }
+
+    #[test]
+    fn bundled_puzzles_are_solvable() {
+        let puzzles = bundled();
+        assert!(puzzles.len() >= 8);
+        for puzzle in puzzles {
+            let game = puzzle.game();
+            assert!(!puzzle.goal.is_reached(&game), "{} is solved", puzzle.name);
+            let solution = solve(&game, puzzle.goal, puzzle.moves);
+            assert!(solution.is_some(), "{} has no solution", puzzle.name);
+        }
+    }
+
+    #[test]
+    fn special_tiles_as_symbols() {
+        let puzzle = r##"[{"name": "a", "grid": [2,"#",2,0, 0,0,0,0, 0,0,0,0, 0,0,0,"*"],
+            "spawns": [[3, "?"], [7, 2]], "goal": {"tile": 4}, "moves": 1}]"##;
+        let puzzles = parse(puzzle).unwrap();
+        assert_eq!(puzzles[0].grid[..4], [2, game::STONE, 2, 0]);
+        assert_eq!(puzzles[0].grid[15], game::BOMB);
+        assert_eq!(puzzles[0].spawns, vec![(3, game::WILDCARD), (7, 2)]);
+        let json = serde_json::to_string(&puzzles).unwrap();
+        assert!(json.contains(r##""grid":[2,"#",2,0,"##));
+        assert_eq!(parse(&json), Ok(puzzles));
+    }
+
+    #[test]
+    fn invalid_puzzles() {
+        let puzzle = r#"[{"name": "a", "grid": [0,0,0,0, 0,0,0,0, 0,0,0,0, 0,0,0,2],
+            "spawns": [[16, 2]], "goal": {"tile": 4}, "moves": 1}]"#;
+        assert_eq!(
+            parse(puzzle),
+            Err(String::from("Puzzle `a` spawns in cell 16"))
+        );
+        assert!(parse(r#"[{"name": "a"}]"#).is_err());
+        let reserved = r#"[{"name": "a", "grid": [2,4294967295,2,0, 0,0,0,0, 0,0,0,0, 0,0,0,0],
+            "goal": {"tile": 4}, "moves": 1}]"#;
+        assert!(parse(reserved).is_err());
+        assert_eq!(Goal::Clear(2).describe(5), "leave 2 tiles in 5 moves");
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{Game, SpawnRules, Variant};
+use crate::ruleset::Ruleset;
+
+/// Seed, rules and actions of a game with the result claimed by the player, replaying the actions
+/// proves the result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    #[serde(default)]
+    pub variant: Variant,
+    pub rules: SpawnRules,
+    pub actions: Vec<i8>,
+    /// Score claimed by the player
+    pub score: u32,
+    /// Highest tile claimed by the player
+    pub max_tile: u32,
+}
+
+/// Reason why a replay is not valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The replay was played with other spawn rules than the expected ones
+    Rules { claimed: SpawnRules },
+    /// Action number `index` does not exist on the board or does not move any tile
+    IllegalAction { index: usize, action: i8 },
+    /// Score of the replayed game differs from the claimed one
+    Score { claimed: u32, replayed: u32 },
+    /// Highest tile of the replayed game differs from the claimed one
+    MaxTile { claimed: u32, replayed: u32 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Rules { claimed } => {
+                let claimed = claimed.notation(Ruleset::Classic);
+                write!(
+                    f,
+                    "the replay was played with other spawn rules `{claimed}`"
+                )
+            }
+            ReplayError::IllegalAction { index, action } => {
+                write!(f, "action {action} at position {index} is not possible")
+            }
+            ReplayError::Score { claimed, replayed } => {
+                write!(
+                    f,
+                    "claimed score {claimed} but the replay scores {replayed}"
+                )
+            }
+            ReplayError::MaxTile { claimed, replayed } => {
+                write!(
+                    f,
+                    "claimed tile {claimed} but the replay reaches {replayed}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    /// Creates the replay of `game`, claiming its current score and highest tile
+    pub fn from_game(game: &Game) -> Replay {
+        Replay {
+            seed: game.seed(),
+            variant: game.variant(),
+            rules: game.rules().clone(),
+            actions: game.actions().to_vec(),
+            score: game.score(),
+            max_tile: game.max_tile(),
+        }
+    }
+
+    /// Plays the actions on a game created from the seed, each action is followed by its spawns
+    pub fn replay(&self) -> Result<Game, ReplayError> {
+        let mut game = Game::with_variant(self.seed, self.variant, self.rules.clone());
+        for (index, &action) in self.actions.iter().enumerate() {
+            let exists = game.topology().actions().contains(&action);
+            if !exists || !game.partial_move(action) {
+                return Err(ReplayError::IllegalAction { index, action });
+            }
+            game.action(action);
+            game.random();
+        }
+        Ok(game)
+    }
+
+    /// Checks that the replay follows `rules`, then replays the actions and checks that the game
+    /// ends with the claimed score and highest tile
+    ///
+    /// The rules stored in the replay are not trusted, they must be `rules` with the spawned values
+    /// of the ruleset if it has its own, as in `Game::with_variant`.
+    pub fn verify(&self, rules: &SpawnRules) -> Result<Game, ReplayError> {
+        let mut expected = rules.clone();
+        if let Some(values) = self.variant.ruleset.spawn_values() {
+            expected.values = values;
+        }
+        if self.rules != expected {
+            return Err(ReplayError::Rules {
+                claimed: self.rules.clone(),
+            });
+        }
+        let game = self.replay()?;
+        if game.score() != self.score {
+            return Err(ReplayError::Score {
+                claimed: self.score,
+                replayed: game.score(),
+            });
+        }
+        if game.max_tile() != self.max_tile {
+            return Err(ReplayError::MaxTile {
+                claimed: self.max_tile,
+                replayed: game.max_tile(),
+            });
+        }
+        Ok(game)
+    }
+
+    /// Loads the replay stored at `path`
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Saves the replay at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(self)?;
+        fs::write(path, content)
+    }
+}
+
+#[cfg(test)]
+mod test_replay {
+    use super::*;
+    use crate::game::{SpawnerKind, ACTIONS, WILDCARD};
+
+    /// Plays `count` possible actions from the seed `seed`
+    fn played_game(seed: u64, count: usize) -> Game {
+        let mut game = Game::from_seed(seed);
+        for action in ACTIONS.iter().cycle() {
+            if game.moves() as usize == count || game.is_gameover() {
+                break;
+            }
+            if game.partial_move(*action) {
+                game.action(*action);
+                game.random();
+            }
+        }
+        game
+    }
+
+    #[test]
+    fn honest_replay() {
+        let game = played_game(3, 50);
+        let replay = Replay::from_game(&game);
+        assert_eq!(replay.actions.len(), 50);
+        let replayed = replay.verify(&SpawnRules::default()).unwrap();
+        assert_eq!(replayed.copy_grid(), game.copy_grid());
+        let json = serde_json::to_string(&replay).unwrap();
+        assert_eq!(serde_json::from_str::<Replay>(&json).unwrap(), replay);
+    }
+
+    #[test]
+    fn tampered_replays() {
+        let mut replay = Replay::from_game(&played_game(3, 50));
+        replay.score += 4;
+        assert_eq!(
+            replay.verify(&SpawnRules::default()).err(),
+            Some(ReplayError::Score {
+                claimed: replay.score,
+                replayed: replay.score - 4
+            })
+        );
+        replay.score -= 4;
+        replay.max_tile *= 2;
+        assert!(matches!(
+            replay.verify(&SpawnRules::default()),
+            Err(ReplayError::MaxTile { .. })
+        ));
+        replay.max_tile /= 2;
+        replay.actions.push(5);
+        assert_eq!(
+            replay.verify(&SpawnRules::default()).err(),
+            Some(ReplayError::IllegalAction {
+                index: 50,
+                action: 5
+            })
+        );
+        replay.actions.pop();
+        replay.seed += 1;
+        assert!(replay.verify(&SpawnRules::default()).is_err());
+    }
+
+    #[test]
+    fn forged_rules() {
+        let mut replay = Replay::from_game(&played_game(3, 50));
+        assert!(replay.verify(&SpawnRules::preset("hard").unwrap()).is_err());
+        for rules in [
+            SpawnRules {
+                values: vec![(1024, 1)],
+                ..SpawnRules::original()
+            },
+            SpawnRules {
+                spawner: SpawnerKind::Kind,
+                ..SpawnRules::original()
+            },
+            SpawnRules {
+                specials: vec![(WILDCARD, 10)],
+                ..SpawnRules::original()
+            },
+        ] {
+            replay.rules = rules.clone();
+            let game = Game::with_rules(3, rules);
+            (replay.score, replay.max_tile) = (game.score(), game.max_tile());
+            replay.actions.clear();
+            assert_eq!(
+                replay.verify(&SpawnRules::default()).err(),
+                Some(ReplayError::Rules {
+                    claimed: replay.rules.clone()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn rulesets_spawn_their_values() {
+        let variant = Variant {
+            ruleset: Ruleset::Fibonacci,
+            ..Variant::default()
+        };
+        let game = Game::with_variant(3, variant, SpawnRules::default());
+        assert!(Replay::from_game(&game)
+            .verify(&SpawnRules::default())
+            .is_ok());
+    }
+}
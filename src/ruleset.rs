@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::WIN_TILE;
+
+/// Rulesets which can be selected in the settings
+pub const RULESETS: [Ruleset; 4] = [
+    Ruleset::Classic,
+    Ruleset::Fibonacci,
+    Ruleset::PowersOfThree,
+    Ruleset::Threes,
+];
+
+/// Rules deciding which tiles merge together and which tiles exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ruleset {
+    /// Two equal tiles merge into their sum: 2, 4, 8, 16, ...
+    #[default]
+    Classic,
+    /// Two consecutive Fibonacci numbers merge into their sum: 1, 2, 3, 5, 8, ...
+    Fibonacci,
+    /// Three equal tiles merge into their sum, like in "2187": 1, 3, 9, 27, ...
+    PowersOfThree,
+    /// A `1` and a `2` merge into `3`, then two equal tiles merge into their sum, like in
+    /// "Threes": 1, 2, 3, 6, 12, ...
+    Threes,
+}
+
+/// Returns `true` if `a` and `b` are two consecutive numbers of the Fibonacci sequence
+fn consecutive_fibonacci(a: u32, b: u32) -> bool {
+    let (low, high) = (a.min(b), a.max(b));
+    if low == 1 && high <= 2 {
+        return true;
+    }
+    let (mut x, mut y) = (1u32, 2u32);
+    while x < low {
+        (x, y) = (y, x.saturating_add(y));
+    }
+    x == low && y == high
+}
+
+impl Ruleset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ruleset::Classic => "classic",
+            Ruleset::Fibonacci => "fibonacci",
+            Ruleset::PowersOfThree => "2187",
+            Ruleset::Threes => "threes",
+        }
+    }
+
+    /// Returns the ruleset called `name`
+    pub fn from_name(name: &str) -> Option<Ruleset> {
+        RULESETS.into_iter().find(|ruleset| ruleset.name() == name)
+    }
+
+    /// Number of tiles which merge together
+    pub fn merge_size(&self) -> usize {
+        match self {
+            Ruleset::PowersOfThree => 3,
+            _ => 2,
+        }
+    }
+
+    /// Returns the value of the tile created by merging `values`, `None` if they do not merge
+    ///
+    /// `values` contains `merge_size` non zero values, in the order of the line.
+    pub fn merge(&self, values: &[u32]) -> Option<u32> {
+        if values.len() != self.merge_size() || values.contains(&0) {
+            return None;
+        }
+        let sum = values.iter().sum();
+        let equal = values.iter().all(|&value| value == values[0]);
+        let merged = match self {
+            Ruleset::Classic | Ruleset::PowersOfThree => equal,
+            Ruleset::Fibonacci => consecutive_fibonacci(values[0], values[1]),
+            Ruleset::Threes => sum == 3 || (equal && values[0] >= 3),
+        };
+        merged.then_some(sum)
+    }
+
+    /// Returns `true` if two neighbours `a` and `b` can take part in the same merge
+    pub fn related(&self, a: u32, b: u32) -> bool {
+        match self {
+            Ruleset::PowersOfThree => a != 0 && a == b,
+            _ => self.merge(&[a, b]).is_some(),
+        }
+    }
+
+    /// Value following `value` in the sequence of tiles, created when it merges with wildcards
+    pub fn promote(&self, value: u32) -> u32 {
+        match self {
+            Ruleset::Classic => value * 2,
+            Ruleset::Fibonacci => {
+                let (mut x, mut y) = (1u32, 2u32);
+                while x < value {
+                    (x, y) = (y, x.saturating_add(y));
+                }
+                y
+            }
+            Ruleset::PowersOfThree => value * 3,
+            Ruleset::Threes => match value {
+                1 | 2 => 3,
+                _ => value * 2,
+            },
+        }
+    }
+
+    /// Position of `value` in the sequence of tiles, starting at `1` for the smallest tile
+    ///
+    /// It is used to pick the color of a tile, `0` is returned for an empty cell.
+    pub fn rank(&self, value: u32) -> usize {
+        if value == 0 {
+            return 0;
+        }
+        match self {
+            Ruleset::Classic => value.ilog2() as usize,
+            Ruleset::Fibonacci => {
+                let (mut rank, mut x, mut y) = (1, 1u32, 2u32);
+                while x < value {
+                    (x, y) = (y, x.saturating_add(y));
+                    rank += 1;
+                }
+                rank
+            }
+            Ruleset::PowersOfThree => value.ilog(3) as usize + 1,
+            Ruleset::Threes => match value {
+                1 | 2 => value as usize,
+                _ => (value / 3).ilog2() as usize + 3,
+            },
+        }
+    }
+
+    /// Values which spawn with their weights, `None` if the spawn rules decide
+    pub fn spawn_values(&self) -> Option<Vec<(u32, u32)>> {
+        match self {
+            Ruleset::Classic => None,
+            Ruleset::Fibonacci => Some(vec![(1, 9), (2, 1)]),
+            Ruleset::PowersOfThree => Some(vec![(1, 9), (3, 1)]),
+            Ruleset::Threes => Some(vec![(1, 2), (2, 2), (3, 1)]),
+        }
+    }
+
+    /// Value of the tile to reach in order to win the game
+    pub fn win_tile(&self) -> u32 {
+        match self {
+            Ruleset::Classic => WIN_TILE,
+            Ruleset::Fibonacci => 2584,
+            Ruleset::PowersOfThree => 2187,
+            Ruleset::Threes => 3072,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_ruleset {
+    use super::*;
+
+    #[test]
+    fn merges() {
+        assert_eq!(Ruleset::Classic.merge(&[4, 4]), Some(8));
+        assert_eq!(Ruleset::Classic.merge(&[4, 2]), None);
+        assert_eq!(Ruleset::Fibonacci.merge(&[1, 1]), Some(2));
+        assert_eq!(Ruleset::Fibonacci.merge(&[5, 3]), Some(8));
+        assert_eq!(Ruleset::Fibonacci.merge(&[3, 3]), None);
+        assert_eq!(Ruleset::Fibonacci.merge(&[2, 5]), None);
+        assert_eq!(Ruleset::PowersOfThree.merge(&[9, 9, 9]), Some(27));
+        assert_eq!(Ruleset::PowersOfThree.merge(&[9, 9]), None);
+        assert_eq!(Ruleset::Threes.merge(&[2, 1]), Some(3));
+        assert_eq!(Ruleset::Threes.merge(&[1, 1]), None);
+        assert_eq!(Ruleset::Threes.merge(&[6, 6]), Some(12));
+        assert_eq!(Ruleset::Fibonacci.promote(1), 2);
+        assert_eq!(Ruleset::Fibonacci.promote(5), 8);
+        assert_eq!(Ruleset::Threes.promote(1), 3);
+    }
+
+    #[test]
+    fn ranks() {
+        assert_eq!(Ruleset::Classic.rank(2048), 11);
+        assert_eq!(Ruleset::Fibonacci.rank(1), 1);
+        assert_eq!(Ruleset::Fibonacci.rank(8), 5);
+        assert_eq!(Ruleset::PowersOfThree.rank(2187), 8);
+        assert_eq!(Ruleset::Threes.rank(2), 2);
+        assert_eq!(Ruleset::Threes.rank(12), 5);
+        for ruleset in RULESETS {
+            assert_eq!(ruleset.rank(0), 0);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::Game;
+use crate::mode::GameMode;
+
+const SAVE_FILE: &str = "savegame.json";
+
+/// Game in progress stored when the window is closed, it is resumed at the next launch
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub game: Game,
+    /// Other boards of a multi board game
+    #[serde(default)]
+    pub others: Vec<Game>,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// Time spent on the game in seconds
+    pub duration: f64,
+    pub undos: u32,
+    pub won: bool,
+}
+
+impl SavedGame {
+    /// Loads the game saved in `dir` if there is one
+    pub fn load(dir: &Path) -> Option<SavedGame> {
+        fs::read_to_string(dir.join(SAVE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    /// Saves the game in `dir`
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let content = serde_json::to_string(self)?;
+        fs::write(dir.join(SAVE_FILE), content)
+    }
+
+    /// Removes the saved game from `dir`
+    pub fn delete(dir: &Path) -> io::Result<()> {
+        match fs::remove_file(dir.join(SAVE_FILE)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_save {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("rust-2048-save-{}", std::process::id()));
+        let mut game = Game::from_seed(3);
+        for action in [-1, 4, 1, -4, -1, 4] {
+            game.action(action);
+        }
+        let mode = GameMode::MoveLimited {
+            moves: 200,
+            target: 1024,
+        };
+        let saved = SavedGame {
+            game: game.clone(),
+            others: vec![],
+            mode,
+            duration: 42.5,
+            undos: 2,
+            won: false,
+        };
+        saved.save(&dir).unwrap();
+        let loaded = SavedGame::load(&dir).unwrap();
+        assert_eq!(loaded.game.copy_grid(), game.copy_grid());
+        assert_eq!(loaded.game.moves(), game.moves());
+        assert_eq!(loaded.game.score(), game.score());
+        assert_eq!(loaded.mode, mode);
+        assert_eq!(loaded.duration, 42.5);
+        assert_eq!(loaded.undos, 2);
+        SavedGame::delete(&dir).unwrap();
+        assert!(SavedGame::load(&dir).is_none());
+        fs::remove_dir(&dir).unwrap();
+    }
+}
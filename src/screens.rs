@@ -0,0 +1,426 @@
+use ggez::glam::*;
+use ggez::graphics::{Canvas, Color, DrawMode, Drawable, Mesh, PxScale, Rect, Text, TextFragment};
+use ggez::{Context, GameResult};
+
+use crate::colors::{GameColors, BLUNDER, DIM, EVALUATION, OVERLAY};
+use crate::mode::{GameMode, MODES};
+use crate::progress::PuzzleProgress;
+use crate::puzzle::Puzzle;
+use crate::ruleset::Ruleset;
+use crate::settings::{Settings, ENTRIES};
+use crate::stats::{GameRecord, Statistics};
+
+// puzzles listed at once by the puzzle selection screen, the list scrolls with the selection
+const PUZZLE_ROWS: usize = 8;
+
+/// Formats a duration given in seconds as `m:ss` or `h:mm:ss`
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Creates a text with the font of the game
+pub fn text(content: impl Into<String>, size: f32, color: Color) -> Text {
+    Text::new(
+        TextFragment::new(content)
+            .font("ClearSans-Bold")
+            .color(color)
+            .scale(PxScale::from(size)),
+    )
+}
+
+/// Draws a text horizontally centered on `x`
+pub fn draw_centered(canvas: &mut Canvas, ctx: &mut Context, text: &Text, x: f32, y: f32) {
+    let [w, _] = text.dimensions(ctx).unwrap().center().into();
+    canvas.draw(text, Vec2::new(x - w, y));
+}
+
+/// Draws a translucent layer over the whole window
+pub fn draw_overlay(canvas: &mut Canvas, ctx: &mut Context) -> GameResult<()> {
+    let (width, height) = ctx.gfx.drawable_size();
+    let overlay = Mesh::new_rectangle(
+        ctx,
+        DrawMode::fill(),
+        Rect::new(0., 0., width, height),
+        OVERLAY,
+    )?;
+    canvas.draw(&overlay, Vec2::ZERO);
+    Ok(())
+}
+
+/// Draws boxes above the board (score, moves, time, ...), each item is a title and a value
+pub fn draw_hud(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    items: &[(&str, String)],
+    color: Color,
+) -> GameResult<()> {
+    for (i, (title, value)) in items.iter().enumerate() {
+        let x = 15. + 162. * i as f32;
+        let rect = Mesh::new_rounded_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(x, 12., 146., 60.),
+            5.,
+            color,
+        )?;
+        canvas.draw(&rect, Vec2::ZERO);
+        draw_centered(canvas, ctx, &text(*title, 16., OVERLAY), x + 73., 16.);
+        draw_centered(canvas, ctx, &text(value, 28., Color::WHITE), x + 73., 36.);
+    }
+    Ok(())
+}
+
+/// Draws the evaluation bar of a reviewed move between the boxes and the board, filled by the
+/// `accuracy` of the played action
+pub fn draw_evaluation(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    accuracy: f32,
+    blunder: bool,
+    color: Color,
+) -> GameResult<()> {
+    let track = Rect::new(15., 76., 470., 10.);
+    let mesh = Mesh::new_rounded_rectangle(ctx, DrawMode::fill(), track, 5., color)?;
+    canvas.draw(&mesh, Vec2::ZERO);
+    let width = track.w * accuracy.clamp(0., 1.);
+    if width >= 1. {
+        let fill = if blunder { BLUNDER } else { EVALUATION };
+        let bar = Rect { w: width, ..track };
+        let mesh =
+            Mesh::new_rounded_rectangle(ctx, DrawMode::fill(), bar, (width / 2.).min(5.), fill)?;
+        canvas.draw(&mesh, Vec2::ZERO);
+    }
+    Ok(())
+}
+
+/// Dims the board and draws the pause screen
+pub fn draw_pause(canvas: &mut Canvas, ctx: &mut Context) -> GameResult<()> {
+    let (width, height) = ctx.gfx.drawable_size();
+    let dim = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., width, height), DIM)?;
+    canvas.draw(&dim, Vec2::ZERO);
+    draw_centered(
+        canvas,
+        ctx,
+        &text("Paused", 78., Color::WHITE),
+        width / 2.,
+        220.,
+    );
+    let help = text("P / Escape: resume", 24., Color::WHITE);
+    draw_centered(canvas, ctx, &help, width / 2., 320.);
+    Ok(())
+}
+
+/// Dims the board and draws the lobby of a network race, `players` are waiting for `needed`
+/// players
+pub fn draw_lobby(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    players: &[String],
+    needed: usize,
+) -> GameResult<()> {
+    let (width, height) = ctx.gfx.drawable_size();
+    let dim = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., width, height), DIM)?;
+    canvas.draw(&dim, Vec2::ZERO);
+    let title = format!("Waiting {}/{needed}", players.len());
+    draw_centered(
+        canvas,
+        ctx,
+        &text(title, 56., Color::WHITE),
+        width / 2.,
+        180.,
+    );
+    for (i, name) in players.iter().enumerate() {
+        let y = 260. + 32. * i as f32;
+        draw_centered(canvas, ctx, &text(name, 24., Color::WHITE), width / 2., y);
+    }
+    Ok(())
+}
+
+/// Draws the result of today's daily challenge, `summary` is `None` if it was not played yet
+pub fn draw_daily(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    summary: Option<&str>,
+    copied: bool,
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    draw_centered(canvas, ctx, &text("Daily challenge", 48., color), 250., 40.);
+    let Some(summary) = summary else {
+        let message = text("Not played today", 24., color);
+        draw_centered(canvas, ctx, &message, 250., 200.);
+        return Ok(());
+    };
+    // the emoji board of the summary is only meant for the clipboard
+    for (i, line) in summary.lines().take(2).enumerate() {
+        let y = 150. + 40. * i as f32;
+        draw_centered(canvas, ctx, &text(line, 24., color), 250., y);
+    }
+    let status = if copied {
+        "Summary copied to the clipboard"
+    } else {
+        "Summary could not be copied"
+    };
+    draw_centered(canvas, ctx, &text(status, 22., color), 250., 300.);
+    let help = text("C: copy   Escape: close", 20., color);
+    draw_centered(canvas, ctx, &help, 250., 460.);
+    Ok(())
+}
+
+/// Draws the best games of a mode
+pub fn draw_high_scores(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    title: &str,
+    games: &[&GameRecord],
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    draw_centered(canvas, ctx, &text(title, 40., color), 250., 10.);
+    let columns = [
+        ("#", 40.),
+        ("Score", 130.),
+        ("Tile", 240.),
+        ("Moves", 330.),
+        ("Time", 430.),
+    ];
+    for (name, x) in columns {
+        draw_centered(canvas, ctx, &text(name, 20., color), x, 70.);
+    }
+    for (i, game) in games.iter().enumerate() {
+        let y = 100. + 32. * i as f32;
+        let values = [
+            (i + 1).to_string(),
+            game.score.to_string(),
+            game.max_tile.to_string(),
+            game.moves.to_string(),
+            format_duration(game.duration),
+        ];
+        for (value, (_, x)) in values.into_iter().zip(columns) {
+            draw_centered(canvas, ctx, &text(value, 20., color), x, y);
+        }
+    }
+    if games.is_empty() {
+        draw_centered(
+            canvas,
+            ctx,
+            &text("No game played yet", 24., color),
+            250.,
+            200.,
+        );
+    }
+    let help = text("Left / Right: change mode   Escape: close", 20., color);
+    draw_centered(canvas, ctx, &help, 250., 460.);
+    Ok(())
+}
+
+/// Draws the mode selection screen, `selected` is the index of the selected mode in `MODES`,
+/// titled for new games following `ruleset`
+pub fn draw_modes(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    current: GameMode,
+    ruleset: Ruleset,
+    selected: usize,
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    draw_centered(canvas, ctx, &text("Game modes", 56., color), 250., 30.);
+    for (i, mode) in MODES.iter().enumerate() {
+        let mut label = mode.title(ruleset);
+        if *mode == current {
+            label = format!("{label} (current)");
+        }
+        if i == selected {
+            label = format!("> {label} <");
+        }
+        let y = 95. + 28. * i as f32;
+        draw_centered(canvas, ctx, &text(label, 22., color), 250., y);
+    }
+    let help = text("Enter: new game   Escape: close", 20., color);
+    draw_centered(canvas, ctx, &help, 250., 460.);
+    Ok(())
+}
+
+/// Draws the puzzle selection screen, `selected` is the index of the selected puzzle
+pub fn draw_puzzles(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    puzzles: &[Puzzle],
+    progress: &PuzzleProgress,
+    selected: usize,
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    let title = format!("Puzzles ({}/{})", progress.solved.len(), puzzles.len());
+    draw_centered(canvas, ctx, &text(title, 48., color), 250., 30.);
+    let first = selected
+        .saturating_sub(PUZZLE_ROWS / 2)
+        .min(puzzles.len().saturating_sub(PUZZLE_ROWS));
+    let rows = puzzles.iter().enumerate().skip(first).take(PUZZLE_ROWS);
+    for (row, (i, puzzle)) in rows.enumerate() {
+        let mut label = format!("{}: {}", puzzle.name, puzzle.goal.describe(puzzle.moves));
+        if let Some(moves) = progress.best(&puzzle.name) {
+            label = format!("{label} (solved in {moves})");
+        }
+        if i == selected {
+            label = format!("> {label}");
+        }
+        let y = 100. + 38. * row as f32;
+        draw_centered(canvas, ctx, &text(label, 18., color), 250., y);
+    }
+    if puzzles.len() > PUZZLE_ROWS {
+        let last = (first + PUZZLE_ROWS).min(puzzles.len());
+        let shown = format!("{}-{last} of {}", first + 1, puzzles.len());
+        draw_centered(canvas, ctx, &text(shown, 16., color), 250., 415.);
+    }
+    let help = text("Enter: play   Escape: close", 20., color);
+    draw_centered(canvas, ctx, &help, 250., 460.);
+    Ok(())
+}
+
+/// Draws the settings screen, `selected` is the index of the selected entry
+pub fn draw_settings(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    settings: &Settings,
+    selected: usize,
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    draw_centered(canvas, ctx, &text("Settings", 56., color), 250., 40.);
+    for (i, &entry) in ENTRIES.iter().enumerate() {
+        let label = settings.label(entry);
+        let label = if i == selected {
+            format!("< {label} >")
+        } else {
+            label
+        };
+        draw_centered(
+            canvas,
+            ctx,
+            &text(label, 28., color),
+            250.,
+            110. + 36. * i as f32,
+        );
+    }
+    let help = text("Arrows: change   M: mute   Escape: close", 20., color);
+    draw_centered(canvas, ctx, &help, 250., 440.);
+    Ok(())
+}
+
+/// Draws the statistics screen: totals, max tile distribution and scores over time
+pub fn draw_statistics(
+    canvas: &mut Canvas,
+    ctx: &mut Context,
+    statistics: &Statistics,
+    game_colors: &GameColors,
+    color: Color,
+) -> GameResult<()> {
+    draw_overlay(canvas, ctx)?;
+    draw_centered(canvas, ctx, &text("Statistics", 40., color), 250., 10.);
+    let (streak, best_streak) = statistics.win_streaks();
+    let lines = [
+        format!(
+            "Games: {}   Wins: {}   Moves: {}   Time: {}",
+            statistics.games_played(),
+            statistics.wins(),
+            statistics.total_moves(),
+            format_duration(statistics.total_duration())
+        ),
+        format!(
+            "Best score: {}   Average score: {:.0}",
+            statistics.best_score(),
+            statistics.average_score()
+        ),
+        format!("Win streak: {streak}   Best win streak: {best_streak}"),
+    ];
+    for (i, line) in lines.into_iter().enumerate() {
+        draw_centered(
+            canvas,
+            ctx,
+            &text(line, 18., color),
+            250.,
+            65. + 24. * i as f32,
+        );
+    }
+
+    // max tile distribution
+    draw_centered(canvas, ctx, &text("Max tiles", 18., color), 250., 140.);
+    let distribution = statistics.tile_distribution();
+    let highest = distribution.values().copied().max().unwrap_or(1) as f32;
+    let width = 440. / distribution.len().max(1) as f32;
+    for (i, (tile, &count)) in distribution.iter().enumerate() {
+        let height = 90. * count as f32 / highest;
+        let x = 30. + width * i as f32;
+        // the tile is coloured with the ruleset of the last game which reached it
+        let ruleset = statistics
+            .games
+            .iter()
+            .rev()
+            .find(|game| game.max_tile == *tile)
+            .map_or(Ruleset::Classic, |game| game.ruleset);
+        let bar = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(x + 0.1 * width, 270. - height, 0.8 * width, height),
+            game_colors[ruleset.rank(*tile)].rgb,
+        )?;
+        canvas.draw(&bar, Vec2::ZERO);
+        let count = text(count.to_string(), 13., color);
+        draw_centered(canvas, ctx, &count, x + width / 2., 255. - height);
+        draw_centered(
+            canvas,
+            ctx,
+            &text(tile.to_string(), 13., color),
+            x + width / 2.,
+            272.,
+        );
+    }
+
+    // scores over time
+    draw_centered(canvas, ctx, &text("Scores", 18., color), 250., 300.);
+    let (left, top, right, bottom) = (40., 330., 470., 470.);
+    let axes = Mesh::new_line(
+        ctx,
+        &[
+            Vec2::new(left, top),
+            Vec2::new(left, bottom),
+            Vec2::new(right, bottom),
+        ],
+        2.,
+        color,
+    )?;
+    canvas.draw(&axes, Vec2::ZERO);
+    let scores = statistics.scores();
+    if scores.len() > 1 {
+        let best = statistics.best_score().max(1) as f32;
+        let step = (right - left) / (scores.len() - 1) as f32;
+        let points: Vec<Vec2> = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| {
+                Vec2::new(
+                    left + step * i as f32,
+                    bottom - (bottom - top) * score as f32 / best,
+                )
+            })
+            .collect();
+        let line = Mesh::new_line(
+            ctx,
+            &points,
+            2.,
+            game_colors[Ruleset::Classic.rank(2048)].rgb,
+        )?;
+        canvas.draw(&line, Vec2::ZERO);
+    }
+    let best = text(statistics.best_score().to_string(), 13., color);
+    draw_centered(canvas, ctx, &best, left, top - 18.);
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{SpawnRules, SpawnerKind, Variant, SPAWNERS, SPAWN_PRESETS};
+use crate::ruleset::{Ruleset, RULESETS};
+use crate::topology::{Topology, TOPOLOGIES};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+// step used when a volume is changed from the settings screen
+const VOLUME_STEP: f32 = 0.1;
+
+/// Entries shown by the settings screen, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsEntry {
+    MasterVolume,
+    EffectsVolume,
+    MusicVolume,
+    Music,
+    Mute,
+    Ruleset,
+    Topology,
+    SpawnRules,
+    Spawner,
+}
+
+pub const ENTRIES: [SettingsEntry; 9] = [
+    SettingsEntry::MasterVolume,
+    SettingsEntry::EffectsVolume,
+    SettingsEntry::MusicVolume,
+    SettingsEntry::Music,
+    SettingsEntry::Mute,
+    SettingsEntry::Ruleset,
+    SettingsEntry::Topology,
+    SettingsEntry::SpawnRules,
+    SettingsEntry::Spawner,
+];
+
+/// Returns the item next to `current` in `items`, `step` is `-1` or `1`
+pub fn cycle<T: Copy + PartialEq>(items: &[T], current: T, step: i8) -> T {
+    let index = items.iter().position(|&item| item == current).unwrap_or(0) as i8;
+    items[(index + step).rem_euclid(items.len() as i8) as usize]
+}
+
+/// Player preferences kept between two sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub effects_volume: f32,
+    pub music_volume: f32,
+    pub music: bool,
+    pub muted: bool,
+    /// Ruleset used by new games
+    pub ruleset: Ruleset,
+    /// Shape of the board of new games
+    pub topology: Topology,
+    /// Name of the spawn rules preset used by new games
+    pub spawn_rules: String,
+    /// Spawner used by new games
+    pub spawner: SpawnerKind,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.8,
+            effects_volume: 1.0,
+            music_volume: 0.5,
+            music: true,
+            muted: false,
+            ruleset: Ruleset::Classic,
+            topology: Topology::Square,
+            spawn_rules: String::from(SPAWN_PRESETS[0]),
+            spawner: SpawnerKind::Uniform,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings stored in `dir`, defaults are used if the file is missing or invalid
+    pub fn load(dir: &Path) -> Settings {
+        fs::read_to_string(dir.join(SETTINGS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Saves settings in `dir`
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(dir.join(SETTINGS_FILE), content)
+    }
+
+    /// Volume applied to sound effects
+    pub fn effects_gain(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.master_volume * self.effects_volume
+        }
+    }
+
+    /// Volume applied to the background music
+    pub fn music_gain(&self) -> f32 {
+        if self.muted || !self.music {
+            0.
+        } else {
+            self.master_volume * self.music_volume
+        }
+    }
+
+    /// Spawn rules used by new games
+    pub fn spawn_rules(&self) -> SpawnRules {
+        SpawnRules {
+            spawner: self.spawner,
+            ..SpawnRules::preset(&self.spawn_rules).unwrap_or_default()
+        }
+    }
+
+    /// Ruleset and board used by new games
+    pub fn variant(&self) -> Variant {
+        Variant {
+            ruleset: self.ruleset,
+            topology: self.topology,
+        }
+    }
+
+    /// Text displayed by the settings screen for an entry
+    pub fn label(&self, entry: SettingsEntry) -> String {
+        let on_off = |value: bool| if value { "on" } else { "off" };
+        let percent = |value: f32| (value * 100.).round() as u32;
+        match entry {
+            SettingsEntry::MasterVolume => {
+                format!("Master volume: {}%", percent(self.master_volume))
+            }
+            SettingsEntry::EffectsVolume => {
+                format!("Effects volume: {}%", percent(self.effects_volume))
+            }
+            SettingsEntry::MusicVolume => format!("Music volume: {}%", percent(self.music_volume)),
+            SettingsEntry::Music => format!("Music: {}", on_off(self.music)),
+            SettingsEntry::Mute => format!("Mute: {}", on_off(self.muted)),
+            SettingsEntry::Ruleset => format!("Rules (new games): {}", self.ruleset.name()),
+            SettingsEntry::Topology => format!("Board (new games): {}", self.topology.name()),
+            SettingsEntry::SpawnRules => format!("Spawns (new games): {}", self.spawn_rules),
+            SettingsEntry::Spawner => format!("Spawner (new games): {}", self.spawner.name()),
+        }
+    }
+
+    /// Changes the value of an entry, `step` is `-1` or `1`
+    pub fn adjust(&mut self, entry: SettingsEntry, step: i8) {
+        let change = |value: f32| (value + step as f32 * VOLUME_STEP).clamp(0., 1.);
+        match entry {
+            SettingsEntry::MasterVolume => self.master_volume = change(self.master_volume),
+            SettingsEntry::EffectsVolume => self.effects_volume = change(self.effects_volume),
+            SettingsEntry::MusicVolume => self.music_volume = change(self.music_volume),
+            SettingsEntry::Music => self.music = !self.music,
+            SettingsEntry::Mute => self.muted = !self.muted,
+            SettingsEntry::Ruleset => self.ruleset = cycle(&RULESETS, self.ruleset, step),
+            SettingsEntry::Topology => self.topology = cycle(&TOPOLOGIES, self.topology, step),
+            SettingsEntry::SpawnRules => {
+                let preset = cycle(&SPAWN_PRESETS, self.spawn_rules.as_str(), step);
+                self.spawn_rules = String::from(preset);
+            }
+            SettingsEntry::Spawner => self.spawner = cycle(&SPAWNERS, self.spawner, step),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_settings {
+    use super::*;
+
+    #[test]
+    fn volumes_clamped() {
+        let mut settings = Settings::default();
+        settings.adjust(SettingsEntry::EffectsVolume, 1);
+        assert_eq!(settings.effects_volume, 1.);
+        for _ in 0..10 {
+            settings.adjust(SettingsEntry::MasterVolume, -1);
+        }
+        assert_eq!(settings.master_volume, 0.);
+        settings.adjust(SettingsEntry::MasterVolume, 1);
+        assert!((settings.master_volume - VOLUME_STEP).abs() < 1e-6);
+    }
+
+    #[test]
+    fn muted_gains() {
+        let mut settings = Settings::default();
+        settings.adjust(SettingsEntry::Mute, 1);
+        assert_eq!(settings.effects_gain(), 0.);
+        assert_eq!(settings.music_gain(), 0.);
+        settings.adjust(SettingsEntry::Mute, 1);
+        settings.adjust(SettingsEntry::Music, 1);
+        assert!(settings.effects_gain() > 0.);
+        assert_eq!(settings.music_gain(), 0.);
+    }
+
+    #[test]
+    fn master_volume_scales_gains() {
+        let settings = Settings {
+            master_volume: 0.5,
+            effects_volume: 0.8,
+            music_volume: 0.4,
+            ..Settings::default()
+        };
+        assert!((settings.effects_gain() - 0.4).abs() < 1e-6);
+        assert!((settings.music_gain() - 0.2).abs() < 1e-6);
+        let silent = Settings {
+            master_volume: 0.,
+            ..settings
+        };
+        assert_eq!(silent.effects_gain(), 0.);
+        assert_eq!(silent.music_gain(), 0.);
+    }
+}
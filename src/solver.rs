@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use crate::game::Game;
+use crate::ntuple::Network;
+use crate::tablebase::Tablebase;
+
+/// Number of actions looked ahead by the default solver
+pub const DEFAULT_DEPTH: u32 = 2;
+
+// rating of a locked board, below the rating of any board which can still move
+const LOCKED: f32 = 0.;
+
+/// Action with the highest rating among `ratings`, the first one in case of a tie
+pub fn best_rated(ratings: &[(i8, f32)]) -> Option<(i8, f32)> {
+    (ratings.iter().copied()).fold(None, |best, (action, value)| match best {
+        Some((_, best_value)) if best_value >= value => best,
+        _ => Some((action, value)),
+    })
+}
+
+/// Expectimax search choosing the actions of a game
+///
+/// The player picks the action with the best rating and spawns are averaged by their probability
+/// (see `Game::spawn_outcomes`), boards at the end of the search are rated by `Game::heuristic`.
+/// With a network, actions are rated by their points plus the points expected afterwards instead,
+/// the network rating the boards at the end of the search before their spawn, for the games it
+/// rates (see `Network::rates`). Games covered by the tablebase are rated exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solver {
+    /// Number of actions looked ahead, at least 1
+    pub depth: u32,
+    pub tablebase: Option<Arc<Tablebase>>,
+    pub network: Option<Arc<Network>>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new(DEFAULT_DEPTH)
+    }
+}
+
+impl Solver {
+    pub fn new(depth: u32) -> Solver {
+        Solver {
+            depth,
+            tablebase: None,
+            network: None,
+        }
+    }
+
+    /// Expected rating of every possible action of `game`, in the order of its topology
+    ///
+    /// Games covered by the tablebase rate actions by their probability to reach its target with
+    /// optimal play, or by their expected score once the target is out of reach.
+    pub fn evaluate(&self, game: &Game) -> Vec<(i8, f32)> {
+        let tablebase = self.tablebase.as_ref();
+        if let Some(values) = tablebase.and_then(|tablebase| tablebase.action_values(game)) {
+            let winnable = values.iter().any(|(_, value)| value.win > 0.);
+            return (values.into_iter())
+                .map(|(action, value)| (action, if winnable { value.win } else { value.score }))
+                .collect();
+        }
+        if (self.network.as_ref()).is_some_and(|network| !network.rates(game)) {
+            let solver = Solver {
+                network: None,
+                ..self.clone()
+            };
+            return solver.evaluate(game);
+        }
+        let depth = self.depth.max(1);
+        (game.topology().actions().iter())
+            .filter_map(|&action| {
+                let after = game.after_action(action)?;
+                Some((action, self.action_rating(game, &after, depth - 1)))
+            })
+            .collect()
+    }
+
+    /// Action with the best rating, `None` if the board is locked
+    pub fn best_action(&self, game: &Game) -> Option<i8> {
+        best_rated(&self.evaluate(game)).map(|(action, _)| action)
+    }
+
+    /// Rating of `game` when the player chooses the action, `depth` actions ahead
+    fn rating(&self, game: &Game, depth: u32) -> f32 {
+        if depth == 0 {
+            return game.heuristic();
+        }
+        (game.topology().actions().iter())
+            .filter_map(|&action| game.after_action(action))
+            .map(|after| self.action_rating(game, &after, depth - 1))
+            .fold(LOCKED, f32::max)
+    }
+
+    /// Rating of `after`, `game` after an action, `depth` actions ahead, with the points of the
+    /// action when a network rates the points to come
+    fn action_rating(&self, game: &Game, after: &Game, depth: u32) -> f32 {
+        let rating = self.expected(after, depth);
+        match self.network {
+            Some(_) => (after.score() - game.score()) as f32 + rating,
+            None => rating,
+        }
+    }
+
+    /// Expected rating of `game` after its spawn, `depth` actions ahead
+    fn expected(&self, game: &Game, depth: u32) -> f32 {
+        if let (Some(network), 0) = (&self.network, depth) {
+            return network.value(game);
+        }
+        let outcomes = game.spawn_outcomes();
+        if outcomes.is_empty() {
+            return self.rating(game, depth);
+        }
+        (outcomes.iter())
+            .map(|(probability, next)| probability * self.rating(next, depth))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test_solver {
+    use super::*;
+    use crate::game::{SpawnRules, Variant, BOMB};
+    use crate::ntuple;
+    use crate::tablebase::Region;
+    use crate::topology::Topology;
+
+    #[test]
+    fn spawn_probabilities() {
+        let game = Game::from_seed(3);
+        let outcomes = game.spawn_outcomes();
+        assert_eq!(outcomes.len(), 14 * 2);
+        let total: f32 = outcomes.iter().map(|(probability, _)| probability).sum();
+        assert!((total - 1.).abs() < 1e-5);
+
+        let rules = SpawnRules {
+            specials: vec![(BOMB, 10)],
+            ..SpawnRules::default()
+        };
+        let game = Game::with_variant(3, Variant::default(), rules);
+        let bombs: f32 = (game.spawn_outcomes().iter())
+            .filter(|(_, next)| next.copy_grid().contains(&BOMB))
+            .map(|(probability, _)| probability)
+            .sum();
+        assert!((bombs - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn solver_plays() {
+        let mut grid = [0; 16];
+        grid[0] = 4;
+        grid[1] = 4;
+        grid[4] = 2;
+        let game = Game::with_grid(0, grid, Variant::default(), SpawnRules::default());
+        let solver = Solver::new(1);
+        // up moves nothing
+        assert_eq!(solver.evaluate(&game).len(), 3);
+        assert_eq!(solver.best_action(&game), Some(-1));
+
+        let mut game = Game::from_seed(11);
+        while let Some(action) = solver.best_action(&game) {
+            assert!(game.partial_move(action));
+            game.action(action);
+            game.random();
+        }
+        assert!(game.is_gameover());
+        assert!(game.max_tile() >= 256, "{}", game.max_tile());
+    }
+
+    #[test]
+    fn tablebase_ratings() {
+        let region = Region::square(2);
+        let tablebase = Tablebase::generate(Topology::Square, region, 16);
+        let mut grid = region.grid();
+        grid[0] = 8;
+        grid[1] = 8;
+        grid[4] = 2;
+        let variant = tablebase.start().variant();
+        let game = Game::with_grid(0, grid, variant, SpawnRules::default());
+        let solver = Solver {
+            tablebase: Some(Arc::new(tablebase)),
+            ..Solver::new(1)
+        };
+        // merging the two 8 wins at once
+        let ratings = solver.evaluate(&game);
+        assert!(ratings.contains(&(-1, 1.)));
+        assert!(ratings.contains(&(1, 1.)));
+        assert_eq!(solver.best_action(&game), Some(-1));
+
+        // other boards are searched
+        let game = Game::from_seed(11);
+        assert_eq!(solver.evaluate(&game), Solver::new(1).evaluate(&game));
+    }
+
+    #[test]
+    fn network_ratings() {
+        let mut network = Network::preset("small").unwrap();
+        for _ in 0..20 {
+            network.train(ntuple::DEFAULT_RATE);
+        }
+        let network = Arc::new(network);
+        let game = Game::from_seed(5);
+        let solver = Solver {
+            network: Some(network.clone()),
+            ..Solver::new(1)
+        };
+        // at depth 1 the solver plays like the network
+        let best = network.best_action(&game).map(|(action, _)| action);
+        assert_eq!(solver.best_action(&game), best);
+        for (action, rating) in solver.evaluate(&game) {
+            let after = game.after_action(action).unwrap();
+            let expected = (after.score() - game.score()) as f32 + network.value(&after);
+            assert_eq!(rating, expected);
+        }
+
+        let solver = Solver {
+            network: Some(network),
+            ..Solver::new(2)
+        };
+        assert!(solver.best_action(&game).is_some());
+
+        // other games are rated without the network
+        let variant = Variant {
+            topology: Topology::Hexagonal,
+            ..Variant::default()
+        };
+        let game = Game::with_variant(5, variant, SpawnRules::default());
+        assert_eq!(solver.evaluate(&game), Solver::new(2).evaluate(&game));
+    }
+}
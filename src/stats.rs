@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::mode::GameMode;
+use crate::ruleset::Ruleset;
+
+const STATISTICS_FILE: &str = "statistics.json";
+
+/// Summary of one finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+    /// Duration of the game in seconds
+    pub duration: f64,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub undos: u32,
+    #[serde(default)]
+    pub ruleset: Ruleset,
+    /// Unix time (in seconds) when the game was finished
+    pub finished_at: u64,
+    /// Final board of daily challenges, for their shared summary
+    #[serde(default)]
+    pub grid: Option<[u32; 16]>,
+}
+
+impl GameRecord {
+    pub fn won(&self) -> bool {
+        self.mode
+            .is_won(self.max_tile, self.moves, self.ruleset.win_tile())
+    }
+}
+
+/// Returns the current Unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// History of every finished game
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Statistics {
+    pub games: Vec<GameRecord>,
+}
+
+impl Statistics {
+    /// Loads statistics stored in `dir`, an empty history is returned if there is none
+    pub fn load(dir: &Path) -> Statistics {
+        fs::read_to_string(dir.join(STATISTICS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Appends a finished game to the history and saves it in `dir`
+    pub fn record(&mut self, dir: &Path, record: GameRecord) -> io::Result<()> {
+        self.games.push(record);
+        fs::create_dir_all(dir)?;
+        let content = serde_json::to_string(self)?;
+        fs::write(dir.join(STATISTICS_FILE), content)
+    }
+
+    pub fn games_played(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn wins(&self) -> usize {
+        self.games.iter().filter(|game| game.won()).count()
+    }
+
+    pub fn total_moves(&self) -> u64 {
+        self.games.iter().map(|game| game.moves as u64).sum()
+    }
+
+    /// Total time played in seconds
+    pub fn total_duration(&self) -> f64 {
+        self.games.iter().map(|game| game.duration).sum()
+    }
+
+    pub fn best_score(&self) -> u32 {
+        self.games.iter().map(|game| game.score).max().unwrap_or(0)
+    }
+
+    pub fn average_score(&self) -> f64 {
+        if self.games.is_empty() {
+            0.
+        } else {
+            self.games.iter().map(|game| game.score as f64).sum::<f64>() / self.games.len() as f64
+        }
+    }
+
+    /// Number of games for each reached max tile
+    pub fn tile_distribution(&self) -> BTreeMap<u32, usize> {
+        let mut distribution = BTreeMap::new();
+        for game in self.games.iter() {
+            *distribution.entry(game.max_tile).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// Returns the current win streak and the best win streak
+    pub fn win_streaks(&self) -> (usize, usize) {
+        let mut current = 0;
+        let mut best = 0;
+        for game in self.games.iter() {
+            if game.won() {
+                current += 1;
+                best = best.max(current);
+            } else {
+                current = 0;
+            }
+        }
+        (current, best)
+    }
+
+    /// Best games of a mode, at most `count` of them
+    ///
+    /// Games are ranked by score, except for modes with a goal where won games come first and are
+    /// ranked by moves (move limited) or by time (sprint).
+    pub fn high_scores(&self, mode: GameMode, count: usize) -> Vec<&GameRecord> {
+        let mut games: Vec<&GameRecord> =
+            self.games.iter().filter(|game| game.mode == mode).collect();
+        games.sort_by(|a, b| match mode {
+            GameMode::Classic
+            | GameMode::Daily
+            | GameMode::TimeAttack { .. }
+            | GameMode::SpecialTiles
+            | GameMode::MultiBoard { .. }
+            | GameMode::VersusRace { .. }
+            | GameMode::VersusTime { .. }
+            | GameMode::OnlineRace { .. }
+            | GameMode::Puzzle { .. } => b.score.cmp(&a.score).then(a.moves.cmp(&b.moves)),
+            GameMode::MoveLimited { .. } => b
+                .won()
+                .cmp(&a.won())
+                .then(a.moves.cmp(&b.moves))
+                .then(b.score.cmp(&a.score)),
+            GameMode::Sprint => b
+                .won()
+                .cmp(&a.won())
+                .then(a.duration.total_cmp(&b.duration))
+                .then(b.score.cmp(&a.score)),
+        });
+        games.truncate(count);
+        games
+    }
+
+    /// Attempt at the daily challenge played with `seed`, see `Date::seed`
+    pub fn daily(&self, seed: u64) -> Option<&GameRecord> {
+        self.games
+            .iter()
+            .find(|game| game.mode == GameMode::Daily && game.seed == seed)
+    }
+
+    /// Scores of games in the order they were played
+    pub fn scores(&self) -> Vec<u32> {
+        self.games.iter().map(|game| game.score).collect()
+    }
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+
+    fn record(score: u32, max_tile: u32) -> GameRecord {
+        GameRecord {
+            score,
+            max_tile,
+            moves: 10,
+            duration: 30.,
+            seed: 0,
+            mode: GameMode::Classic,
+            undos: 0,
+            ruleset: Ruleset::Classic,
+            finished_at: 0,
+            grid: None,
+        }
+    }
+
+    #[test]
+    fn summary() {
+        let statistics = Statistics {
+            games: vec![record(100, 64), record(3000, 256), record(20000, 2048)],
+        };
+        assert_eq!(statistics.games_played(), 3);
+        assert_eq!(statistics.best_score(), 20000);
+        assert_eq!(statistics.average_score(), 23100. / 3.);
+        assert_eq!(statistics.total_moves(), 30);
+        let distribution = statistics.tile_distribution();
+        assert_eq!(distribution.get(&256), Some(&1));
+        assert_eq!(distribution.get(&128), None);
+        let high_scores = statistics.high_scores(GameMode::Classic, 2);
+        assert_eq!(high_scores.len(), 2);
+        assert_eq!(high_scores[0].score, 20000);
+        assert_eq!(high_scores[1].score, 3000);
+    }
+
+    #[test]
+    fn win_streaks() {
+        let statistics = Statistics {
+            games: vec![
+                record(20000, 2048),
+                record(22000, 2048),
+                record(100, 64),
+                record(20000, 2048),
+            ],
+        };
+        assert_eq!(statistics.wins(), 3);
+        assert_eq!(statistics.win_streaks(), (1, 2));
+    }
+
+    #[test]
+    fn sprint_ranked_by_time() {
+        let mut fast = record(18000, 2048);
+        fast.mode = GameMode::Sprint;
+        fast.duration = 300.;
+        let mut slow = fast.clone();
+        slow.duration = 600.;
+        slow.score = 25000;
+        let mut lost = fast.clone();
+        lost.max_tile = 1024;
+        let statistics = Statistics {
+            games: vec![lost, slow, fast, record(30000, 2048)],
+        };
+        let high_scores = statistics.high_scores(GameMode::Sprint, 10);
+        assert_eq!(high_scores.len(), 3);
+        assert_eq!(high_scores[0].duration, 300.);
+        assert_eq!(high_scores[1].duration, 600.);
+        assert!(!high_scores[2].won());
+    }
+
+    #[test]
+    fn one_daily_attempt() {
+        let mut daily = record(5000, 512);
+        daily.mode = GameMode::Daily;
+        daily.seed = 20261019;
+        let mut classic = record(6000, 512);
+        classic.seed = 20261020;
+        let statistics = Statistics {
+            games: vec![daily, classic],
+        };
+        assert_eq!(
+            statistics.daily(20261019).map(|game| game.score),
+            Some(5000)
+        );
+        assert!(statistics.daily(20261020).is_none());
+    }
+}
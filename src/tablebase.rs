@@ -0,0 +1,631 @@
+//! Exact values of small boards, for research and for the solver
+//!
+//! A small board is a region of the 4 x 4 grid whose other cells hold stones, which never move:
+//! the top left 2 x 2 or 3 x 3 squares or any set of cells. Every state reachable from the start
+//! of a classic game with the original spawn rules is enumerated until the target tile is reached
+//! or the board is locked, and rated for optimal play: the highest probability to reach the
+//! target, then the highest expected score.
+//!
+//! A tablebase file is little-endian: the magic `T2048TB`, the version, the topology, the region
+//! (one bit per playable cell), the target tile and the number of states, then for each state
+//! sorted by key its key on `⌈cells / 2⌉` bytes, its win probability and its expected score as
+//! `f32`. The key stores the exponent of the tile of each playable cell on 4 bits, in order.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::game::{Cell, Game, SpawnRules, SpawnerKind, Variant, STONE};
+use crate::ruleset::Ruleset;
+use crate::topology::{Topology, TOPOLOGIES};
+
+const MAGIC: &[u8; 7] = b"T2048TB";
+const VERSION: u8 = 1;
+// exponent of the largest tile which fits in the 4 bits of a cell of a key
+const MAX_EXPONENT: u32 = 15;
+
+/// Playable cells of a board, the other cells of the 4 x 4 grid hold stones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region(u16);
+
+impl Region {
+    /// Top left square of `side` x `side` cells, `side` is at most 4
+    pub fn square(side: usize) -> Region {
+        let cells: Vec<usize> = (0..side * side).map(|i| i / side * 4 + i % side).collect();
+        Region::from_cells(&cells).expect("Squares fit in the grid")
+    }
+
+    /// Region made of `cells`, `None` if a cell is outside of the grid or if there is none
+    pub fn from_cells(cells: &[usize]) -> Option<Region> {
+        let mut mask = 0u16;
+        for &cell in cells {
+            mask |= 1u16.checked_shl(cell as u32).filter(|_| cell < 16)?;
+        }
+        (mask != 0).then_some(Region(mask))
+    }
+
+    /// Region called `name`: `2x2`, `3x3`, `4x4` or the list of its cells such as `0,1,4,5,8`
+    pub fn from_name(name: &str) -> Option<Region> {
+        match name.split_once('x') {
+            Some((side, other)) if side == other => {
+                let side = side.parse().ok().filter(|side| (1..=4).contains(side))?;
+                Some(Region::square(side))
+            }
+            Some(_) => None,
+            None => {
+                let cells: Result<Vec<usize>, _> = name.split(',').map(str::parse).collect();
+                Region::from_cells(&cells.ok()?)
+            }
+        }
+    }
+
+    /// Playable cells in increasing order
+    pub fn cells(self) -> impl Iterator<Item = usize> {
+        (0..16).filter(move |&cell| self.0 & (1 << cell) != 0)
+    }
+
+    /// Empty board of the region
+    pub fn grid(self) -> [u32; 16] {
+        let mut grid = [STONE; 16];
+        for cell in self.cells() {
+            grid[cell] = 0;
+        }
+        grid
+    }
+
+    /// Number of bytes of the key of a state
+    fn key_bytes(self) -> usize {
+        (self.0.count_ones() as usize).div_ceil(2)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (2..=4).find(|&side| Region::square(side) == *self) {
+            Some(side) => write!(f, "{side}x{side}"),
+            None => {
+                let cells: Vec<String> = self.cells().map(|cell| cell.to_string()).collect();
+                write!(f, "{}", cells.join(","))
+            }
+        }
+    }
+}
+
+/// Rating of a state for optimal play
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Value {
+    /// Probability to reach the target tile
+    pub win: f32,
+    /// Points expected until the target tile is reached or the board is locked
+    pub score: f32,
+}
+
+/// Values of every state reachable in a region, sorted by key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tablebase {
+    pub topology: Topology,
+    pub region: Region,
+    /// Tile ending the game once reached, a power of two
+    pub target: u32,
+    states: Vec<(u64, Value)>,
+}
+
+impl Tablebase {
+    /// Enumerates and rates every state of `region` reachable from the start of a game, until
+    /// `target` is reached
+    ///
+    /// States grow about five times each time the target doubles: 3 x 3 boards have a million
+    /// states up to 32 and five millions up to 64, solved in seconds, larger regions need a small
+    /// target or stones.
+    pub fn generate(topology: Topology, region: Region, target: u32) -> Tablebase {
+        let target = target.next_power_of_two().clamp(4, 1 << MAX_EXPONENT);
+        let mut generator = Generator::new(topology, region, target);
+        let mut start = [WALL; 16];
+        for cell in region.cells() {
+            start[cell] = 0;
+        }
+        for (_, first) in generator.spawns(&start) {
+            for (_, board) in generator.spawns(&first) {
+                generator.solve(&board);
+            }
+        }
+        let mut states: Vec<(u64, Value)> = (generator.values.into_iter())
+            .map(|(key, (win, score))| {
+                let value = Value {
+                    win: win.min(1.) as f32,
+                    score: score as f32,
+                };
+                (key, value)
+            })
+            .collect();
+        states.sort_unstable_by_key(|&(key, _)| key);
+        Tablebase {
+            topology,
+            region,
+            target,
+            states,
+        }
+    }
+
+    /// Empty board of the region, ready for the two first spawns
+    pub fn start(&self) -> Game {
+        let variant = Variant {
+            ruleset: Ruleset::Classic,
+            topology: self.topology,
+        };
+        Game::with_grid(0, self.region.grid(), variant, SpawnRules::default())
+    }
+
+    /// Number of states stored
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Expected value of a game of the region before its two first spawns
+    pub fn start_value(&self) -> Value {
+        let (mut win, mut score) = (0f64, 0f64);
+        for (first_probability, first) in self.start().spawn_outcomes() {
+            for (probability, game) in first.spawn_outcomes() {
+                let state = self.value(&game).unwrap_or_default();
+                let probability = first_probability as f64 * probability as f64;
+                win += probability * state.win as f64;
+                score += probability * state.score as f64;
+            }
+        }
+        Value {
+            win: win.min(1.) as f32,
+            score: score as f32,
+        }
+    }
+
+    /// Value of `game` if it is a state of the tablebase or a finished game of the region, `None`
+    /// if it is not covered: other rules, region or topology, or a state which cannot be reached
+    pub fn value(&self, game: &Game) -> Option<Value> {
+        let key = self.key(game)?;
+        if let Some(value) = self.terminal(game) {
+            return Some(value);
+        }
+        let index = (self.states)
+            .binary_search_by_key(&key, |&(key, _)| key)
+            .ok()?;
+        Some(self.states[index].1)
+    }
+
+    /// Value of every action of `game` which moves tiles with optimal play afterwards, in the
+    /// order of its topology, `None` if `game` is not covered
+    pub fn action_values(&self, game: &Game) -> Option<Vec<(i8, Value)>> {
+        self.value(game)?;
+        (game.topology().actions().iter())
+            .filter_map(|&action| game.after_action(action).map(|after| (action, after)))
+            .map(|(action, after)| {
+                let value = self.expected(game, &after)?;
+                Some((action, value))
+            })
+            .collect()
+    }
+
+    /// Loads the tablebase stored at `path`
+    pub fn load(path: &Path) -> io::Result<Tablebase> {
+        Tablebase::read(&mut io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Saves the tablebase at `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the tablebase in its file format
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let topology = TOPOLOGIES
+            .iter()
+            .position(|&topology| topology == self.topology);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, topology.unwrap_or(0) as u8])?;
+        writer.write_all(&self.region.0.to_le_bytes())?;
+        writer.write_all(&self.target.to_le_bytes())?;
+        writer.write_all(&(self.states.len() as u64).to_le_bytes())?;
+        let key_bytes = self.region.key_bytes();
+        for (key, value) in self.states.iter() {
+            writer.write_all(&key.to_le_bytes()[..key_bytes])?;
+            writer.write_all(&value.win.to_le_bytes())?;
+            writer.write_all(&value.score.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a tablebase written by `write`
+    pub fn read(reader: &mut impl Read) -> io::Result<Tablebase> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut header = [0; 23];
+        reader.read_exact(&mut header)?;
+        if &header[..7] != MAGIC || header[7] != VERSION {
+            return Err(invalid("Not a tablebase of this version"));
+        }
+        let topology = *(TOPOLOGIES.get(header[8] as usize)).ok_or_else(|| invalid("Topology"))?;
+        let region = Region(u16::from_le_bytes([header[9], header[10]]));
+        let target = u32::from_le_bytes(header[11..15].try_into().unwrap());
+        let count = u64::from_le_bytes(header[15..23].try_into().unwrap());
+        if region.0 == 0 || !target.is_power_of_two() {
+            return Err(invalid("Invalid header"));
+        }
+        let key_bytes = region.key_bytes();
+        let mut entry = vec![0; key_bytes + 8];
+        let mut states = Vec::new();
+        for _ in 0..count {
+            reader.read_exact(&mut entry)?;
+            let mut key = [0; 8];
+            key[..key_bytes].copy_from_slice(&entry[..key_bytes]);
+            let float = |i: usize| f32::from_le_bytes(entry[i..i + 4].try_into().unwrap());
+            let value = Value {
+                win: float(key_bytes),
+                score: float(key_bytes + 4),
+            };
+            states.push((u64::from_le_bytes(key), value));
+        }
+        if !states.is_sorted_by_key(|&(key, _)| key) {
+            return Err(invalid("States are not sorted"));
+        }
+        Ok(Tablebase {
+            topology,
+            region,
+            target,
+            states,
+        })
+    }
+
+    /// Key of `game`, `None` if it is not a classic game of the region with the original spawn
+    /// rules and tiles below the largest exponent
+    fn key(&self, game: &Game) -> Option<u64> {
+        let rules = game.rules();
+        let original = SpawnRules::original();
+        let covered = game.topology() == self.topology
+            && game.ruleset() == Ruleset::Classic
+            && rules.values == original.values
+            && rules.per_move == 1
+            && rules.spawner == SpawnerKind::Uniform
+            && rules.specials.is_empty();
+        if !covered {
+            return None;
+        }
+        let grid = game.copy_grid();
+        let mut key = 0;
+        for (cell, &value) in grid.iter().enumerate() {
+            let playable = self.region.0 & (1 << cell) != 0;
+            let exponent = match Cell::from(value) {
+                Cell::Stone if !playable => continue,
+                Cell::Empty if playable => 0,
+                Cell::Number(number) if playable && number.is_power_of_two() && number > 1 => {
+                    number.trailing_zeros()
+                }
+                _ => return None,
+            };
+            if exponent > MAX_EXPONENT {
+                return None;
+            }
+            key = key << 4 | exponent as u64;
+        }
+        Some(key)
+    }
+
+    /// Value of a finished game: won once the target is reached, lost once the board is locked
+    fn terminal(&self, game: &Game) -> Option<Value> {
+        if game.max_tile() >= self.target {
+            Some(Value { win: 1., score: 0. })
+        } else if game.is_gameover() {
+            Some(Value::default())
+        } else {
+            None
+        }
+    }
+
+    /// Value of `after`, `game` after an action, averaged over its spawns, `None` if a spawn leads
+    /// out of the tablebase
+    fn expected(&self, game: &Game, after: &Game) -> Option<Value> {
+        let points = (after.score() - game.score()) as f32;
+        // the game ends with the merge which reaches the target, before any spawn
+        if after.max_tile() >= self.target {
+            return Some(Value {
+                win: 1.,
+                score: points,
+            });
+        }
+        let (mut win, mut score) = (0f64, points as f64);
+        for (probability, next) in after.spawn_outcomes() {
+            let next = self.value(&next)?;
+            win += probability as f64 * next.win as f64;
+            score += probability as f64 * next.score as f64;
+        }
+        Some(Value {
+            win: win.min(1.) as f32,
+            score: score as f32,
+        })
+    }
+}
+
+/// Exponents of the tiles of the 4 x 4 grid, `WALL` for stones
+///
+/// Cloning a game for each of the millions of states is too slow, the generator moves tiles on
+/// boards itself following the classic ruleset.
+type Board = [u8; 16];
+
+const WALL: u8 = u8::MAX;
+
+/// Enumeration of the states of a tablebase, rated in `f64` so that sums of probabilities stay
+/// exact enough
+struct Generator {
+    cells: Vec<usize>,
+    /// Lines of each action of the topology, from the front to the back of the movement
+    lines: Vec<Vec<Vec<usize>>>,
+    /// Exponent of the target tile
+    target: u8,
+    /// Spawned exponents with their probabilities
+    spawned: Vec<(u8, f64)>,
+    values: HashMap<u64, (f64, f64)>,
+}
+
+impl Generator {
+    fn new(topology: Topology, region: Region, target: u32) -> Generator {
+        let lines = (topology.actions().iter())
+            .map(|&action| {
+                (topology.lines(action).into_iter())
+                    .map(|line| line.into_iter().rev().collect())
+                    .collect()
+            })
+            .collect();
+        let values = SpawnRules::original().values;
+        let total: u32 = values.iter().map(|&(_, weight)| weight).sum();
+        let spawned = (values.iter())
+            .map(|&(value, weight)| (value.trailing_zeros() as u8, weight as f64 / total as f64))
+            .collect();
+        Generator {
+            cells: region.cells().collect(),
+            lines,
+            target: target.trailing_zeros() as u8,
+            spawned,
+            values: HashMap::new(),
+        }
+    }
+
+    fn key(&self, board: &Board) -> u64 {
+        (self.cells.iter()).fold(0, |key, &cell| key << 4 | board[cell] as u64)
+    }
+
+    fn won(&self, board: &Board) -> bool {
+        (self.cells.iter()).any(|&cell| board[cell] >= self.target)
+    }
+
+    /// Every board following a spawn in `board` with its probability
+    fn spawns(&self, board: &Board) -> Vec<(f64, Board)> {
+        let empty: Vec<usize> = (self.cells.iter())
+            .copied()
+            .filter(|&cell| board[cell] == 0)
+            .collect();
+        let mut outcomes = Vec::new();
+        for &cell in empty.iter() {
+            for &(exponent, probability) in self.spawned.iter() {
+                let mut next = *board;
+                next[cell] = exponent;
+                outcomes.push((probability / empty.len() as f64, next));
+            }
+        }
+        outcomes
+    }
+
+    /// Board after moving along `lines` with the points scored, `None` if no tile moves
+    ///
+    /// Tiles slide to the front of their segment between stones, two equal tiles merge once,
+    /// the ones at the front first.
+    fn slide(board: &Board, lines: &[Vec<usize>]) -> Option<(Board, f64)> {
+        let mut next = *board;
+        let mut points = 0.;
+        for line in lines {
+            let mut front = 0;
+            let mut last = None;
+            for (i, &cell) in line.iter().enumerate() {
+                let exponent = board[cell];
+                if exponent == WALL {
+                    front = i + 1;
+                    last = None;
+                } else if exponent != 0 {
+                    next[cell] = 0;
+                    if last == Some(exponent) {
+                        next[line[front - 1]] = exponent + 1;
+                        points += (1u32 << (exponent + 1)) as f64;
+                        last = None;
+                    } else {
+                        next[line[front]] = exponent;
+                        last = Some(exponent);
+                        front += 1;
+                    }
+                }
+            }
+        }
+        (next != *board).then_some((next, points))
+    }
+
+    /// Rates `board` and every state reachable from it as a win probability and an expected
+    /// score, storing states which are not finished
+    fn solve(&mut self, board: &Board) -> (f64, f64) {
+        if self.won(board) {
+            return (1., 0.);
+        }
+        let key = self.key(board);
+        if let Some(&value) = self.values.get(&key) {
+            return value;
+        }
+        let mut best = None;
+        for action in 0..self.lines.len() {
+            let Some((after, points)) = Generator::slide(board, &self.lines[action]) else {
+                continue;
+            };
+            // the game ends with the merge which reaches the target, before any spawn
+            let value = if self.won(&after) {
+                (1., points)
+            } else {
+                let (mut win, mut score) = (0., points);
+                for (probability, next) in self.spawns(&after) {
+                    let (next_win, next_score) = self.solve(&next);
+                    win += probability * next_win;
+                    score += probability * next_score;
+                }
+                (win, score)
+            };
+            if best.is_none_or(|best| value > best) {
+                best = Some(value);
+            }
+        }
+        // locked boards are lost, `Tablebase::terminal` recognises them
+        let Some(best) = best else {
+            return (0., 0.);
+        };
+        self.values.insert(key, best);
+        best
+    }
+}
+
+#[cfg(test)]
+mod test_tablebase {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /// Game at the state of `key`
+    fn state(tablebase: &Tablebase, key: u64) -> Game {
+        let mut grid = tablebase.region.grid();
+        let cells: Vec<usize> = tablebase.region.cells().collect();
+        for (i, &cell) in cells.iter().rev().enumerate() {
+            let exponent = key >> (4 * i) & 0xf;
+            grid[cell] = if exponent == 0 { 0 } else { 1 << exponent };
+        }
+        let variant = tablebase.start().variant();
+        Game::with_grid(0, grid, variant, SpawnRules::default())
+    }
+
+    #[test]
+    fn regions() {
+        assert_eq!(Region::from_name("2x2"), Region::from_cells(&[0, 1, 4, 5]));
+        assert_eq!(Region::from_name("3x3").unwrap().cells().count(), 9);
+        assert_eq!(
+            Region::from_name("0,1,2,3,7").unwrap().to_string(),
+            "0,1,2,3,7"
+        );
+        assert_eq!(Region::square(3).to_string(), "3x3");
+        assert_eq!(Region::from_name("2x3"), None);
+        assert_eq!(Region::from_name("0,16"), None);
+        assert_eq!(Region::square(3).key_bytes(), 5);
+        let grid = Region::square(2).grid();
+        assert_eq!(grid[..6], [0, 0, STONE, STONE, 0, 0]);
+    }
+
+    #[test]
+    fn two_by_two() {
+        let tablebase = Tablebase::generate(Topology::Square, Region::square(2), 16);
+        assert!(!tablebase.is_empty());
+        let start = tablebase.start_value();
+        assert!(0. < start.win && start.win < 1., "{start:?}");
+
+        // two 8 on the top row merge into the target at once
+        let &(key, value) = (tablebase.states.iter())
+            .find(|&&(key, _)| key >> 8 == 0x33)
+            .unwrap();
+        let game = state(&tablebase, key);
+        assert_eq!(value.win, 1.);
+        let values = tablebase.action_values(&game).unwrap();
+        for (action, value) in values {
+            if action == -1 || action == 1 {
+                assert_eq!(value.win, 1.);
+                assert!(value.score >= 16.);
+            }
+        }
+
+        // another region or other spawn rules are not covered
+        let rules = SpawnRules::preset("hard").unwrap();
+        let hard = Game::with_grid(0, game.copy_grid(), game.variant(), rules);
+        assert_eq!(tablebase.value(&hard), None);
+        let mut outside = game.copy_grid();
+        outside[2] = 2;
+        let outside = Game::with_grid(0, outside, Variant::default(), SpawnRules::default());
+        assert_eq!(tablebase.value(&outside), None);
+
+        let mut file = Vec::new();
+        tablebase.write(&mut file).unwrap();
+        assert_eq!(file.len(), 23 + tablebase.len() * (2 + 8));
+        assert_eq!(Tablebase::read(&mut file.as_slice()).unwrap(), tablebase);
+        file[3] = b'x';
+        assert!(Tablebase::read(&mut file.as_slice()).is_err());
+    }
+
+    #[test]
+    fn optimal_play() {
+        // the value of each state is the one of its best action, up to rounding
+        let tablebase = Tablebase::generate(Topology::Square, Region::square(3), 16);
+        let start = tablebase.start_value();
+        assert!(start.win > 0.99 && start.win <= 1., "{start:?}");
+        for &(key, value) in tablebase.states.iter().step_by(211) {
+            let game = state(&tablebase, key);
+            assert_eq!(tablebase.value(&game), Some(value));
+            let values = tablebase.action_values(&game).unwrap();
+            let close = |a: f32, b: f32| (a - b).abs() <= 1e-4 * b.max(1.);
+            assert!(
+                (values.iter())
+                    .any(|(_, best)| close(best.win, value.win) && close(best.score, value.score)),
+                "{values:?} {value:?}"
+            );
+            assert!(values
+                .iter()
+                .all(|(_, other)| other.win <= value.win + 1e-4));
+        }
+    }
+
+    #[test]
+    fn boards_move_like_games() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for &topology in TOPOLOGIES.iter() {
+            let variant = Variant {
+                ruleset: Ruleset::Classic,
+                topology,
+            };
+            let generator = Generator::new(topology, Region::square(4), 1 << MAX_EXPONENT);
+            for _ in 0..200 {
+                let mut board = [0; 16];
+                for exponent in board.iter_mut() {
+                    *exponent = match rng.random_range(0..8) {
+                        0 => WALL,
+                        n => n % 4,
+                    };
+                }
+                let grid = board.map(|exponent| match exponent {
+                    WALL => STONE,
+                    0 => 0,
+                    exponent => 1 << exponent,
+                });
+                let game = Game::with_grid(0, grid, variant, SpawnRules::default());
+                for (&action, lines) in topology.actions().iter().zip(generator.lines.iter()) {
+                    let after = game.after_action(action).map(|after| {
+                        let points = (after.score() - game.score()) as f64;
+                        (after.copy_grid(), points)
+                    });
+                    let slid = Generator::slide(&board, lines).map(|(next, points)| {
+                        let grid = next.map(|exponent| match exponent {
+                            WALL => STONE,
+                            0 => 0,
+                            exponent => 1 << exponent,
+                        });
+                        (grid, points)
+                    });
+                    assert_eq!(slid, after, "{topology:?} {action} {grid:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::iter;
+
+use crate::game::ACTIONS;
+
+// number of rows and columns of the grid
+const SIDE: i8 = 4;
+
+/// Topologies which can be selected in the settings
+pub const TOPOLOGIES: [Topology; 3] = [Topology::Square, Topology::Diagonal, Topology::Hexagonal];
+
+/// Shape of the board, it decides the directions in which tiles move
+///
+/// Every topology stores its 16 cells row by row and an action is the offset from the index of a
+/// cell to the index of its neighbour: `-4` (up), `4` (down), `-1` (left), `1` (right), `-5`
+/// (up left), `-3` (up right), `3` (down left) and `5` (down right).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Topology {
+    /// Square grid with four directions
+    #[default]
+    Square,
+    /// Square grid with eight directions, diagonals included
+    Diagonal,
+    /// Hexagonal grid with six directions
+    ///
+    /// The grid is a rhombus where each row is shifted by half a cell to the right of the row
+    /// above, so `-4` is up left, `-3` up right, `3` down left and `4` down right.
+    Hexagonal,
+}
+
+/// Column and row offsets of an action
+fn offset(action: i8) -> (i8, i8) {
+    let row = (action + 1).div_euclid(SIDE);
+    (action - SIDE * row, row)
+}
+
+impl Topology {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topology::Square => "square",
+            Topology::Diagonal => "diagonal",
+            Topology::Hexagonal => "hexagonal",
+        }
+    }
+
+    /// Returns the topology called `name`
+    pub fn from_name(name: &str) -> Option<Topology> {
+        TOPOLOGIES
+            .into_iter()
+            .find(|topology| topology.name() == name)
+    }
+
+    /// Every action of the topology
+    pub fn actions(&self) -> &'static [i8] {
+        match self {
+            Topology::Square => &ACTIONS,
+            Topology::Diagonal => &[-4, 4, -1, 1, -5, -3, 3, 5],
+            Topology::Hexagonal => &[-4, 4, -1, 1, -3, 3],
+        }
+    }
+
+    /// Direction of `action` on the board, `?` if the topology does not have it
+    pub fn action_name(&self, action: i8) -> &'static str {
+        match (self, action) {
+            (_, -1) => "left",
+            (_, 1) => "right",
+            (Topology::Hexagonal, -4) => "up left",
+            (Topology::Hexagonal, -3) => "up right",
+            (Topology::Hexagonal, 3) => "down left",
+            (Topology::Hexagonal, 4) => "down right",
+            (_, -4) => "up",
+            (_, 4) => "down",
+            (Topology::Diagonal, -5) => "up left",
+            (Topology::Diagonal, -3) => "up right",
+            (Topology::Diagonal, 3) => "down left",
+            (Topology::Diagonal, 5) => "down right",
+            _ => "?",
+        }
+    }
+
+    /// Returns the cell next to `cell` in the direction of `action`, `None` if `cell` is on the
+    /// border
+    pub fn neighbour(&self, cell: usize, action: i8) -> Option<usize> {
+        let (dx, dy) = offset(action);
+        let x = (cell as i8 % SIDE) + dx;
+        let y = (cell as i8 / SIDE) + dy;
+        if (0..SIDE).contains(&x) && (0..SIDE).contains(&y) {
+            Some((y * SIDE + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Lines of cells followed by tiles during `action`, each line goes from the back to the front
+    /// of the movement
+    pub fn lines(&self, action: i8) -> Vec<Vec<usize>> {
+        if !self.actions().contains(&action) {
+            panic!("Action {action} not found in the {} topology", self.name());
+        }
+        (0..(SIDE * SIDE) as usize)
+            .filter(|&cell| self.neighbour(cell, -action).is_none())
+            .map(|start| {
+                iter::successors(Some(start), |&cell| self.neighbour(cell, action)).collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_topology {
+    use super::*;
+
+    #[test]
+    fn square_lines() {
+        assert_eq!(
+            Topology::Square.lines(1),
+            vec![[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]]
+        );
+        assert_eq!(
+            Topology::Square.lines(-4),
+            vec![[12, 8, 4, 0], [13, 9, 5, 1], [14, 10, 6, 2], [15, 11, 7, 3]]
+        );
+    }
+
+    #[test]
+    fn diagonal_and_hexagonal_lines() {
+        let lines = Topology::Diagonal.lines(5);
+        assert_eq!(lines.len(), 7);
+        assert!(lines.contains(&vec![0, 5, 10, 15]));
+        assert!(lines.contains(&vec![3]));
+        let lines = Topology::Hexagonal.lines(-3);
+        assert!(lines.contains(&vec![12, 9, 6, 3]));
+        assert!(lines.contains(&vec![0]));
+        assert_eq!(Topology::Hexagonal.neighbour(3, 3), Some(6));
+        assert_eq!(Topology::Hexagonal.neighbour(3, -3), None);
+    }
+
+    #[test]
+    fn action_names() {
+        assert_eq!(Topology::Square.action_name(-4), "up");
+        assert_eq!(Topology::Diagonal.action_name(-3), "up right");
+        assert_eq!(Topology::Hexagonal.action_name(4), "down right");
+        assert_eq!(Topology::Square.action_name(5), "?");
+        for topology in TOPOLOGIES {
+            assert!((topology.actions().iter()).all(|&action| topology.action_name(action) != "?"));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_action() {
+        Topology::Hexagonal.lines(5);
+    }
+}
@@ -0,0 +1,133 @@
+//! WebAssembly build of the engine of the game, used from JavaScript as the `Game` class
+//!
+//! Seeds are given by the caller (a `BigInt` in JavaScript) since the operating system random
+//! generator is not available. Actions are the offsets of `rust_2048::topology::Topology`:
+//! `-4` (up), `4` (down), `-1` (left), `1` (right) and the diagonals on other boards.
+use wasm_bindgen::prelude::*;
+
+use rust_2048::game::{Game, SpawnRules, Variant};
+use rust_2048::ruleset::Ruleset;
+use rust_2048::topology::Topology;
+
+/// Game following the rules of the Rust game, with undo
+#[wasm_bindgen(js_name = Game)]
+pub struct WebGame {
+    game: Game,
+    // boards before each move, for undo
+    history: Vec<Game>,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WebGame {
+    /// Creates a classic game whose spawns are fully determined by `seed`
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u64) -> WebGame {
+        WebGame::from_game(Game::from_seed(seed))
+    }
+
+    /// Creates a game with the ruleset and board named `ruleset` and `topology`, or `undefined`
+    /// if a name is unknown
+    #[wasm_bindgen(js_name = withVariant)]
+    pub fn with_variant(seed: u64, ruleset: &str, topology: &str) -> Option<WebGame> {
+        let variant = Variant {
+            ruleset: Ruleset::from_name(ruleset)?,
+            topology: Topology::from_name(topology)?,
+        };
+        let game = Game::with_variant(seed, variant, SpawnRules::default());
+        Some(WebGame::from_game(game))
+    }
+
+    /// Reads a game written in text notation, see `toString`, or `undefined` if it is invalid
+    #[wasm_bindgen(js_name = fromNotation)]
+    pub fn from_notation(notation: &str) -> Option<WebGame> {
+        notation.parse().ok().map(WebGame::from_game)
+    }
+
+    /// Game in text notation
+    #[wasm_bindgen(js_name = toString)]
+    pub fn notation(&self) -> String {
+        self.game.to_string()
+    }
+
+    /// Plays `action` then spawns tiles, returns `false` without changing the game if the action
+    /// does not move tiles
+    #[wasm_bindgen(js_name = move)]
+    pub fn play(&mut self, action: i8) -> bool {
+        if !self.can_move(action) {
+            return false;
+        }
+        self.history.push(self.game.clone());
+        self.game.action(action);
+        self.game.random();
+        true
+    }
+
+    /// Goes back to the board before the last move, returns `false` at the start of the game
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(game) => {
+                self.game = game;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks if `action` moves tiles
+    #[wasm_bindgen(js_name = canMove)]
+    pub fn can_move(&self, action: i8) -> bool {
+        self.game.topology().actions().contains(&action) && self.game.partial_move(action)
+    }
+
+    /// Every action of the board
+    pub fn actions(&self) -> Vec<i8> {
+        self.game.topology().actions().to_vec()
+    }
+
+    /// Values of the 16 cells row by row, `0` for an empty cell
+    ///
+    /// Stones, wildcards and bombs are the largest values of `u32`, see `rust_2048::game`.
+    pub fn board(&self) -> Vec<u32> {
+        self.game.copy_grid().to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn score(&self) -> u32 {
+        self.game.score()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn moves(&self) -> u32 {
+        self.game.moves()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u64 {
+        self.game.seed()
+    }
+
+    #[wasm_bindgen(getter, js_name = maxTile)]
+    pub fn max_tile(&self) -> u32 {
+        self.game.max_tile()
+    }
+
+    /// Name of the board, which tells how to draw it: `square`, `diagonal` or `hexagonal`
+    #[wasm_bindgen(getter)]
+    pub fn topology(&self) -> String {
+        self.game.topology().name().to_string()
+    }
+
+    #[wasm_bindgen(js_name = isGameOver)]
+    pub fn is_gameover(&self) -> bool {
+        self.game.is_gameover()
+    }
+}
+
+impl WebGame {
+    fn from_game(game: Game) -> WebGame {
+        WebGame {
+            game,
+            history: Vec::new(),
+        }
+    }
+}
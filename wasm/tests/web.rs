@@ -0,0 +1,49 @@
+//! Run with `wasm-pack test --node wasm` (or `--headless --firefox`)
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use game2048_wasm::WebGame;
+
+#[wasm_bindgen_test]
+fn seeded_games() {
+    let mut first = WebGame::new(42);
+    let mut second = WebGame::new(42);
+    assert_eq!(first.board(), second.board());
+    assert_eq!(first.seed(), 42);
+    assert_eq!(first.board().iter().filter(|&&value| value != 0).count(), 2);
+    for action in [-4, 1, 4, -1, 4, 1] {
+        assert_eq!(first.play(action), second.play(action));
+    }
+    assert_eq!(first.board(), second.board());
+    assert_eq!(first.score(), second.score());
+}
+
+#[wasm_bindgen_test]
+fn moves_and_undo() {
+    let mut game = WebGame::from_notation("2,2,-,-/-,-,-,-/-,-,-,-/4,-,-,# 0 0 1").unwrap();
+    let start = game.board();
+    assert!(!game.undo());
+    assert!(!game.can_move(7));
+    assert!(game.play(-1));
+    assert_eq!((game.score(), game.moves(), game.max_tile()), (4, 1, 4));
+    while game.undo() {}
+    assert_eq!(game.board(), start);
+    assert_eq!((game.score(), game.moves()), (0, 0));
+    assert_eq!(
+        WebGame::from_notation(&game.notation()).unwrap().board(),
+        start
+    );
+}
+
+#[wasm_bindgen_test]
+fn variants() {
+    let game = WebGame::with_variant(3, "fibonacci", "hexagonal").unwrap();
+    assert_eq!(game.topology(), "hexagonal");
+    assert_eq!(game.actions().len(), 6);
+    assert!(WebGame::with_variant(3, "classic", "round").is_none());
+    assert!(WebGame::from_notation("2,2").is_none());
+    let mut game = WebGame::new(1);
+    while let Some(&action) = (game.actions().iter()).find(|&&action| game.can_move(action)) {
+        game.play(action);
+    }
+    assert!(game.is_gameover());
+}